@@ -0,0 +1,27 @@
+use std::{env, net::Ipv4Addr, process::exit, time::Duration};
+
+use rbroadlink::Device;
+
+fn main() {
+    let addr: Ipv4Addr = match env::args().nth(1).map(|arg| arg.parse()) {
+        Some(Ok(addr)) => addr,
+        _ => {
+            eprintln!("Usage: custom-timeout DEVICE_IP");
+            exit(1);
+        }
+    };
+
+    // Give slow devices more time to answer, and retry the handshake a few more times
+    let device = Device::builder(addr)
+        .timeout(Duration::from_secs(20))
+        .retries(5)
+        .connect();
+
+    match device {
+        Ok(device) => println!("Connected to {}", device),
+        Err(e) => {
+            eprintln!("Could not connect to device! {}", e);
+            exit(1);
+        }
+    }
+}
@@ -1,11 +1,18 @@
 use std::fs;
 use std::net::Ipv4Addr;
 use std::path::Path;
+use std::time::Duration;
 
 use clap::{ArgEnum, Parser, Subcommand};
+use packed_struct::PackedStructSlice;
 use rpassword::read_password_from_tty;
+use zeroize::Zeroize;
 
-use rbroadlink::{network::WirelessConnection, Device};
+use rbroadlink::{
+    codes::{decode_base64, encode_base64, BroadlinkCode},
+    network::{CommandMessage, EnergyEncoding, FirmwareVersionMessage, WirelessConnection, FIRMWARE_VERSION_QUERY},
+    Device, RbroadlinkError,
+};
 
 /// Command line arguments for the CLI
 #[derive(Parser, Debug)]
@@ -26,7 +33,11 @@ enum Commands {
         /// The IP address of the broadlink device.
         device_ip: Ipv4Addr,
 
-        /// The code to send, in hex (e.g. abcdef0123456789)
+        /// The format of the code
+        #[clap(long, short, arg_enum, default_value = "hex")]
+        format: CodeFormat,
+
+        /// The code to send, in hex (e.g. abcdef0123456789) or Base64
         code: String,
     },
 
@@ -39,6 +50,10 @@ enum Commands {
         /// The IP address of the broadlink device.
         device_ip: Ipv4Addr,
 
+        /// The format of the stored code
+        #[clap(long, short, arg_enum, default_value = "hex")]
+        format: CodeFormat,
+
         /// filename where code is sored
         store_file: String,
     },
@@ -75,7 +90,11 @@ enum Commands {
 
         /// file where to sore code
         #[clap(long,short)]
-        store_file: Option<String>
+        store_file: Option<String>,
+
+        /// The format used to print and store the code
+        #[clap(long, short, arg_enum, default_value = "hex")]
+        format: CodeFormat,
     },
 
     /// Lists available broadlink devices on the network
@@ -91,6 +110,24 @@ enum Commands {
         #[clap(long, short)]
         local_ip: Option<Ipv4Addr>,
 
+        /// Also print the header of the commands sent to the device
+        #[clap(long, short)]
+        verbose: bool,
+
+        /// The IP address of the broadlink device
+        device_ip: Ipv4Addr,
+    },
+
+    /// Polls the readable state of a broadlink device and prints changes until interrupted
+    Watch {
+        /// Local IP of this machine. Use this if the broadlink device is on a different subnet.
+        #[clap(long, short)]
+        local_ip: Option<Ipv4Addr>,
+
+        /// The polling interval, in seconds
+        #[clap(long, short, default_value = "5")]
+        interval: u64,
+
         /// The IP address of the broadlink device
         device_ip: Ipv4Addr,
     },
@@ -102,6 +139,12 @@ enum LearnCodeType {
     RF,
 }
 
+#[derive(ArgEnum, Clone, Debug)]
+enum CodeFormat {
+    Hex,
+    Base64,
+}
+
 #[derive(ArgEnum, Clone, Debug)]
 enum WirelessConnectionArg {
     None,
@@ -111,7 +154,7 @@ enum WirelessConnectionArg {
     WPA,
 }
 
-fn main() -> Result<(), String> {
+fn main() -> Result<(), RbroadlinkError> {
     // Get the args
     let args = Args::parse();
 
@@ -120,13 +163,15 @@ fn main() -> Result<(), String> {
         Commands::Blast {
             local_ip,
             device_ip,
+            format,
             code,
-        } => blast(local_ip, device_ip, code),
+        } => blast(local_ip, device_ip, format, code),
         Commands::BlastFile {
             local_ip,
             device_ip,
+            format,
             store_file,
-        } => blast_file(local_ip, device_ip, store_file),
+        } => blast_file(local_ip, device_ip, format, store_file),
         Commands::Connect {
             security_mode,
             ssid,
@@ -138,40 +183,57 @@ fn main() -> Result<(), String> {
             device_ip,
             code_type,
             store_file,
-        } => learn(local_ip, device_ip, code_type, store_file),
+            format,
+        } => learn(local_ip, device_ip, code_type, store_file, format),
         Commands::List { local_ip } => list(local_ip),
         Commands::Info {
             local_ip,
+            verbose,
+            device_ip,
+        } => info(local_ip, device_ip, verbose),
+        Commands::Watch {
+            local_ip,
+            interval,
             device_ip,
-        } => info(local_ip, device_ip),
+        } => watch(local_ip, device_ip, interval),
     };
 }
 
-fn blast(local_ip: Option<Ipv4Addr>, device_ip: Ipv4Addr, code: String) -> Result<(), String> {
+fn blast(
+    local_ip: Option<Ipv4Addr>,
+    device_ip: Ipv4Addr,
+    format: CodeFormat,
+    code: String,
+) -> Result<(), RbroadlinkError> {
     // Construct a device directly
     let device = Device::from_ip(device_ip, local_ip).expect("Could not connect to device!");
-    let hex_code = hex::decode(code).expect("Invalid code!");
+    let hex_code = decode_code(&code, &format).expect("Invalid code!");
 
     // Ensure that the device is a remote
     let remote = match device {
         Device::Remote { remote } => remote,
-        _ => return Err("Device specified is not a remote!".into()),
+        _ => return Err(RbroadlinkError::Unsupported("Device specified is not a remote!".into())),
     };
 
     println!("Blasting IR/RF code: {:02X?}", hex_code);
     return remote.send_code(&hex_code);
 }
 
-fn blast_file(local_ip: Option<Ipv4Addr>, device_ip: Ipv4Addr, store_file: String) -> Result<(), String> {
+fn blast_file(
+    local_ip: Option<Ipv4Addr>,
+    device_ip: Ipv4Addr,
+    format: CodeFormat,
+    store_file: String,
+) -> Result<(), RbroadlinkError> {
     // Construct a device directly
     let device = Device::from_ip(device_ip, local_ip).expect("Could not connect to device!");
     let code = fs::read_to_string(Path::new(&store_file)).expect("Unable to find or open store file");
-    let hex_code = hex::decode(code).expect("Invalid code!");
+    let hex_code = decode_code(&code, &format).expect("Invalid code!");
 
     // Ensure that the device is a remote
     let remote = match device {
         Device::Remote { remote } => remote,
-        _ => return Err("Device specified is not a remote!".into()),
+        _ => return Err(RbroadlinkError::Unsupported("Device specified is not a remote!".into())),
     };
 
     println!("Blasting IR/RF code: {:02X?}", hex_code);
@@ -183,10 +245,10 @@ fn connect(
     ssid: String,
     password: Option<String>,
     prompt: bool,
-) -> Result<(), String> {
+) -> Result<(), RbroadlinkError> {
     // Enforce unwrapping the password if using a security mode that requires it.
     let password_prompt = Some("Wireless Password (will not show): ");
-    let unwrapped_pass = match sec_mode {
+    let mut unwrapped_pass: String = match sec_mode {
         WirelessConnectionArg::None => "".into(),
         _ => {
             if prompt {
@@ -214,6 +276,9 @@ fn connect(
         connection
     );
 
+    // Clear the password from memory now that it has been sent
+    unwrapped_pass.zeroize();
+
     return Ok(());
 }
 
@@ -222,33 +287,63 @@ fn learn(
     device_ip: Ipv4Addr,
     code_type: LearnCodeType,
     write_file_name: Option<String>,
-) -> Result<(), String> {
+    format: CodeFormat,
+) -> Result<(), RbroadlinkError> {
     println!("Attempting to learn a code of type {:?}...", code_type);
 
     // Ensure that the device is a remote
     let device = Device::from_ip(device_ip, local_ip).expect("Could not connect to device!");
     let remote = match device {
         Device::Remote { remote } => remote,
-        _ => return Err("Device specified is not a remote!".into()),
+        _ => return Err(RbroadlinkError::Unsupported("Device specified is not a remote!".into())),
     };
 
     // Try to learn the code
     let code = match code_type {
         LearnCodeType::IR => remote.learn_ir(),
-        LearnCodeType::RF => remote.learn_rf(),
+        LearnCodeType::RF => {
+            println!("Long press (and release) the RF button until the orange LED turns off...");
+            remote
+                .sweep_frequency(Duration::from_secs(30))
+                .expect("Could not find frequency!");
+
+            println!("Frequency found! Now press the RF button once more normally...");
+            remote.learn_rf_data(Duration::from_secs(30))
+        }
     }
     .expect("Could not learn code from device!");
 
-    let hex_string = hex::encode(&code);
-    println!("Got code => {}", hex_string);
+    match BroadlinkCode::parse(&code).map(|c| c.frequency_band()) {
+        Ok(Some(band)) => println!("Learned RF {} code", band),
+        Ok(None) => println!("Learned IR code"),
+        Err(e) => println!("Learned a code of unknown type: {}", e),
+    };
+
+    let code_string = encode_code(&code, &format);
+    println!("Got code => {}", code_string);
     if let Some(write_file_name) = write_file_name {
-        fs::write(Path::new(&write_file_name), hex_string).expect("Unable to store code");
+        fs::write(Path::new(&write_file_name), code_string).expect("Unable to store code");
     }
 
     return Ok(());
 }
 
-fn list(local_ip: Option<Ipv4Addr>) -> Result<(), String> {
+fn decode_code(code: &str, format: &CodeFormat) -> Result<Vec<u8>, RbroadlinkError> {
+    return match format {
+        CodeFormat::Hex => hex::decode(code.trim())
+            .map_err(|e| RbroadlinkError::Decode(format!("Invalid hex code! {}", e))),
+        CodeFormat::Base64 => decode_base64(code),
+    };
+}
+
+fn encode_code(code: &[u8], format: &CodeFormat) -> String {
+    return match format {
+        CodeFormat::Hex => hex::encode(code),
+        CodeFormat::Base64 => encode_base64(code),
+    };
+}
+
+fn list(local_ip: Option<Ipv4Addr>) -> Result<(), RbroadlinkError> {
     println!("Searching for devices...");
 
     // Get the devices
@@ -267,12 +362,139 @@ fn list(local_ip: Option<Ipv4Addr>) -> Result<(), String> {
     return Ok(());
 }
 
-fn info(local_ip: Option<Ipv4Addr>, device_ip: Ipv4Addr) -> Result<(), String> {
+fn info(local_ip: Option<Ipv4Addr>, device_ip: Ipv4Addr, verbose: bool) -> Result<(), RbroadlinkError> {
     println!("Getting information for device at {}", device_ip);
 
     // Construct a device directly
-    let device = Device::from_ip(device_ip, local_ip).expect("Could not connect to device!");
+    let mut device = Device::from_ip(device_ip, local_ip).expect("Could not connect to device!");
+
+    // Not all devices answer the firmware query, so this is best effort
+    if let Err(e) = device.query_firmware_version() {
+        println!("  Could not get firmware version: {}", e);
+    }
     println!("  {}", device);
 
+    if verbose {
+        let packet = device.pack_command::<FirmwareVersionMessage>(&FIRMWARE_VERSION_QUERY, None)?;
+        let header = CommandMessage::unpack_from_slice(&packet[0..0x38])
+            .map_err(|e| RbroadlinkError::Decode(format!("Could not unpack command header! {}", e)))?;
+        println!("  Command header: {}", header.describe());
+    }
+
     return Ok(());
 }
+
+fn watch(local_ip: Option<Ipv4Addr>, device_ip: Ipv4Addr, interval: u64) -> Result<(), RbroadlinkError> {
+    // Construct a device directly
+    let device = Device::from_ip(device_ip, local_ip).expect("Could not connect to device!");
+    println!("Watching {} (press Ctrl-C to stop)...", device);
+
+    // Only print the readings which changed since the last poll
+    let mut previous: Vec<(String, String)> = vec![];
+    loop {
+        let readings = read_state(&device)?;
+        for (name, value) in &readings {
+            let old_value = previous.iter().find(|(n, _)| n == name).map(|(_, v)| v);
+            if old_value != Some(value) {
+                println!("  {}: {}", name, value);
+            }
+        }
+
+        previous = readings;
+        std::thread::sleep(Duration::from_secs(interval));
+    }
+}
+
+fn read_state(device: &Device) -> Result<Vec<(String, String)>, RbroadlinkError> {
+    return match device {
+        Device::Hvac { hvac } => {
+            let info = hvac.get_info()?;
+            let state = hvac.get_state()?;
+
+            Ok(vec![
+                ("power".into(), info.power.to_string()),
+                ("ambient temperature".into(), format!("{:.1}", info.get_ambient_temp())),
+                ("target temperature".into(), format!("{:.1}", state.get_target_temp())),
+                ("mode".into(), format!("{:?}", state.mode)),
+                ("fan speed".into(), format!("{:?}", state.fanspeed)),
+            ])
+        }
+        Device::Remote { remote } if remote.capabilities().sensors => {
+            let reading = remote.check_sensors()?;
+
+            Ok(vec![
+                ("temperature".into(), format!("{:.1}", reading.temperature)),
+                ("humidity".into(), format!("{:.1}", reading.humidity)),
+            ])
+        }
+        Device::Plug { plug } => {
+            let mut readings = vec![("power".into(), plug.get_power()?.to_string())];
+
+            // Only metering plugs report their power draw
+            if EnergyEncoding::from_model_code(plug.info.model_code).is_some() {
+                readings.push(("power draw (W)".into(), format!("{:.2}", plug.get_energy()?)));
+            }
+
+            Ok(readings)
+        }
+        Device::PowerStrip { strip } => Ok(strip
+            .get_states()?
+            .iter()
+            .enumerate()
+            .map(|(index, on)| (format!("outlet {}", index), on.to_string()))
+            .collect()),
+        Device::Sensor { sensor } => {
+            let reading = sensor.read()?;
+
+            Ok(vec![
+                ("temperature".into(), format!("{:.1}", reading.temperature)),
+                ("humidity".into(), format!("{:.1}", reading.humidity)),
+                ("light".into(), format!("{:?}", reading.light)),
+                ("air quality".into(), format!("{:?}", reading.air_quality)),
+                ("noise".into(), format!("{:?}", reading.noise)),
+            ])
+        }
+        Device::Thermostat { thermostat } => {
+            let status = thermostat.get_status()?;
+
+            Ok(vec![
+                ("power".into(), status.power.to_string()),
+                ("heating".into(), status.active.to_string()),
+                ("mode".into(), format!("{:?}", status.mode)),
+                ("room temperature".into(), format!("{:.1}", status.current_temperature)),
+                ("target temperature".into(), format!("{:.1}", status.target_temperature)),
+            ])
+        }
+        Device::Alarm { alarm } => Ok(alarm
+            .get_sensors()?
+            .iter()
+            .map(|sensor| (sensor.name.clone(), format!("status {:#04X}", sensor.status)))
+            .collect()),
+        Device::Curtain { curtain } => Ok(vec![("position".into(), curtain.get_position()?.to_string())]),
+        Device::Bulb { bulb } => {
+            let state = bulb.get_state()?;
+
+            Ok(vec![
+                ("power".into(), state.power.to_string()),
+                ("brightness".into(), state.brightness.to_string()),
+                ("color".into(), format!("#{:02X}{:02X}{:02X}", state.red, state.green, state.blue)),
+                ("color temperature".into(), state.color_temperature.to_string()),
+            ])
+        }
+        Device::WallSwitch { switch } => {
+            let state = switch.get_state()?;
+            let mut readings: Vec<(String, String)> = state
+                .gangs
+                .iter()
+                .enumerate()
+                .map(|(index, on)| (format!("gang {}", index), on.to_string()))
+                .collect();
+            if let Some(night_light) = state.night_light {
+                readings.push(("night light".into(), night_light.to_string()));
+            }
+
+            Ok(readings)
+        }
+        _ => Err(RbroadlinkError::Unsupported("Device specified has no readable state to watch!".into())),
+    };
+}
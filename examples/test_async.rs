@@ -1,6 +1,4 @@
-use std::net::Ipv4Addr;
 use std::process::exit;
-use std::str::FromStr;
 use std::time::Duration;
 
 use rbroadlink::Device;
@@ -17,26 +15,33 @@ async fn main() {
         }
     };
 
-    let device_ip = devices.first().expect("No device found").clone().get_info().address;
-    println!("Device IP {}", device_ip);
+    let device = devices.first().expect("No device found").clone();
+    println!("Device IP {}", device.get_info().address);
 
+    // Clones share the authenticated session, so no new handshake is needed
+    let task_device = device.clone();
     tokio::spawn(async move {
         tokio::time::sleep(Duration::from_secs(1)).await;
         loop {
-            // Construct a device directly
-            match Device::from_ip(device_ip, None) {
-                Ok(device) => {
-                    println!("inside:  {}", device);
-                }
-                Err(e) => eprintln!("Error {}", e)
-            }
+            println!("inside:  {}", task_device);
 
             tokio::time::sleep(Duration::from_secs(1)).await;
         }
     });
 
-    // Construct a device directly
-    let device = Device::from_ip(device_ip, None).expect("Could not connect to device2!");
+    // Reconnecting from within a task must not block the executor
+    let address = device.get_info().address;
+    tokio::spawn(async move {
+        loop {
+            match Device::from_ip_async(address, None, Duration::from_secs(3)).await {
+                Ok(reconnected) => println!("reconnected:  {}", reconnected),
+                Err(e) => eprintln!("Could not reconnect! {}", e),
+            }
+
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    });
+
     println!("outside:  {}", device);
 
     tokio::time::sleep(Duration::from_secs(30)).await
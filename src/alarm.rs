@@ -0,0 +1,197 @@
+use std::net::Ipv4Addr;
+
+use phf::phf_map;
+
+use crate::{
+    network::{
+        AlarmDataMessage, DiscoveryResponse, ALARM_SENSORS_QUERY,
+    },
+    Device, DeviceInfo, RbroadlinkError,
+};
+
+/// A mapping of alarm kit device codes to their friendly model equivalent.
+pub const ALARM_CODES: phf::Map<u16, &'static str> = phf_map! {
+    0x2722u16 => "S1C",
+};
+
+/// The offset of the first sensor record in the response to [ALARM_SENSORS_QUERY].
+const SENSOR_RECORDS_OFFSET: usize = 0x06;
+
+/// The size of a single sensor record, in bytes.
+const SENSOR_RECORD_SIZE: usize = 83;
+
+/// The type of a sensor paired with an alarm kit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AlarmSensorKind {
+    DoorSensor,
+    MotionSensor,
+    KeyFob,
+
+    /// A sensor with a type byte which is not known to this library.
+    Unknown(u8),
+}
+
+impl AlarmSensorKind {
+    /// Get the kind of a sensor from its type byte.
+    pub fn from_type_byte(byte: u8) -> AlarmSensorKind {
+        return match byte {
+            0x31 => AlarmSensorKind::DoorSensor,
+            0x21 => AlarmSensorKind::MotionSensor,
+            0x91 => AlarmSensorKind::KeyFob,
+            x => AlarmSensorKind::Unknown(x),
+        };
+    }
+}
+
+/// The state of a sensor paired with an alarm kit, decoded from its status byte.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AlarmSensorState {
+    /// A door sensor whose contacts are together.
+    Closed,
+
+    /// A door sensor whose contacts are apart.
+    Open,
+
+    /// A motion sensor or key fob which has not been triggered.
+    Idle,
+
+    /// A motion sensor which detected movement, or a key fob which was pressed.
+    Triggered,
+
+    /// A non-idle status of a sensor with an unknown type.
+    Unknown(u8),
+}
+
+impl AlarmSensorState {
+    /// Decode the state of a sensor of the given kind from its status byte.
+    ///
+    /// Note: Only the distinction between a status of 0 (idle) and any other status is
+    /// known, so every non-zero status maps to the active state of the sensor.
+    pub fn from_status(kind: AlarmSensorKind, status: u8) -> AlarmSensorState {
+        return match (kind, status) {
+            (AlarmSensorKind::DoorSensor, 0) => AlarmSensorState::Closed,
+            (AlarmSensorKind::DoorSensor, _) => AlarmSensorState::Open,
+            (AlarmSensorKind::MotionSensor | AlarmSensorKind::KeyFob, 0) => AlarmSensorState::Idle,
+            (AlarmSensorKind::MotionSensor | AlarmSensorKind::KeyFob, _) => {
+                AlarmSensorState::Triggered
+            }
+            (AlarmSensorKind::Unknown(_), 0) => AlarmSensorState::Idle,
+            (AlarmSensorKind::Unknown(_), x) => AlarmSensorState::Unknown(x),
+        };
+    }
+}
+
+/// A sensor paired with an alarm kit.
+///
+/// Note: The sensor records sent by the kit do not include a battery level, so it is not
+/// reported here.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlarmSensor {
+    /// The name of the sensor.
+    pub name: String,
+
+    /// The type of the sensor.
+    pub kind: AlarmSensorKind,
+
+    /// The serial number of the sensor.
+    pub serial: u32,
+
+    /// The position of the sensor in the kit's list of sensors.
+    pub order: u8,
+
+    /// The state of the sensor, decoded from [AlarmSensor::status].
+    pub state: AlarmSensorState,
+
+    /// The raw status byte of the sensor.
+    ///
+    /// Note: The meaning of the status depends on the type of the sensor, and is not
+    /// documented. A status of 0 is idle (e.g. a closed door).
+    pub status: u8,
+}
+
+/// A broadlink S1C alarm kit.
+#[derive(Debug, Clone)]
+pub struct AlarmKit {
+    /// Base information about the alarm kit.
+    pub info: DeviceInfo,
+}
+
+impl AlarmKit {
+    /// Create a new AlarmKit.
+    ///
+    /// Note: This should not be called directly. Please use [Device::from_ip] or
+    /// [Device::list] instead.
+    pub fn new(name: &str, addr: Ipv4Addr, response: DiscoveryResponse) -> AlarmKit {
+        // Get the type of alarm kit
+        let friendly_model: String = ALARM_CODES
+            .get(&response.model_code)
+            .unwrap_or(&"Unknown")
+            .to_string();
+
+        return Self {
+            info: DeviceInfo::from_response(name, addr, &response, "Alarm", &friendly_model),
+        };
+    }
+
+    /// Get the sensors paired with the alarm kit, along with their status.
+    pub fn get_sensors(&self) -> Result<Vec<AlarmSensor>, RbroadlinkError> {
+        // We cast this object to a generic device in order to make use of the shared
+        // helper utilities.
+        let generic_device = Device::Alarm {
+            alarm: self.clone(),
+        };
+
+        let response = generic_device
+            .send_command::<AlarmDataMessage>(&ALARM_SENSORS_QUERY)
+            .map_err(|e| e.context("Could not get sensors from alarm kit!"))?;
+
+        return parse_alarm_sensors(&response);
+    }
+}
+
+/// Parses the response to [ALARM_SENSORS_QUERY] into a list of sensors.
+///
+/// Only the amount of records given by the count field are read, and empty slots (with a
+/// serial number of 0) are skipped.
+pub(crate) fn parse_alarm_sensors(data: &[u8]) -> Result<Vec<AlarmSensor>, RbroadlinkError> {
+    let count = usize::from(*data.get(0x04).ok_or_else(|| {
+        RbroadlinkError::Decode(format!(
+            "Alarm sensor response is too short! Expected at least 5 bytes, got {}",
+            data.len()
+        ))
+    })?);
+
+    let expected = SENSOR_RECORDS_OFFSET + count * SENSOR_RECORD_SIZE;
+    if data.len() < expected {
+        return Err(RbroadlinkError::Decode(format!(
+            "Alarm sensor response is too short for {} sensors! Expected at least {} bytes, got {}",
+            count,
+            expected,
+            data.len()
+        )));
+    }
+
+    let mut sensors = vec![];
+    for record in data[SENSOR_RECORDS_OFFSET..expected].chunks(SENSOR_RECORD_SIZE) {
+        let serial = u32::from_le_bytes([record[26], record[27], record[28], record[29]]);
+        if serial == 0 {
+            continue;
+        }
+
+        let name = String::from_utf8_lossy(&record[4..26])
+            .trim_end_matches('\0')
+            .to_string();
+
+        let kind = AlarmSensorKind::from_type_byte(record[3]);
+        sensors.push(AlarmSensor {
+            name,
+            kind,
+            serial,
+            order: record[1],
+            state: AlarmSensorState::from_status(kind, record[0]),
+            status: record[0],
+        });
+    }
+
+    return Ok(sensors);
+}
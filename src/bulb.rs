@@ -0,0 +1,143 @@
+use std::net::Ipv4Addr;
+
+use phf::phf_map;
+use serde_json::Value;
+
+use crate::{
+    network::{
+        unpack_json_payload,
+        BulbDataMessage, DiscoveryResponse,
+    },
+    Device, DeviceInfo, RbroadlinkError,
+};
+
+/// A mapping of smart bulb device codes to their friendly model equivalent.
+pub const BULB_CODES: phf::Map<u16, &'static str> = phf_map! {
+    0x60C7u16 => "LB1",
+    0x60C8u16 => "LB1",
+    0x6112u16 => "LB1",
+};
+
+/// The state of a smart bulb.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulbState {
+    /// Whether the bulb is on.
+    pub power: bool,
+
+    /// The brightness, as a percentage.
+    pub brightness: u8,
+
+    /// The red component of the RGB color.
+    pub red: u8,
+
+    /// The green component of the RGB color.
+    pub green: u8,
+
+    /// The blue component of the RGB color.
+    pub blue: u8,
+
+    /// The white color temperature, in kelvin.
+    pub color_temperature: u16,
+
+    /// The color mode of the bulb. 0 shows the RGB color, 1 shows the color temperature.
+    pub color_mode: u8,
+}
+
+/// A broadlink smart bulb.
+#[derive(Debug, Clone)]
+pub struct SmartBulb {
+    /// Base information about the bulb.
+    pub info: DeviceInfo,
+}
+
+impl SmartBulb {
+    /// Create a new SmartBulb.
+    ///
+    /// Note: This should not be called directly. Please use [Device::from_ip] or
+    /// [Device::list] instead.
+    pub fn new(name: &str, addr: Ipv4Addr, response: DiscoveryResponse) -> SmartBulb {
+        // Get the type of bulb
+        let friendly_model: String = BULB_CODES
+            .get(&response.model_code)
+            .unwrap_or(&"Unknown")
+            .to_string();
+
+        return Self {
+            info: DeviceInfo::from_response(name, addr, &response, "Bulb", &friendly_model),
+        };
+    }
+
+    /// Turn the bulb on or off.
+    pub fn set_power(&self, on: bool) -> Result<(), RbroadlinkError> {
+        self.send_command(BulbDataMessage::power(on))
+            .map_err(|e| e.context("Could not set power state of bulb!"))?;
+
+        return Ok(());
+    }
+
+    /// Set the brightness of the bulb, as a percentage.
+    pub fn set_brightness(&self, brightness: u8) -> Result<(), RbroadlinkError> {
+        self.send_command(BulbDataMessage::brightness(brightness)?)
+            .map_err(|e| e.context("Could not set brightness of bulb!"))?;
+
+        return Ok(());
+    }
+
+    /// Set the bulb to an RGB color.
+    pub fn set_color(&self, red: u8, green: u8, blue: u8) -> Result<(), RbroadlinkError> {
+        self.send_command(BulbDataMessage::color(red, green, blue))
+            .map_err(|e| e.context("Could not set color of bulb!"))?;
+
+        return Ok(());
+    }
+
+    /// Set the bulb to a white color temperature, in kelvin (between 2700 and 6500).
+    pub fn set_color_temperature(&self, kelvin: u16) -> Result<(), RbroadlinkError> {
+        self.send_command(BulbDataMessage::color_temperature(kelvin)?)
+            .map_err(|e| e.context("Could not set color temperature of bulb!"))?;
+
+        return Ok(());
+    }
+
+    /// Get the current state of the bulb.
+    pub fn get_state(&self) -> Result<BulbState, RbroadlinkError> {
+        let response = self
+            .send_command(BulbDataMessage::query())
+            .map_err(|e| e.context("Could not get state of bulb!"))?;
+
+        return parse_bulb_state(&unpack_json_payload(&response)?);
+    }
+
+    /// Sends a raw command to the bulb.
+    /// Note: Try to avoid using this method in favor of [SmartBulb::set_power], [SmartBulb::get_state], etc.
+    pub fn send_command(&self, msg: BulbDataMessage) -> Result<Vec<u8>, RbroadlinkError> {
+        // We cast this object to a generic device in order to make use of the shared
+        // helper utilities.
+        let generic_device = Device::Bulb { bulb: self.clone() };
+
+        let packed = msg.pack()?;
+
+        return generic_device
+            .send_command::<BulbDataMessage>(&packed)
+            .map_err(|e| e.context("Could not send command!"));
+    }
+}
+
+/// Parses the JSON state reported by a bulb into a [BulbState].
+pub(crate) fn parse_bulb_state(state: &Value) -> Result<BulbState, RbroadlinkError> {
+    let field = |key: &str| {
+        return state.get(key).and_then(Value::as_u64).ok_or_else(|| {
+            RbroadlinkError::Decode(format!("Bulb state is missing a valid '{}' value!", key))
+        });
+    };
+
+    return Ok(BulbState {
+        power: field("pwr")? != 0,
+        brightness: field("brightness")?.min(100) as u8,
+        red: field("red")?.min(255) as u8,
+        green: field("green")?.min(255) as u8,
+        blue: field("blue")?.min(255) as u8,
+        color_temperature: field("colortemp")?.min(u64::from(u16::MAX)) as u16,
+        color_mode: field("bulb_colormode")?.min(255) as u8,
+    });
+}
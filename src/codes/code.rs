@@ -0,0 +1,152 @@
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "serde")]
+use crate::codes::{decode_base64, encode_base64};
+use crate::{
+    codes::{decode_durations, encode_durations, IR_CODE_TYPE, RF_315_CODE_TYPE, RF_433_CODE_TYPE},
+    RbroadlinkError,
+};
+
+/// The kind of signal a code is sent as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeKind {
+    /// An infrared code.
+    Ir,
+
+    /// A 433MHz RF code.
+    Rf433,
+
+    /// A 315MHz RF code.
+    Rf315,
+}
+
+impl CodeKind {
+    /// Get the kind of a code from its leading type byte.
+    pub fn from_type_byte(byte: u8) -> Option<CodeKind> {
+        return match byte {
+            IR_CODE_TYPE => Some(CodeKind::Ir),
+            RF_433_CODE_TYPE => Some(CodeKind::Rf433),
+            RF_315_CODE_TYPE => Some(CodeKind::Rf315),
+            _ => None,
+        };
+    }
+
+    /// Get the leading type byte of codes of this kind.
+    pub fn type_byte(&self) -> u8 {
+        return match self {
+            CodeKind::Ir => IR_CODE_TYPE,
+            CodeKind::Rf433 => RF_433_CODE_TYPE,
+            CodeKind::Rf315 => RF_315_CODE_TYPE,
+        };
+    }
+}
+
+/// The frequency band of an RF code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RfBand {
+    /// The 315MHz band.
+    Rf315,
+
+    /// The 433MHz band.
+    Rf433,
+}
+
+impl RfBand {
+    /// Get the frequency of the band, in kHz, as sent to a device when learning.
+    pub fn frequency_khz(&self) -> u32 {
+        return match self {
+            RfBand::Rf315 => 315_000,
+            RfBand::Rf433 => 433_920,
+        };
+    }
+
+    /// Get the leading type byte of codes in this band.
+    pub fn type_byte(&self) -> u8 {
+        return match self {
+            RfBand::Rf315 => RF_315_CODE_TYPE,
+            RfBand::Rf433 => RF_433_CODE_TYPE,
+        };
+    }
+}
+
+impl fmt::Display for RfBand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            RfBand::Rf315 => write!(f, "315MHz"),
+            RfBand::Rf433 => write!(f, "433MHz"),
+        };
+    }
+}
+
+/// A parsed IR / RF code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BroadlinkCode {
+    /// The kind of signal this code is sent as.
+    pub kind: CodeKind,
+
+    /// The amount of times the code is repeated when sent.
+    pub repeat: u8,
+
+    /// The durations of the code in microseconds, alternating between on and off.
+    pub durations: Vec<u32>,
+}
+
+impl BroadlinkCode {
+    /// Parse a code, as learned from a device.
+    pub fn parse(code: &[u8]) -> Result<BroadlinkCode, RbroadlinkError> {
+        let type_byte = *code
+            .first()
+            .ok_or_else(|| RbroadlinkError::Decode("Could not parse code! Code is empty.".into()))?;
+        let kind = CodeKind::from_type_byte(type_byte).ok_or_else(|| {
+            RbroadlinkError::Decode(format!("Could not parse code! Unknown type {:#04X}", type_byte))
+        })?;
+        let durations = decode_durations(code).map_err(|e| e.context("Could not parse code!"))?;
+
+        return Ok(BroadlinkCode {
+            kind,
+            repeat: code[0x01],
+            durations,
+        });
+    }
+
+    /// Get the frequency band of the code, or None if it is an IR code.
+    pub fn frequency_band(&self) -> Option<RfBand> {
+        return match self.kind {
+            CodeKind::Ir => None,
+            CodeKind::Rf433 => Some(RfBand::Rf433),
+            CodeKind::Rf315 => Some(RfBand::Rf315),
+        };
+    }
+
+    /// Encode the code into the format sent to a device.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        return encode_durations(self.kind.type_byte(), self.repeat, &self.durations);
+    }
+}
+
+/// Codes are serialized as the base64 encoding of [BroadlinkCode::to_bytes].
+#[cfg(feature = "serde")]
+impl Serialize for BroadlinkCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_str(&encode_base64(&self.to_bytes()));
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for BroadlinkCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<BroadlinkCode, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        let bytes = decode_base64(&encoded).map_err(de::Error::custom)?;
+
+        return BroadlinkCode::parse(&bytes).map_err(de::Error::custom);
+    }
+}
+
+impl From<&BroadlinkCode> for Vec<u8> {
+    fn from(code: &BroadlinkCode) -> Vec<u8> {
+        return code.to_bytes();
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{codes::is_code_type, RbroadlinkError};
+
+/// The length of a single tick of a code, in microseconds.
+pub const TICK_MICROSECONDS: f64 = 32.84;
+
+/// The longest duration which can be encoded in a code, in microseconds.
+pub const MAX_DURATION_MICROSECONDS: u32 = (u16::MAX as f64 * TICK_MICROSECONDS) as u32;
+
+/// The size of the header which precedes the durations in a code.
+const CODE_HEADER_SIZE: usize = 0x04;
+
+/// Decodes the durations of a code into microseconds, alternating between on and off.
+///
+/// Durations longer than 255 ticks are stored as 0x00 followed by the duration as a big
+/// endian u16, which is expanded here.
+pub fn decode_durations(code: &[u8]) -> Result<Vec<u32>, RbroadlinkError> {
+    if code.len() < CODE_HEADER_SIZE {
+        return Err(RbroadlinkError::Decode(format!(
+            "Code is too short! Expected at least {} bytes, got {}",
+            CODE_HEADER_SIZE,
+            code.len()
+        )));
+    }
+
+    if !is_code_type(code[0]) {
+        return Err(RbroadlinkError::Decode(format!(
+            "Code has an unknown type! Got {:#04X}",
+            code[0]
+        )));
+    }
+
+    // Make sure that the payload is as long as the code says it is.
+    // Note: Learned codes are padded with zeros past the end, which are ignored.
+    let length = usize::from(u16::from_le_bytes([code[0x02], code[0x03]]));
+    let payload = &code[CODE_HEADER_SIZE..];
+    if length > payload.len() {
+        return Err(RbroadlinkError::Decode(format!(
+            "Code length field says {} but payload has {} bytes",
+            length,
+            payload.len()
+        )));
+    }
+
+    // Durations longer than a byte are escaped as 0x00 followed by a big endian u16
+    let data = &payload[..length];
+    let mut durations = vec![];
+    let mut index = 0;
+    while index < data.len() {
+        let ticks = if data[index] == 0 {
+            if index + 2 >= data.len() {
+                return Err(RbroadlinkError::Decode(format!(
+                    "Code is malformed! Escaped duration at byte {} is truncated.",
+                    index + CODE_HEADER_SIZE
+                )));
+            }
+
+            index += 3;
+            u32::from(u16::from_be_bytes([data[index - 2], data[index - 1]]))
+        } else {
+            index += 1;
+            u32::from(data[index - 1])
+        };
+
+        durations.push((f64::from(ticks) * TICK_MICROSECONDS).round() as u32);
+    }
+
+    return Ok(durations);
+}
+
+/// Encodes durations, in microseconds, into a code with the specified type and repeat count.
+/// This is the inverse of [decode_durations].
+///
+/// Note: Durations too long to be encoded are clamped to the longest possible duration.
+pub fn encode_durations(code_type: u8, repeat: u8, durations: &[u32]) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![];
+    for &duration in durations {
+        let ticks = (f64::from(duration) / TICK_MICROSECONDS).round() as u32;
+        let ticks = ticks.min(u32::from(u16::MAX)) as u16;
+
+        // Zero must also be escaped, since it marks the start of an escaped duration
+        match u8::try_from(ticks) {
+            Ok(short) if short != 0 => data.push(short),
+            _ => {
+                data.push(0x00);
+                data.extend(ticks.to_be_bytes());
+            }
+        }
+    }
+
+    let length = data.len().min(usize::from(u16::MAX)) as u16;
+    let mut code = vec![code_type, repeat];
+    code.extend(length.to_le_bytes());
+    code.extend(data);
+
+    return code;
+}
+
+/// Ensures that a duration, in microseconds, can be encoded into a code.
+///
+/// Durations must be non-zero and at most [MAX_DURATION_MICROSECONDS].
+pub fn validate_duration(duration: u32) -> Result<(), RbroadlinkError> {
+    if duration == 0 || duration > MAX_DURATION_MICROSECONDS {
+        return Err(RbroadlinkError::InvalidInput(format!(
+            "Duration is {}us, expected between 1 and {}us.",
+            duration, MAX_DURATION_MICROSECONDS
+        )));
+    }
+
+    return Ok(());
+}
+
+/// Encodes durations, in microseconds, into a code with the specified type and repeat count,
+/// failing instead of clamping if any duration cannot be encoded.
+///
+/// Durations must be non-zero and at most [MAX_DURATION_MICROSECONDS].
+pub fn try_encode_durations(code_type: u8, repeat: u8, durations: &[u32]) -> Result<Vec<u8>, RbroadlinkError> {
+    if durations.is_empty() {
+        return Err(RbroadlinkError::InvalidInput("Could not encode code! No durations given.".into()));
+    }
+
+    for (index, &duration) in durations.iter().enumerate() {
+        validate_duration(duration)
+            .map_err(|e| e.context(format!("Could not encode code! Duration {} is invalid.", index)))?;
+    }
+
+    // Each duration takes at most 3 bytes, which must fit in the length field
+    let code = encode_durations(code_type, repeat, durations);
+    if code.len() - CODE_HEADER_SIZE > usize::from(u16::MAX) {
+        return Err(RbroadlinkError::InvalidInput(format!(
+            "Could not encode code! {} durations do not fit in a single code.",
+            durations.len()
+        )));
+    }
+
+    return Ok(code);
+}
@@ -0,0 +1,15 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::RbroadlinkError;
+
+/// Encodes a code as Base64, as used by Home Assistant.
+pub fn encode_base64(code: &[u8]) -> String {
+    return STANDARD.encode(code);
+}
+
+/// Decodes a Base64 code, as used by Home Assistant.
+pub fn decode_base64(code: &str) -> Result<Vec<u8>, RbroadlinkError> {
+    return STANDARD
+        .decode(code.trim())
+        .map_err(|e| RbroadlinkError::Decode(format!("Could not decode Base64 code! {}", e)));
+}
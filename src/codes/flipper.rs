@@ -0,0 +1,108 @@
+//! Importing IR codes from Flipper Zero `.ir` files.
+//!
+//! An `.ir` file is a list of `key: value` lines, where each signal starts with a `name` key
+//! and lines starting with `#` are comments.
+
+use crate::{
+    codes::{validate_duration, BroadlinkCode, CodeKind},
+    RbroadlinkError,
+};
+
+/// A signal from a `.ir` file, as a list of its keys and values.
+struct Signal<'a> {
+    name: &'a str,
+    fields: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Signal<'a> {
+    /// Get the value of a key, or an error if it is missing.
+    fn field(&self, key: &str) -> Result<&'a str, RbroadlinkError> {
+        return self
+            .fields
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| {
+                RbroadlinkError::Decode(format!("Signal '{}' has no {}.", self.name, key))
+            });
+    }
+}
+
+/// Parses the contents of a Flipper Zero `.ir` file into a list of named codes.
+///
+/// Each signal is converted separately, so that a single bad signal does not prevent
+/// importing the rest of the file. Only a malformed file is reported as an error.
+///
+/// Note: Only raw signals are supported. Signals parsed into a protocol (NEC, RC5, etc.) are
+/// returned as an [RbroadlinkError::Unsupported] error.
+pub fn parse(content: &str) -> Result<Vec<(String, Result<BroadlinkCode, RbroadlinkError>)>, RbroadlinkError> {
+    let mut signals: Vec<Signal> = vec![];
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line.split_once(':').ok_or_else(|| {
+            RbroadlinkError::Decode(format!(
+                "Could not parse Flipper file! Invalid line '{}'.",
+                line
+            ))
+        })?;
+        let (key, value) = (key.trim(), value.trim());
+
+        // Keys before the first signal (Filetype, Version) are part of the header
+        if key == "name" {
+            signals.push(Signal {
+                name: value,
+                fields: vec![],
+            });
+        } else if let Some(signal) = signals.last_mut() {
+            signal.fields.push((key, value));
+        }
+    }
+
+    let codes = signals
+        .iter()
+        .map(|signal| (signal.name.to_string(), parse_signal(signal)))
+        .collect();
+
+    return Ok(codes);
+}
+
+/// Converts a single signal into a code.
+fn parse_signal(signal: &Signal) -> Result<BroadlinkCode, RbroadlinkError> {
+    let signal_type = signal.field("type")?;
+    if signal_type != "raw" {
+        return Err(RbroadlinkError::Unsupported(format!(
+            "Signal '{}' is of type '{}', only raw signals are supported.",
+            signal.name, signal_type
+        )));
+    }
+
+    let mut durations = vec![];
+    for value in signal.field("data")?.split_whitespace() {
+        let duration = value.parse::<u32>().map_err(|e| {
+            RbroadlinkError::Decode(format!(
+                "Signal '{}' has an invalid duration '{}'. {}",
+                signal.name, value, e
+            ))
+        })?;
+        validate_duration(duration)
+            .map_err(|e| e.context(format!("Signal '{}' has an invalid duration.", signal.name)))?;
+
+        durations.push(duration);
+    }
+
+    if durations.is_empty() {
+        return Err(RbroadlinkError::Decode(format!(
+            "Signal '{}' has no durations.",
+            signal.name
+        )));
+    }
+
+    return Ok(BroadlinkCode {
+        kind: CodeKind::Ir,
+        repeat: 0,
+        durations,
+    });
+}
@@ -0,0 +1,106 @@
+//! Conversion between IR codes and Global Caché (iTach) `sendir` commands.
+//!
+//! A `sendir` command has the form `sendir,<address>,<id>,<frequency>,<repeat>,<offset>,...`
+//! followed by on / off pairs measured in cycles of the carrier frequency.
+
+use crate::{
+    codes::{decode_durations, try_encode_durations, IR_CODE_TYPE},
+    RbroadlinkError,
+};
+
+/// The carrier frequency assumed when converting IR codes, in Hz.
+const IR_CARRIER_FREQUENCY: u32 = 38_000;
+
+/// The range of carrier frequencies accepted by Global Caché devices, in Hz.
+const FREQUENCY_RANGE: std::ops::RangeInclusive<u32> = 15_000..=500_000;
+
+/// The gap added to the end of a code with an odd number of durations, in microseconds.
+const TRAILING_GAP_MICROSECONDS: u32 = 100_000;
+
+/// Converts a Global Caché `sendir` command into an IR code.
+///
+/// The command's repeat count (the total amount of times the code is sent) becomes the
+/// code's repeat count, and the repeat offset is ignored.
+pub fn from_global_cache(command: &str) -> Result<Vec<u8>, RbroadlinkError> {
+    let fields: Vec<&str> = command.trim().split(',').map(str::trim).collect();
+    if fields.len() < 8 || !fields[0].eq_ignore_ascii_case("sendir") {
+        return Err(RbroadlinkError::Decode(
+            "Could not parse Global Caché code! Expected a sendir command with durations.".into(),
+        ));
+    }
+
+    let number = |index: usize, name: &str| {
+        return fields[index].parse::<u32>().map_err(|e| {
+            RbroadlinkError::Decode(format!(
+                "Could not parse Global Caché code! Invalid {} '{}'. {}",
+                name, fields[index], e
+            ))
+        });
+    };
+
+    let frequency = number(3, "frequency")?;
+    if !FREQUENCY_RANGE.contains(&frequency) {
+        return Err(RbroadlinkError::Decode(format!(
+            "Could not parse Global Caché code! Frequency must be between 15000 and 500000Hz, got {}.",
+            frequency
+        )));
+    }
+
+    // The command's repeat is the total amount of sends, while the code's is the extra ones
+    let repeat = number(4, "repeat count")?;
+    if repeat == 0 {
+        return Err(RbroadlinkError::Decode(
+            "Could not parse Global Caché code! Repeat count must be at least 1.".into(),
+        ));
+    }
+
+    let mut durations = vec![];
+    for index in 6..fields.len() {
+        let cycles = number(index, "duration")?;
+        let microseconds = u64::from(cycles) * 1_000_000 / u64::from(frequency);
+        durations.push(u32::try_from(microseconds).unwrap_or(u32::MAX));
+    }
+
+    let repeat = u8::try_from(repeat - 1).unwrap_or(u8::MAX);
+
+    return try_encode_durations(IR_CODE_TYPE, repeat, &durations)
+        .map_err(|e| e.context("Could not parse Global Caché code!"));
+}
+
+/// Converts an IR code into a Global Caché `sendir` command for the first connector, which
+/// sends the code `repeat` times in total.
+///
+/// A 38kHz carrier is assumed.
+pub fn to_global_cache(code: &[u8], repeat: u8) -> Result<String, RbroadlinkError> {
+    if code.first() != Some(&IR_CODE_TYPE) {
+        return Err(RbroadlinkError::InvalidInput(
+            "Could not convert code to Global Caché! Only IR codes are supported.".into(),
+        ));
+    }
+    if repeat == 0 {
+        return Err(RbroadlinkError::InvalidInput(
+            "Could not convert code to Global Caché! Repeat count must be at least 1.".into(),
+        ));
+    }
+
+    let mut durations = decode_durations(code)
+        .map_err(|e| e.context("Could not convert code to Global Caché!"))?;
+    if durations.is_empty() {
+        return Err(RbroadlinkError::Decode(
+            "Could not convert code to Global Caché! Code has no durations.".into(),
+        ));
+    }
+
+    // Global Caché codes are made of on / off pairs, so close off an unpaired burst
+    if durations.len() % 2 != 0 {
+        durations.push(TRAILING_GAP_MICROSECONDS);
+    }
+
+    let mut command = format!("sendir,1:1,1,{},{},1", IR_CARRIER_FREQUENCY, repeat);
+    for duration in durations {
+        let cycles = (f64::from(duration) * f64::from(IR_CARRIER_FREQUENCY) / 1_000_000.0).round();
+        command.push_str(&format!(",{}", cycles.max(1.0) as u32));
+    }
+
+    return Ok(command);
+}
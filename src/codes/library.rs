@@ -0,0 +1,161 @@
+#[cfg(feature = "serde")]
+use std::collections::BTreeMap;
+use std::{collections::HashMap, fs, path::Path, str::from_utf8};
+
+#[cfg(feature = "serde")]
+use crate::codes::{decode_base64, encode_base64};
+use crate::{
+    codes::{is_code_type, BroadlinkCode},
+    RbroadlinkError, RemoteDevice,
+};
+
+/// A collection of IR / RF codes, indexed by name.
+#[derive(Debug, Clone, Default)]
+pub struct CodeLibrary {
+    codes: HashMap<String, Vec<u8>>,
+}
+
+impl CodeLibrary {
+    /// Load all of the codes stored in a directory.
+    ///
+    /// Each file holds a single code, either as hex (as stored by the CLI's `learn` command)
+    /// or as raw bytes, and is indexed by its file name without the extension. Hidden files
+    /// (e.g. `.DS_Store`) are skipped, and two files with the same name but different
+    /// extensions are an error.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<CodeLibrary, RbroadlinkError> {
+        let dir = dir.as_ref();
+        let entries = fs::read_dir(dir).map_err(|e| {
+            RbroadlinkError::from(e).context(format!("Could not read code directory {}!", dir.display()))
+        })?;
+
+        let mut codes = HashMap::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|e| RbroadlinkError::from(e).context("Could not read code directory entry!"))?
+                .path();
+            if !path.is_file() {
+                continue;
+            }
+
+            // Index the code by its file name, without the extension
+            let name = match path.file_stem() {
+                Some(stem) => stem.to_string_lossy().to_string(),
+                None => continue,
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+            if codes.contains_key(&name) {
+                return Err(RbroadlinkError::InvalidInput(format!(
+                    "Could not load code directory {}! More than one file is named {}.",
+                    dir.display(),
+                    name
+                )));
+            }
+
+            let contents = fs::read(&path).map_err(|e| {
+                RbroadlinkError::from(e).context(format!("Could not read code file {}!", path.display()))
+            })?;
+
+            // Codes stored as text are hex encoded, anything else is stored as-is
+            let code = from_utf8(&contents)
+                .ok()
+                .and_then(|text| decode_hex(text.trim()))
+                .unwrap_or(contents);
+
+            codes.insert(name, code);
+        }
+
+        return Ok(CodeLibrary { codes });
+    }
+
+    /// Load a library saved with [CodeLibrary::save_to_path].
+    ///
+    /// The file is a JSON object mapping each name to its base64 encoded code.
+    #[cfg(feature = "serde")]
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<CodeLibrary, RbroadlinkError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| {
+            RbroadlinkError::from(e).context(format!("Could not read code library {}!", path.display()))
+        })?;
+        let encoded: HashMap<String, String> = serde_json::from_str(&contents)
+            .map_err(|e| RbroadlinkError::Decode(format!("Could not parse code library! {}", e)))?;
+
+        let mut codes = HashMap::new();
+        for (name, code) in encoded {
+            let code = decode_base64(&code)
+                .map_err(|e| e.context(format!("Could not decode code named {}!", name)))?;
+            codes.insert(name, code);
+        }
+
+        return Ok(CodeLibrary { codes });
+    }
+
+    /// Save the library to a file as JSON, mapping each name to its base64 encoded code.
+    #[cfg(feature = "serde")]
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> Result<(), RbroadlinkError> {
+        let path = path.as_ref();
+
+        // Sort the codes by name so that saved libraries are stable between runs
+        let encoded: BTreeMap<&String, String> = self
+            .codes
+            .iter()
+            .map(|(name, code)| (name, encode_base64(code)))
+            .collect();
+        let contents = serde_json::to_string_pretty(&encoded)
+            .map_err(|e| RbroadlinkError::Encode(format!("Could not serialize code library! {}", e)))?;
+
+        fs::write(path, contents).map_err(|e| {
+            RbroadlinkError::from(e).context(format!("Could not write code library {}!", path.display()))
+        })?;
+
+        return Ok(());
+    }
+
+    /// Add a code to the library, replacing any code with the same name.
+    pub fn insert(&mut self, name: impl Into<String>, code: &BroadlinkCode) {
+        self.codes.insert(name.into(), code.to_bytes());
+    }
+
+    /// Get a code by name.
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        return self.codes.get(name).map(|code| code.as_slice());
+    }
+
+    /// Get the names of all of the codes in the library.
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        return self.codes.keys();
+    }
+
+    /// Sends the code with the specified name using a remote.
+    pub fn blast(&self, remote: &RemoteDevice, name: &str) -> Result<(), RbroadlinkError> {
+        let code = self.get(name).ok_or_else(|| {
+            RbroadlinkError::InvalidInput(format!("Could not find code named {} in library!", name))
+        })?;
+
+        // Make sure that the stored code looks like a code before sending it
+        match code.first() {
+            Some(&code_type) if is_code_type(code_type) => {}
+            _ => {
+                return Err(RbroadlinkError::Decode(format!(
+                    "Code named {} is not a valid IR / RF code!",
+                    name
+                )))
+            }
+        }
+
+        return remote.send_code(code);
+    }
+}
+
+/// Decodes a hex string, returning None if it is not valid hex.
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if text.is_empty() || text.len() % 2 != 0 || !text.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    return (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect();
+}
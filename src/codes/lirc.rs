@@ -0,0 +1,263 @@
+//! Conversion between IR codes and LIRC configuration files.
+
+use crate::{
+    codes::{decode_durations, encode_durations, IR_CODE_TYPE},
+    RbroadlinkError,
+};
+
+/// The gap used when a LIRC remote does not specify one, in microseconds.
+const DEFAULT_GAP_MICROSECONDS: u32 = 100_000;
+
+/// The amount of durations written per line of a raw code.
+const DURATIONS_PER_LINE: usize = 6;
+
+/// Converts an IR code into a LIRC configuration containing a single raw code.
+///
+/// The name is used for both the remote and the code.
+pub fn to_lirc(name: &str, code: &[u8]) -> Result<String, RbroadlinkError> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(RbroadlinkError::InvalidInput(format!(
+            "Could not convert code to LIRC! Name '{}' must be non-empty and have no whitespace.",
+            name
+        )));
+    }
+
+    let mut durations =
+        decode_durations(code).map_err(|e| e.context("Could not convert code to LIRC!"))?;
+
+    // Raw LIRC codes end on a pulse, so the trailing space becomes the remote's gap
+    let gap = if durations.len() % 2 == 0 {
+        durations.pop().unwrap_or(DEFAULT_GAP_MICROSECONDS)
+    } else {
+        DEFAULT_GAP_MICROSECONDS
+    };
+
+    let mut conf = String::new();
+    conf.push_str("begin remote\n");
+    conf.push_str(&format!("  name  {}\n", name));
+    conf.push_str("  flags RAW_CODES\n");
+    conf.push_str("  eps   30\n");
+    conf.push_str("  aeps  100\n");
+    conf.push_str(&format!("  gap   {}\n", gap));
+    conf.push('\n');
+    conf.push_str("  begin raw_codes\n");
+    conf.push_str(&format!("    name {}\n", name));
+    for line in durations.chunks(DURATIONS_PER_LINE) {
+        let line: Vec<String> = line.iter().map(|d| d.to_string()).collect();
+        conf.push_str(&format!("      {}\n", line.join(" ")));
+    }
+    conf.push_str("  end raw_codes\n");
+    conf.push_str("end remote\n");
+
+    return Ok(conf);
+}
+
+/// Converts every code in a LIRC configuration into an IR code, paired with its name.
+///
+/// Both `raw_codes` and bit-encoded `codes` sections are supported. Bit-encoded codes are
+/// expanded into raw timings using the remote's `header`, `one`, `zero`, `ptrail`,
+/// `pre_data` and `post_data` settings.
+pub fn from_lirc(conf: &str) -> Result<Vec<(String, Vec<u8>)>, RbroadlinkError> {
+    let mut codes: Vec<(String, Vec<u8>)> = vec![];
+    let mut remote: Option<LircRemote> = None;
+    let mut section = Section::Remote;
+    let mut raw_code: Option<(String, Vec<u32>)> = None;
+
+    for (line_number, line) in conf.lines().enumerate() {
+        // Strip comments
+        let line = line.split('#').next().unwrap_or("");
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+
+        let error = |e: String| {
+            RbroadlinkError::Decode(format!(
+                "Could not parse LIRC config on line {}! {}",
+                line_number + 1,
+                e
+            ))
+        };
+        match (tokens[0], tokens.get(1).copied()) {
+            ("begin", Some("remote")) => {
+                // A new remote starts from a clean state, even if the previous block was not closed
+                if let (Some((name, durations)), Some(r)) = (raw_code.take(), &remote) {
+                    codes.push((name, r.finish(durations)));
+                }
+                remote = Some(LircRemote::default());
+                section = Section::Remote;
+            }
+            ("end", Some("remote")) => {
+                if let (Some((name, durations)), Some(r)) = (raw_code.take(), &remote) {
+                    codes.push((name, r.finish(durations)));
+                }
+                remote = None;
+                section = Section::Remote;
+            }
+            ("begin", Some("codes")) => section = Section::Codes,
+            ("begin", Some("raw_codes")) => section = Section::RawCodes,
+            ("end", Some("codes")) => section = Section::Remote,
+            ("end", Some("raw_codes")) => {
+                if let (Some((name, durations)), Some(r)) = (raw_code.take(), &remote) {
+                    codes.push((name, r.finish(durations)));
+                }
+                section = Section::Remote;
+            }
+            (key, _) => {
+                let r = remote
+                    .as_mut()
+                    .ok_or_else(|| error("Found data outside of a remote block.".into()))?;
+
+                match section {
+                    Section::Remote => r.set(key, &tokens[1..]).map_err(error)?,
+                    Section::Codes => {
+                        let value = tokens
+                            .get(1)
+                            .ok_or_else(|| error(format!("Code {} has no value.", key)))?;
+                        let value = parse_number(value).map_err(error)?;
+                        codes.push((key.to_string(), r.finish(r.expand(value))));
+                    }
+                    Section::RawCodes if key == "name" => {
+                        let name = tokens
+                            .get(1)
+                            .ok_or_else(|| error("Raw code has no name.".into()))?;
+                        if let Some((previous, durations)) = raw_code.take() {
+                            codes.push((previous, r.finish(durations)));
+                        }
+                        raw_code = Some((name.to_string(), vec![]));
+                    }
+                    Section::RawCodes => {
+                        let (_, durations) = raw_code
+                            .as_mut()
+                            .ok_or_else(|| error("Found durations before a code name.".into()))?;
+                        for token in &tokens {
+                            let duration = parse_number(token).map_err(error)?;
+                            durations.push(u32::try_from(duration).map_err(|e| error(e.to_string()))?);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Keep the last raw code, even if its section was never closed
+    if let (Some((name, durations)), Some(r)) = (raw_code.take(), &remote) {
+        codes.push((name, r.finish(durations)));
+    }
+
+    return Ok(codes);
+}
+
+/// The section of a LIRC remote block currently being parsed.
+enum Section {
+    Remote,
+    Codes,
+    RawCodes,
+}
+
+/// The timing settings of a LIRC remote, in microseconds.
+#[derive(Default)]
+struct LircRemote {
+    bits: u32,
+    header: Option<(u32, u32)>,
+    one: (u32, u32),
+    zero: (u32, u32),
+    ptrail: Option<u32>,
+    pre_data_bits: u32,
+    pre_data: u64,
+    post_data_bits: u32,
+    post_data: u64,
+    gap: Option<u32>,
+}
+
+impl LircRemote {
+    /// Sets a setting of the remote. Unknown settings are ignored.
+    fn set(&mut self, key: &str, values: &[&str]) -> Result<(), String> {
+        let numbers = || -> Result<Vec<u64>, String> {
+            return values.iter().map(|v| parse_number(v)).collect();
+        };
+        let pair = || -> Result<(u32, u32), String> {
+            return match numbers()?[..] {
+                [a, b] => Ok((to_u32(a)?, to_u32(b)?)),
+                _ => Err(format!("Expected two values for {}", key)),
+            };
+        };
+        let single = || -> Result<u64, String> {
+            return match numbers()?[..] {
+                [a] => Ok(a),
+                _ => Err(format!("Expected one value for {}", key)),
+            };
+        };
+
+        match key {
+            "bits" => self.bits = to_u32(single()?)?,
+            "header" => self.header = Some(pair()?),
+            "one" => self.one = pair()?,
+            "zero" => self.zero = pair()?,
+            "ptrail" => self.ptrail = Some(to_u32(single()?)?),
+            "pre_data_bits" => self.pre_data_bits = to_u32(single()?)?,
+            "pre_data" => self.pre_data = single()?,
+            "post_data_bits" => self.post_data_bits = to_u32(single()?)?,
+            "post_data" => self.post_data = single()?,
+            "gap" => self.gap = Some(to_u32(*numbers()?.first().unwrap_or(&0))?),
+            _ => {}
+        };
+
+        return Ok(());
+    }
+
+    /// Expands a bit-encoded code into raw timings, most significant bit first.
+    fn expand(&self, value: u64) -> Vec<u32> {
+        let mut durations = vec![];
+        if let Some((pulse, space)) = self.header {
+            durations.extend([pulse, space]);
+        }
+
+        for (data, bits) in [
+            (self.pre_data, self.pre_data_bits),
+            (value, self.bits),
+            (self.post_data, self.post_data_bits),
+        ] {
+            for bit in (0..bits.min(64)).rev() {
+                let (pulse, space) = if (data >> bit) & 1 == 1 { self.one } else { self.zero };
+                durations.extend([pulse, space]);
+            }
+        }
+
+        if let Some(pulse) = self.ptrail {
+            durations.push(pulse);
+        }
+
+        return durations;
+    }
+
+    /// Ends the timings with the remote's gap and encodes them as an IR code.
+    fn finish(&self, mut durations: Vec<u32>) -> Vec<u8> {
+        let gap = self.gap.unwrap_or(DEFAULT_GAP_MICROSECONDS);
+        if durations.len() % 2 == 0 {
+            // Codes which already end on a space just have it extended
+            if let Some(last) = durations.last_mut() {
+                *last += gap;
+            }
+        } else {
+            durations.push(gap);
+        }
+
+        return encode_durations(IR_CODE_TYPE, 0, &durations);
+    }
+}
+
+/// Parses a decimal or hex (0x prefixed) number.
+fn parse_number(value: &str) -> Result<u64, String> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+
+    return parsed.map_err(|e| format!("Invalid number '{}'. {}", value, e));
+}
+
+/// Converts a parsed number into a u32.
+fn to_u32(value: u64) -> Result<u32, String> {
+    return u32::try_from(value).map_err(|e| format!("Value {} is too large. {}", value, e));
+}
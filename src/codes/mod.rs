@@ -0,0 +1,73 @@
+//! Utilities for working with IR / RF codes.
+
+mod code;
+mod durations;
+mod encoding;
+mod library;
+mod pronto;
+
+pub mod flipper;
+pub mod gc;
+pub mod lirc;
+pub mod protocols;
+
+pub use code::*;
+pub use durations::*;
+pub use encoding::*;
+pub use library::*;
+pub use pronto::*;
+
+use crate::{network::RemoteDataCommand, RbroadlinkError};
+
+/// The size of the header which precedes the code in a learn response.
+const LEARN_RESPONSE_HEADER_SIZE: usize = 0x06;
+
+/// The leading byte of an IR code.
+pub const IR_CODE_TYPE: u8 = 0x26;
+
+/// The leading byte of a 433MHz RF code.
+pub const RF_433_CODE_TYPE: u8 = 0xB2;
+
+/// The leading byte of a 315MHz RF code.
+pub const RF_315_CODE_TYPE: u8 = 0xD7;
+
+/// Returns true if the byte is the leading byte of a known code type.
+pub fn is_code_type(byte: u8) -> bool {
+    return byte == IR_CODE_TYPE || byte == RF_433_CODE_TYPE || byte == RF_315_CODE_TYPE;
+}
+
+/// Extracts the code from a complete learn response, as returned by the device when
+/// checking for a learned code.
+///
+/// Codes which have already been extracted are returned as-is, so this is safe to call on
+/// any stored code.
+pub fn extract_from_learn_response(response: &[u8]) -> Result<Vec<u8>, RbroadlinkError> {
+    // The response header starts with the length of the payload, followed by the command
+    let has_header = response.len() > LEARN_RESPONSE_HEADER_SIZE
+        && response[0x02] == RemoteDataCommand::GetCode as u8
+        && is_code_type(response[LEARN_RESPONSE_HEADER_SIZE]);
+
+    if has_header {
+        // The length covers the rest of the header and the code, but not the length field itself
+        let length = u16::from_le_bytes([response[0x00], response[0x01]]);
+        let end = (usize::from(length) + 2).min(response.len());
+        if end <= LEARN_RESPONSE_HEADER_SIZE {
+            return Err(RbroadlinkError::Decode(
+                "Could not extract code! Learn response is empty.".into(),
+            ));
+        }
+
+        return Ok(response[LEARN_RESPONSE_HEADER_SIZE..end].to_vec());
+    }
+
+    return match response.first() {
+        Some(&code_type) if is_code_type(code_type) => Ok(response.to_vec()),
+        Some(&code_type) => Err(RbroadlinkError::Decode(format!(
+            "Could not extract code! Response is not a learned code (unknown type {:#04X}).",
+            code_type
+        ))),
+        None => Err(RbroadlinkError::Decode(
+            "Could not extract code! Response is empty.".into(),
+        )),
+    };
+}
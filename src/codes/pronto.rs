@@ -0,0 +1,104 @@
+use crate::{
+    codes::{
+        durations::{decode_durations, encode_durations},
+        IR_CODE_TYPE,
+    },
+    RbroadlinkError,
+};
+
+/// The length of a Pronto clock tick, in microseconds. The frequency word of a Pronto code
+/// is the number of clock ticks in a single carrier cycle.
+const PRONTO_CLOCK_MICROSECONDS: f64 = 0.241246;
+
+/// The carrier frequency assumed for IR codes, in Hz.
+const IR_CARRIER_FREQUENCY: f64 = 38_000.0;
+
+/// The gap added to the end of a code with an odd number of durations, in microseconds.
+const TRAILING_GAP_MICROSECONDS: u32 = 100_000;
+
+/// Converts an IR code into a learned Pronto (CCF) code.
+///
+/// All of the code's durations are placed in the once sequence, assuming a 38kHz carrier.
+pub fn to_pronto(code: &[u8]) -> Result<Vec<u16>, RbroadlinkError> {
+    let mut durations = decode_durations(code)
+        .map_err(|e| e.context("Could not convert code to Pronto!"))?;
+    if durations.is_empty() {
+        return Err(RbroadlinkError::Decode(
+            "Could not convert code to Pronto! Code has no durations.".into(),
+        ));
+    }
+
+    // Pronto codes are made of on / off pairs, so close off an unpaired burst
+    if durations.len() % 2 != 0 {
+        durations.push(TRAILING_GAP_MICROSECONDS);
+    }
+
+    let frequency_word = (1_000_000.0 / (IR_CARRIER_FREQUENCY * PRONTO_CLOCK_MICROSECONDS)).round();
+    let cycle_microseconds = frequency_word * PRONTO_CLOCK_MICROSECONDS;
+    let pairs = u16::try_from(durations.len() / 2)
+        .map_err(|e| {
+            RbroadlinkError::InvalidInput(format!(
+                "Could not convert code to Pronto! Code is too long. {}",
+                e
+            ))
+        })?;
+
+    let mut words: Vec<u16> = vec![0x0000, frequency_word as u16, pairs, 0x0000];
+    for duration in durations {
+        let cycles = (f64::from(duration) / cycle_microseconds).round();
+        words.push(cycles.min(f64::from(u16::MAX)) as u16);
+    }
+
+    return Ok(words);
+}
+
+/// Converts a learned Pronto (CCF) code into an IR code.
+///
+/// The once sequence is used if present, otherwise the repeat sequence is used.
+pub fn from_pronto(words: &[u16]) -> Result<Vec<u8>, RbroadlinkError> {
+    if words.len() < 4 {
+        return Err(RbroadlinkError::Decode(format!(
+            "Could not convert Pronto code! Expected at least 4 words, got {}",
+            words.len()
+        )));
+    }
+
+    if words[0] != 0x0000 {
+        return Err(RbroadlinkError::Decode(format!(
+            "Could not convert Pronto code! Only learned codes (0x0000) are supported, got {:#06X}",
+            words[0]
+        )));
+    }
+
+    let frequency_word = words[1];
+    if frequency_word == 0 {
+        return Err(RbroadlinkError::Decode(
+            "Could not convert Pronto code! Frequency word is zero.".into(),
+        ));
+    }
+
+    // Make sure that the burst pair counts match the amount of data
+    let once_length = usize::from(words[2]) * 2;
+    let repeat_length = usize::from(words[3]) * 2;
+    if words.len() != 4 + once_length + repeat_length {
+        return Err(RbroadlinkError::Decode(format!(
+            "Could not convert Pronto code! Burst pairs say {} words but code has {}",
+            once_length + repeat_length,
+            words.len() - 4
+        )));
+    }
+
+    let bursts = if once_length > 0 {
+        &words[4..4 + once_length]
+    } else {
+        &words[4..]
+    };
+
+    let cycle_microseconds = f64::from(frequency_word) * PRONTO_CLOCK_MICROSECONDS;
+    let durations: Vec<u32> = bursts
+        .iter()
+        .map(|&cycles| (f64::from(cycles) * cycle_microseconds).round() as u32)
+        .collect();
+
+    return Ok(encode_durations(IR_CODE_TYPE, 0, &durations));
+}
@@ -0,0 +1,173 @@
+//! Encoders for standard IR protocols, for sending codes without learning them first.
+
+use crate::{
+    codes::{BroadlinkCode, CodeKind},
+    RbroadlinkError,
+};
+
+/// The length of the NEC header burst, in microseconds.
+const NEC_HEADER_MARK: u32 = 9000;
+
+/// The length of the NEC header space, in microseconds.
+const NEC_HEADER_SPACE: u32 = 4500;
+
+/// The length of an NEC burst, and of the space of a 0 bit, in microseconds.
+const NEC_BIT_MARK: u32 = 562;
+
+/// The length of the space of an NEC 1 bit, in microseconds.
+const NEC_ONE_SPACE: u32 = 1687;
+
+/// The time between the start of two NEC frames, in microseconds.
+const NEC_FRAME_PERIOD: u32 = 108_000;
+
+/// The allowed difference between a decoded and an expected duration, as a fraction.
+const TIMING_TOLERANCE: f64 = 0.25;
+
+/// The length of half of an RC5 bit, in microseconds.
+const RC5_HALF_BIT: u32 = 889;
+
+/// The time between the start of two RC5 frames, in microseconds.
+const RC5_FRAME_PERIOD: u32 = 113_778;
+
+/// The address and command of an NEC code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NecFrame {
+    /// The address of the device. Addresses above 0xFF are extended NEC addresses.
+    pub address: u16,
+
+    /// The command sent to the device.
+    pub command: u8,
+}
+
+/// Builds an NEC code.
+///
+/// Addresses up to 0xFF are sent followed by their inverse, while larger addresses are sent
+/// as 16 bit extended NEC addresses.
+pub fn nec(address: u16, command: u8) -> BroadlinkCode {
+    let address_bytes = match u8::try_from(address) {
+        Ok(address) => [address, !address],
+        Err(_) => address.to_le_bytes(),
+    };
+
+    let mut durations = vec![NEC_HEADER_MARK, NEC_HEADER_SPACE];
+    for byte in [address_bytes[0], address_bytes[1], command, !command] {
+        // Bits are sent least significant first
+        for bit in 0..8 {
+            let space = if byte & (1 << bit) != 0 {
+                NEC_ONE_SPACE
+            } else {
+                NEC_BIT_MARK
+            };
+
+            durations.extend([NEC_BIT_MARK, space]);
+        }
+    }
+    durations.push(NEC_BIT_MARK);
+
+    let total: u32 = durations.iter().sum();
+    durations.push(NEC_FRAME_PERIOD - total);
+
+    return BroadlinkCode {
+        kind: CodeKind::Ir,
+        repeat: 0,
+        durations,
+    };
+}
+
+/// Decodes the address and command of an NEC code, or None if the code is not an NEC code.
+///
+/// Anything after the first frame (such as repeat codes) is ignored.
+pub fn decode_nec(code: &BroadlinkCode) -> Option<NecFrame> {
+    let durations = &code.durations;
+    if code.kind != CodeKind::Ir || durations.len() < 67 {
+        return None;
+    }
+    if !matches_timing(durations[0], NEC_HEADER_MARK)
+        || !matches_timing(durations[1], NEC_HEADER_SPACE)
+    {
+        return None;
+    }
+
+    let mut bytes = [0u8; 4];
+    for (index, pair) in durations[2..66].chunks(2).enumerate() {
+        if !matches_timing(pair[0], NEC_BIT_MARK) {
+            return None;
+        }
+
+        if matches_timing(pair[1], NEC_ONE_SPACE) {
+            bytes[index / 8] |= 1 << (index % 8);
+        } else if !matches_timing(pair[1], NEC_BIT_MARK) {
+            return None;
+        }
+    }
+
+    if !matches_timing(durations[66], NEC_BIT_MARK) || bytes[3] != !bytes[2] {
+        return None;
+    }
+
+    let address = if bytes[1] == !bytes[0] {
+        u16::from(bytes[0])
+    } else {
+        u16::from_le_bytes([bytes[0], bytes[1]])
+    };
+
+    return Some(NecFrame {
+        address,
+        command: bytes[2],
+    });
+}
+
+/// Builds an RC5 code.
+///
+/// Commands above 63 are sent as extended RC5, by using the inverse of the field bit as the
+/// seventh command bit. The toggle bit should be flipped on every new key press.
+pub fn rc5(address: u8, command: u8, toggle: bool) -> Result<BroadlinkCode, RbroadlinkError> {
+    if address > 0x1F || command > 0x7F {
+        return Err(RbroadlinkError::InvalidInput(format!(
+            "RC5 addresses must be at most 31 and commands at most 127, got {} and {}.",
+            address, command
+        )));
+    }
+
+    // Start bit, field bit and toggle bit, followed by the address and command
+    let mut bits = vec![true, command & 0x40 == 0, toggle];
+    bits.extend((0..5).rev().map(|bit| address & (1 << bit) != 0));
+    bits.extend((0..6).rev().map(|bit| command & (1 << bit) != 0));
+
+    // Bits are Manchester encoded, with a 1 being a space followed by a burst
+    let mut durations: Vec<u32> = vec![];
+    let mut is_mark = false;
+    for half_is_mark in bits.iter().flat_map(|&bit| [!bit, bit]) {
+        if half_is_mark == is_mark {
+            if let Some(last) = durations.last_mut() {
+                *last += RC5_HALF_BIT;
+            }
+
+            // A leading space is not part of the signal
+            continue;
+        }
+
+        durations.push(RC5_HALF_BIT);
+        is_mark = half_is_mark;
+    }
+
+    let total: u32 = durations.iter().sum();
+    if is_mark {
+        durations.push(RC5_FRAME_PERIOD - total);
+    } else if let Some(last) = durations.last_mut() {
+        *last += RC5_FRAME_PERIOD - total;
+    }
+
+    return Ok(BroadlinkCode {
+        kind: CodeKind::Ir,
+        repeat: 0,
+        durations,
+    });
+}
+
+/// Returns true if a duration is within tolerance of the expected duration.
+fn matches_timing(duration: u32, expected: u32) -> bool {
+    let difference = (f64::from(duration) - f64::from(expected)).abs();
+
+    return difference <= f64::from(expected) * TIMING_TOLERANCE;
+}
@@ -0,0 +1,153 @@
+use std::{
+    net::Ipv4Addr,
+    time::{Duration, Instant},
+};
+
+use packed_struct::PackedStruct;
+use phf::phf_map;
+
+use crate::{
+    network::{
+        CurtainDataCommand, CurtainDataMessage, DiscoveryResponse,
+    },
+    Device, DeviceInfo, RbroadlinkError,
+};
+
+/// A mapping of curtain motor device codes to their friendly model equivalent.
+pub const CURTAIN_CODES: phf::Map<u16, &'static str> = phf_map! {
+    0x4E4Du16 => "DT360E-45/20",
+};
+
+/// How often the position is polled while moving to a position.
+const POSITION_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long to wait for the curtain to reach a position before giving up.
+const POSITION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A Dooya curtain motor, bridged by broadlink.
+#[derive(Debug, Clone)]
+pub struct CurtainMotor {
+    /// Base information about the motor.
+    pub info: DeviceInfo,
+}
+
+impl CurtainMotor {
+    /// Create a new CurtainMotor.
+    ///
+    /// Note: This should not be called directly. Please use [Device::from_ip] or
+    /// [Device::list] instead.
+    pub fn new(name: &str, addr: Ipv4Addr, response: DiscoveryResponse) -> CurtainMotor {
+        // Get the type of motor
+        let friendly_model: String = CURTAIN_CODES
+            .get(&response.model_code)
+            .unwrap_or(&"Unknown")
+            .to_string();
+
+        return Self {
+            info: DeviceInfo::from_response(name, addr, &response, "Curtain", &friendly_model),
+        };
+    }
+
+    /// Start opening the curtain.
+    pub fn open(&self) -> Result<(), RbroadlinkError> {
+        self.send_command(CurtainDataCommand::Open)
+            .map_err(|e| e.context("Could not open curtain!"))?;
+
+        return Ok(());
+    }
+
+    /// Start closing the curtain.
+    pub fn close(&self) -> Result<(), RbroadlinkError> {
+        self.send_command(CurtainDataCommand::Close)
+            .map_err(|e| e.context("Could not close curtain!"))?;
+
+        return Ok(());
+    }
+
+    /// Stop moving the curtain.
+    pub fn stop(&self) -> Result<(), RbroadlinkError> {
+        self.send_command(CurtainDataCommand::Stop)
+            .map_err(|e| e.context("Could not stop curtain!"))?;
+
+        return Ok(());
+    }
+
+    /// Get the position of the curtain, as a percentage where 100 is fully open.
+    pub fn get_position(&self) -> Result<u8, RbroadlinkError> {
+        let response = self
+            .send_command(CurtainDataCommand::GetPosition)
+            .map_err(|e| e.context("Could not get position of curtain!"))?;
+
+        return CurtainDataMessage::unpack_position(&response);
+    }
+
+    /// Move the curtain to a position, as a percentage where 100 is fully open.
+    ///
+    /// Note: The motor has no command for moving to a position, so this blocks while polling
+    /// the position until it is reached, and then stops the motor.
+    pub fn set_position(&self, percent: u8) -> Result<(), RbroadlinkError> {
+        if percent > 100 {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Curtain position must be between 0 and 100, got {}.",
+                percent
+            )));
+        }
+
+        let current = self.get_position()?;
+        if current == percent {
+            return Ok(());
+        }
+
+        let opening = current < percent;
+        if opening {
+            self.open()?;
+        } else {
+            self.close()?;
+        }
+
+        // The motor must be stopped even if polling fails, or it keeps running to its end stop
+        let reached = self.wait_for_position(percent, opening);
+        let stopped = self.stop();
+        reached?;
+
+        return stopped;
+    }
+
+    /// Polls the position of the curtain until it has passed the specified position.
+    fn wait_for_position(&self, percent: u8, opening: bool) -> Result<(), RbroadlinkError> {
+        let start = Instant::now();
+        loop {
+            std::thread::sleep(POSITION_POLL_INTERVAL);
+
+            let position = self.get_position()?;
+            if (opening && position >= percent) || (!opening && position <= percent) {
+                return Ok(());
+            }
+
+            if start.elapsed() > POSITION_TIMEOUT {
+                return Err(RbroadlinkError::Timeout(POSITION_TIMEOUT)
+                    .context(format!("Curtain did not reach position {}!", percent)));
+            }
+        }
+    }
+
+    /// Sends a raw command to the motor.
+    /// Note: Try to avoid using this method in favor of [CurtainMotor::open], [CurtainMotor::close], etc.
+    pub fn send_command(&self, command: CurtainDataCommand) -> Result<Vec<u8>, RbroadlinkError> {
+        // We cast this object to a generic device in order to make use of the shared
+        // helper utilities.
+        let generic_device = Device::Curtain {
+            curtain: self.clone(),
+        };
+
+        // Construct the data message
+        let msg = CurtainDataMessage::new(command);
+        let packed = msg.pack().map_err(|e| {
+            RbroadlinkError::Encode(format!("Could not pack curtain data message! {}", e))
+        })?;
+
+        return generic_device
+            .send_command::<CurtainDataMessage>(&packed)
+            .map_err(|e| e.context("Could not send command!"));
+    }
+}
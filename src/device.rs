@@ -1,154 +1,813 @@
 use std::{
+    collections::HashSet,
     fmt,
-    net::{IpAddr, Ipv4Addr, SocketAddr},
+    future::Future,
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4},
     str::from_utf8,
+    sync::Arc,
 };
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Local};
+use log::{debug, warn};
 use packed_struct::prelude::{PackedStruct, PackedStructSlice};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+use zeroize::Zeroize;
 
 use crate::{
+    DeviceBuilder,
     DeviceInfo,
+    DeviceSnapshot,
+    DiagnosticReport,
+    DiagnosticStep,
+    SessionInfo,
+    SharedTransport,
+    RbroadlinkError,
     HVAC_CODES,
     HvacDevice, network::{
         AuthenticationMessage,
-        AuthenticationResponse, CommandMessage, DiscoveryMessage, DiscoveryResponse,
-        util::{local_ip_or, send_and_receive_many, send_and_receive_one}, WirelessConnection, WirelessConnectionMessage,
-    }, REMOTE_CODES, RemoteDevice, traits::{CommandTrait, DeviceTrait},
+        AuthenticationResponse, CommandMessage, DiscoveryMessage, DiscoveryOptions, DiscoveryResponse,
+        FirmwareVersionMessage, FIRMWARE_VERSION_QUERY, SettingsMessage,
+        util::{
+            interface_ip, local_ip_or, reverse_mac, send_and_receive_many, send_and_receive_many_from,
+            send_and_receive_one, DEFAULT_DESTINATION_PORT, DEFAULT_READ_TIMEOUT,
+        }, WirelessConnection, WirelessConnectionMessage,
+    }, A1Sensor, ALARM_CODES, AlarmKit, BULB_CODES, CURTAIN_CODES, CurtainMotor, PLUG_CODES, POWER_STRIP_CODES, PowerStrip, REMOTE_CODES, RemoteDevice, SENSOR_CODES, SmartBulb, SmartPlug, THERMOSTAT_CODES, Thermostat, WALL_SWITCH_CODES, WallSwitch, traits::{CommandTrait, DeviceTrait, TransportTrait},
 };
-use crate::network::util::{send_and_receive_many_async, send_and_receive_one_async};
+use crate::network::util::{send_and_receive_many_async_with, send_and_receive_one_async};
 
 const UDP_PORT: u16 = 42424;
 
+/// How long each discovery waits for responses while waiting for a device to join a network.
+const JOIN_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// A generic broadlink device.
+///
+/// Cloning a device is cheap and does not re-authenticate: every clone shares the same
+/// session (authentication ID and key), so multiple tasks can hold handles to the same
+/// authenticated device. Re-authenticating through any clone updates the session of all
+/// of them.
+///
+/// Note: Commands are not serialized between clones. Each command opens its own socket and
+/// waits for its own response, so concurrent commands may reach the device in any order.
+#[derive(Debug, Clone)]
 pub enum Device {
     /// A device capable of transmitting IR / RF codes.
     Remote { remote: RemoteDevice },
     /// Air Conditioner/HVAC device.
     Hvac { hvac: HvacDevice },
+
+    /// Smart plug device.
+    Plug { plug: SmartPlug },
+
+    /// Power strip device with multiple outlets.
+    PowerStrip { strip: PowerStrip },
+
+    /// Environment sensor device.
+    Sensor { sensor: A1Sensor },
+
+    /// Floor-heating thermostat device.
+    Thermostat { thermostat: Thermostat },
+
+    /// Alarm kit with paired security sensors.
+    Alarm { alarm: AlarmKit },
+
+    /// Curtain motor device.
+    Curtain { curtain: CurtainMotor },
+
+    /// Smart bulb device.
+    Bulb { bulb: SmartBulb },
+
+    /// Multi-gang wall switch device.
+    WallSwitch { switch: WallSwitch },
+
+    /// A device which this library has no specific support for.
+    ///
+    /// Note: These are only returned when explicitly requested with [Device::list_filtered].
+    Unknown { info: DeviceInfo },
+}
+
+/// The category of a broadlink device, as reported by its model code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceKind {
+    /// IR / RF remotes.
+    Remote,
+
+    /// Air conditioners.
+    Hvac,
+
+    /// Smart plugs.
+    Plug,
+
+    /// Power strips.
+    PowerStrip,
+
+    /// Environment sensors.
+    Sensor,
+
+    /// Thermostats.
+    Thermostat,
+
+    /// Alarm kits.
+    Alarm,
+
+    /// Curtain motors.
+    Curtain,
+
+    /// Smart bulbs.
+    Bulb,
+
+    /// Wall switches.
+    WallSwitch,
+
+    /// Devices with a model code that is not known to this library.
+    Unknown,
+}
+
+/// Options used when connecting to a device.
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// The total amount of attempts of the discovery and authentication exchange. Defaults to 3.
+    ///
+    /// Note: Only attempts which failed with a timeout or an I/O error are repeated.
+    pub retries: u8,
+
+    /// How long to wait after the first failed attempt. The wait is doubled after each
+    /// further failed attempt. Defaults to 500 milliseconds.
+    pub initial_backoff: Duration,
+
+    /// How long to wait for each response from the device, including responses to commands
+    /// sent after connecting. Defaults to [DEFAULT_READ_TIMEOUT].
+    pub timeout: Option<Duration>,
+
+    /// The port that messages are sent to. Defaults to [DEFAULT_DESTINATION_PORT].
+    pub destination_port: Option<u16>,
+
+    /// How long remotes wait between checks for a code when learning. Defaults to
+    /// [crate::DEFAULT_LEARN_POLL_INTERVAL].
+    pub learn_poll_interval: Option<Duration>,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> ConnectOptions {
+        return ConnectOptions {
+            retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            timeout: None,
+            destination_port: None,
+            learn_poll_interval: None,
+        };
+    }
+}
+
+impl DeviceKind {
+    /// Get the category of a device from its model code.
+    ///
+    /// Note: Codes missing from the model tables of every device (e.g. [REMOTE_CODES]) are
+    /// considered [DeviceKind::Unknown].
+    pub fn from_model_code(model_code: u16) -> DeviceKind {
+        let kinds: [(&phf::Map<u16, &'static str>, DeviceKind); 10] = [
+            (&REMOTE_CODES, DeviceKind::Remote),
+            (&HVAC_CODES, DeviceKind::Hvac),
+            (&PLUG_CODES, DeviceKind::Plug),
+            (&POWER_STRIP_CODES, DeviceKind::PowerStrip),
+            (&SENSOR_CODES, DeviceKind::Sensor),
+            (&THERMOSTAT_CODES, DeviceKind::Thermostat),
+            (&ALARM_CODES, DeviceKind::Alarm),
+            (&CURTAIN_CODES, DeviceKind::Curtain),
+            (&BULB_CODES, DeviceKind::Bulb),
+            (&WALL_SWITCH_CODES, DeviceKind::WallSwitch),
+        ];
+
+        return kinds
+            .iter()
+            .find(|(codes, _)| codes.contains_key(&model_code))
+            .map_or(DeviceKind::Unknown, |(_, kind)| *kind);
+    }
 }
 
 /// Represents a generic device. See the different implementations for more specific info.
 impl Device {
     /// Create a new device directly from an IP.
-    pub fn from_ip(addr: Ipv4Addr, local_ip: Option<Ipv4Addr>) -> Result<Device, String> {
-        // Grab the first non-loopback address
-        let selected_ip = local_ip_or(local_ip)?;
+    ///
+    /// Note: The exchange with the device is retried as described by [ConnectOptions::default].
+    pub fn from_ip(addr: Ipv4Addr, local_ip: Option<Ipv4Addr>) -> Result<Device, RbroadlinkError> {
+        return Device::from_ip_with_port(addr, local_ip, DEFAULT_DESTINATION_PORT);
+    }
+
+    /// Create a builder for connecting to a device at a specified IP with custom options.
+    pub fn builder(addr: Ipv4Addr) -> DeviceBuilder {
+        return DeviceBuilder::new(addr);
+    }
+
+    /// Create a new device directly from an IP, retrying the exchange with the device as
+    /// described by the specified options.
+    pub fn from_ip_with_options(
+        addr: Ipv4Addr,
+        local_ip: Option<Ipv4Addr>,
+        options: &ConnectOptions,
+    ) -> Result<Device, RbroadlinkError> {
+        return connect(addr, local_ip, options);
+    }
+
+    /// Create a new device directly from an IP, sending all messages to the specified port
+    /// instead of [DEFAULT_DESTINATION_PORT].
+    ///
+    /// This is useful for setups which relay broadlink traffic on a non-standard port.
+    pub fn from_ip_with_port(
+        addr: Ipv4Addr,
+        local_ip: Option<Ipv4Addr>,
+        destination_port: u16,
+    ) -> Result<Device, RbroadlinkError> {
+        let options = ConnectOptions {
+            destination_port: Some(destination_port),
+            ..Default::default()
+        };
+
+        return connect(addr, local_ip, &options);
+    }
+
+    /// Create a new device from its MAC address, by broadcasting a discovery message and
+    /// waiting at most the specified amount of time between responses. Optionally specify the
+    /// local IP if on different subnets.
+    ///
+    /// This is useful for devices whose IP is reassigned by DHCP.
+    pub fn from_mac(
+        mac: [u8; 6],
+        local_ip: Option<Ipv4Addr>,
+        timeout: Duration,
+    ) -> Result<Device, RbroadlinkError> {
+        let mut device = discover_unauthenticated(local_ip, timeout)?
+            .into_iter()
+            .find(|device| device.get_info().mac == mac)
+            .ok_or_else(|| {
+                let formatted = mac
+                    .iter()
+                    .map(|x| format!("{:02X}", x))
+                    .collect::<Vec<String>>()
+                    .join(":");
+
+                return RbroadlinkError::Timeout(timeout)
+                    .context(format!("Device with MAC {} not found!", formatted));
+            })?;
+
+        device
+            .authenticate()
+            .map_err(|e| e.context("Could not authenticate device!"))?;
+
+        return Ok(device);
+    }
+
+    /// Discovers and authenticates a device at a specified IP in a single attempt.
+    fn connect_once(
+        addr: Ipv4Addr,
+        local_ip: Option<Ipv4Addr>,
+        options: &ConnectOptions,
+    ) -> Result<Device, RbroadlinkError> {
+        let read_timeout = options.timeout.unwrap_or(DEFAULT_READ_TIMEOUT);
+        let destination_port = options.destination_port.unwrap_or(DEFAULT_DESTINATION_PORT);
 
         // Construct the discovery message
         let port = UDP_PORT;
-        let discover = DiscoveryMessage::new(selected_ip, port, None)?;
-        let msg = discover
-            .pack()
-            .map_err(|e| format!("Could not pack DiscoveryMessage! {}", e))?;
+        let msg = discovery_message(local_ip, None)?;
 
-        return Ok(
-            send_and_receive_one(&msg, addr, Some(port), |bytes_received, bytes, addr| {
-                return create_device_from_packet(addr, bytes_received, bytes);
-            })
-                .map_err(|e| format!("Could not communicate with specified device! {}", e))?,
-        );
+        let device = send_and_receive_one(
+            &msg,
+            addr,
+            Some(port),
+            None,
+            |bytes_received, bytes, addr| {
+                return device_from_packet(addr, bytes_received, bytes, false);
+            },
+            Some(read_timeout),
+            Some(destination_port),
+        )
+            .map_err(|e| e.context("Could not communicate with specified device!"))?;
+
+        // Get the auth key for this device
+        let mut device = device
+            .with_destination_port(destination_port)
+            .with_read_timeout(read_timeout);
+        device
+            .authenticate()
+            .map_err(|e| e.context("Could not authenticate device!"))?;
+
+        if let Some(interval) = options.learn_poll_interval {
+            device = device.with_learn_poll_interval(interval);
+        }
+
+        return Ok(device);
+    }
+
+    /// Send all further messages to this device to the specified port.
+    pub fn with_destination_port(mut self, destination_port: u16) -> Device {
+        self.info_mut().destination_port = destination_port;
+
+        return self;
     }
 
-    pub async fn from_ip_async(addr: Ipv4Addr, local_ip: Option<Ipv4Addr>, response_timeout: Duration) -> Result<Device, String> {
-        // Grab the first non-loopback address
-        let selected_ip = local_ip_or(local_ip)?;
+    /// Wait at most the specified amount of time for responses to further commands.
+    pub fn with_read_timeout(mut self, read_timeout: Duration) -> Device {
+        self.info_mut().read_timeout = read_timeout;
 
+        return self;
+    }
+
+    /// Wait the specified amount of time between checks for a code when learning.
+    ///
+    /// This only affects remotes, and is ignored by all other devices. See
+    /// [RemoteDevice::with_learn_poll_interval] for more info.
+    pub fn with_learn_poll_interval(self, interval: Duration) -> Device {
+        return match self {
+            Device::Remote { remote } => Device::Remote {
+                remote: remote.with_learn_poll_interval(interval),
+            },
+            device => device,
+        };
+    }
+
+    /// Send further commands over the specified transport instead of UDP.
+    ///
+    /// This is useful for testing command handling without a physical device (see
+    /// `MockTransport`, available with the `testing` feature). The transport is used by every
+    /// command, including [Device::send_command_async].
+    pub fn with_transport(mut self, transport: Arc<dyn TransportTrait + Send + Sync>) -> Device {
+        self.info_mut().transport = SharedTransport::new(transport);
+
+        return self;
+    }
+
+    /// Discover a device directly from an IP, without authenticating with it.
+    ///
+    /// Note: Commands cannot be sent to the returned device until [Device::authenticate] is called.
+    pub fn probe(addr: Ipv4Addr, local_ip: Option<Ipv4Addr>) -> Result<Device, RbroadlinkError> {
         // Construct the discovery message
         let port = UDP_PORT;
-        let discover = DiscoveryMessage::new(selected_ip, port, None)?;
-        let msg = discover
-            .pack()
-            .map_err(|e| format!("Could not pack DiscoveryMessage! {}", e))?;
+        let msg = discovery_message(local_ip, None)?;
 
-        return Ok(
-            send_and_receive_one_async(&msg, addr, port, |bytes_received, bytes, addr| {
-                return create_device_from_packet(addr, bytes_received, bytes);
-            }, response_timeout).await
-                .map_err(|e| format!("Could not communicate with specified device! {}", e))?,
-        );
+        return send_and_receive_one(&msg, addr, Some(port), None, |bytes_received, bytes, addr| {
+            return device_from_packet(addr, bytes_received, bytes, false);
+        }, None, None)
+            .map_err(|e| e.context("Could not communicate with specified device!"));
+    }
+
+    /// Discover a single device by sending the discovery message directly to its IP, waiting
+    /// at most the specified amount of time for a response. Optionally specify the local IP if
+    /// on different subnets.
+    ///
+    /// This works where broadcasts are filtered (e.g. on routed networks or guest VLANs). The
+    /// device is not authenticated, and devices with unrecognized model codes are returned as
+    /// [Device::Unknown]. Returns None if the device did not respond in time.
+    pub fn discover_unicast(
+        addr: Ipv4Addr,
+        local_ip: Option<Ipv4Addr>,
+        timeout: Duration,
+    ) -> Result<Option<Device>, RbroadlinkError> {
+        let options = DiscoveryOptions {
+            timeout: Some(timeout),
+            ..Default::default()
+        };
+
+        return Device::discover_unicast_with_options(addr, local_ip, &options);
+    }
+
+    /// Discover a single device by sending the discovery message directly to its IP, using the
+    /// specified discovery options. Optionally specify the local IP if on different subnets.
+    ///
+    /// See [Device::discover_unicast] for more info.
+    ///
+    /// Note: The message is sent directly to the device, so [DiscoveryOptions::bind_addr] and
+    /// [DiscoveryOptions::broadcast_addr] are ignored.
+    pub fn discover_unicast_with_options(
+        addr: Ipv4Addr,
+        local_ip: Option<Ipv4Addr>,
+        options: &DiscoveryOptions,
+    ) -> Result<Option<Device>, RbroadlinkError> {
+        // Construct the discovery message
+        let port = UDP_PORT;
+        let msg = discovery_message(local_ip, Some(options.reported_time()))?;
+
+        let result = send_and_receive_one(&msg, addr, Some(port), None, |bytes_received, bytes, addr| {
+            return device_from_packet(addr, bytes_received, bytes, true);
+        }, options.timeout, None);
+
+        return match result {
+            Ok(device) => Ok(Some(device)),
+            Err(e) if matches!(e.root_cause(), RbroadlinkError::Timeout(_)) => Ok(None),
+            Err(e) => Err(e.context("Could not communicate with specified device!")),
+        };
+    }
+
+    /// Diagnose communication with a device at a specified IP.
+    ///
+    /// This attempts to discover the device, authenticate with it and then query its status,
+    /// recording which of the steps succeeded and how long each took.
+    pub fn diagnose(addr: Ipv4Addr, local_ip: Option<Ipv4Addr>) -> DiagnosticReport {
+        // Check that the device responds at all
+        let start = Instant::now();
+        let probe = Device::probe(addr, local_ip);
+        let mut report = DiagnosticReport {
+            discovery: DiagnosticStep::new(&probe, start.elapsed()),
+            authentication: None,
+            status_query: None,
+        };
+
+        let mut device = match probe {
+            Ok(device) => device,
+            Err(_) => return report,
+        };
+
+        // Check that the device accepts the handshake
+        let start = Instant::now();
+        let auth = device.authenticate();
+        let auth_step = DiagnosticStep::new(&auth, start.elapsed());
+        report.authentication = Some(auth_step);
+        if auth.is_err() {
+            return report;
+        }
+
+        // Check that the device answers authenticated commands
+        let start = Instant::now();
+        let status = device.query_status();
+        report.status_query = Some(DiagnosticStep::new(&status, start.elapsed()));
+
+        return report;
+    }
+
+    /// Create a new device directly from an IP, without blocking.
+    ///
+    /// The timeout applies to the discovery and authentication exchanges separately.
+    ///
+    /// Note: The exchange with the device is retried as described by [ConnectOptions::default].
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "from_ip",
+        skip_all,
+        fields(ip = %addr, attempts = tracing::field::Empty)
+    ))]
+    pub async fn from_ip_async(addr: Ipv4Addr, local_ip: Option<Ipv4Addr>, response_timeout: Duration) -> Result<Device, RbroadlinkError> {
+        let options = ConnectOptions::default();
+
+        return retry_with_backoff_async(&options, || Device::connect_once_async(addr, local_ip, response_timeout)).await;
+    }
+
+    /// Discovers and authenticates a device at a specified IP in a single attempt, without blocking.
+    async fn connect_once_async(
+        addr: Ipv4Addr,
+        local_ip: Option<Ipv4Addr>,
+        response_timeout: Duration,
+    ) -> Result<Device, RbroadlinkError> {
+        // Construct the discovery message
+        let port = UDP_PORT;
+        let msg = discovery_message(local_ip, None)?;
+
+        let mut device = send_and_receive_one_async(&msg, addr, port, None, |bytes_received, bytes, addr| {
+            return device_from_packet(addr, bytes_received, bytes, false);
+        }, response_timeout, None).await
+            .map_err(|e| e.context("Could not communicate with specified device!"))?;
+
+        // Get the auth key for this device
+        device
+            .authenticate_async(response_timeout)
+            .await
+            .map_err(|e| e.context("Could not authenticate device!"))?;
+
+        return Ok(device);
     }
 
     /// List all devices in the current network. Optionally specify the local IP if on different subnets.
-    pub fn list(ip: Option<Ipv4Addr>) -> Result<Vec<Device>, String> {
-        // Grab the first non-loopback address
-        let selected_ip = local_ip_or(ip)?;
+    ///
+    /// Note: Devices are returned sorted by IP address (and then by MAC address), not in the
+    /// order in which they responded.
+    pub fn list(ip: Option<Ipv4Addr>) -> Result<Vec<Device>, RbroadlinkError> {
+        return Device::list_with_options(ip, &DiscoveryOptions::default());
+    }
 
+    /// List all devices in the current network, waiting at most the specified amount of time
+    /// between responses. Optionally specify the local IP if on different subnets.
+    ///
+    /// Note: Devices are returned sorted by IP address (and then by MAC address), not in the
+    /// order in which they responded.
+    pub fn list_with_timeout(ip: Option<Ipv4Addr>, timeout: Duration) -> Result<Vec<Device>, RbroadlinkError> {
+        let options = DiscoveryOptions {
+            timeout: Some(timeout),
+            ..Default::default()
+        };
+
+        return Device::list_with_options(ip, &options);
+    }
+
+    /// List all devices in the current network using the specified discovery options.
+    /// Optionally specify the local IP if on different subnets.
+    ///
+    /// Devices which cannot be authenticated are skipped, so that a single misbehaving
+    /// device does not fail the whole discovery.
+    ///
+    /// Note: Devices are returned sorted by IP address (and then by MAC address), not in the
+    /// order in which they responded.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "list", skip_all, fields(local_ip = ?ip)))]
+    pub fn list_with_options(
+        ip: Option<Ipv4Addr>,
+        options: &DiscoveryOptions,
+    ) -> Result<Vec<Device>, RbroadlinkError> {
         // Construct the discovery message
         let port = UDP_PORT;
-        let discover = DiscoveryMessage::new(selected_ip, port, None)?;
-        let msg = discover
-            .pack()
-            .map_err(|e| format!("Could not pack DiscoveryMessage! {}", e))?;
+        let msg = discovery_message(ip, Some(options.reported_time()))?;
 
-        let results = send_and_receive_many(
+        let bind_ip = options.bind_addr.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        let bind_addr = SocketAddr::from((bind_ip, port));
+        let results = send_and_receive_many_from(
             &msg,
-            Ipv4Addr::BROADCAST,
-            Some(port),
+            bind_addr,
+            options.broadcast_addr.unwrap_or(Ipv4Addr::BROADCAST),
+            None,
+            |bytes_received, bytes, addr| {
+                return Ok(create_device_from_packet(addr, bytes_received, &bytes));
+            },
+            options.timeout,
+            None,
+        )
+            .map_err(|e| e.context("Could not send discovery message!"))?;
+        let mut results: Vec<Device> = results.into_iter().flatten().collect();
+
+        // Remove duplicates
+        dedup_devices(&mut results);
+
+        if let Some(interval) = options.learn_poll_interval {
+            results = results.into_iter().map(|d| d.with_learn_poll_interval(interval)).collect();
+        }
+
+        // Sort the results so that discovery order is stable between runs
+        sort_devices(&mut results);
+
+        return Ok(results);
+    }
+
+    /// List all devices in the subnet of the specified directed broadcast address (e.g.
+    /// 192.168.2.255), waiting at most the specified amount of time between responses.
+    /// Optionally specify the local IP if on different subnets.
+    ///
+    /// This is useful for reaching devices on routed networks or VLANs which are not on the
+    /// same subnet as the host.
+    ///
+    /// Note: Devices are returned sorted by IP address (and then by MAC address), not in the
+    /// order in which they responded.
+    pub fn list_to_broadcast(
+        broadcast_addr: Ipv4Addr,
+        local_ip: Option<Ipv4Addr>,
+        timeout: Duration,
+    ) -> Result<Vec<Device>, RbroadlinkError> {
+        let options = DiscoveryOptions {
+            timeout: Some(timeout),
+            broadcast_addr: Some(broadcast_addr),
+            ..Default::default()
+        };
+
+        return Device::list_with_options(local_ip, &options);
+    }
+
+    /// List all devices reachable from the network interface with the specified name (e.g.
+    /// `eth0`), waiting at most the specified amount of time between responses.
+    ///
+    /// The discovery socket is bound to the address of the interface, which is useful on
+    /// machines with multiple network interfaces.
+    ///
+    /// Note: Devices are returned sorted by IP address (and then by MAC address), not in the
+    /// order in which they responded.
+    pub fn list_on_interface(iface_name: &str, timeout: Duration) -> Result<Vec<Device>, RbroadlinkError> {
+        let ip = interface_ip(iface_name)?;
+        let options = DiscoveryOptions {
+            timeout: Some(timeout),
+            bind_addr: Some(ip),
+            ..Default::default()
+        };
+
+        return Device::list_with_options(Some(ip), &options);
+    }
+
+    /// List all devices in the current network whose category is one of the specified kinds.
+    /// Optionally specify the local IP if on different subnets.
+    ///
+    /// Devices which are filtered out are never authenticated. Devices with unrecognized model
+    /// codes are returned as [Device::Unknown] when [DeviceKind::Unknown] is requested.
+    ///
+    /// Devices which cannot be authenticated are skipped.
+    ///
+    /// Note: Devices are returned sorted by IP address (and then by MAC address), not in the
+    /// order in which they responded.
+    pub fn list_filtered(ip: Option<Ipv4Addr>, kinds: &[DeviceKind]) -> Result<Vec<Device>, RbroadlinkError> {
+        return Device::list_filtered_with_options(ip, kinds, &DiscoveryOptions::default());
+    }
+
+    /// List all devices in the current network whose category is one of the specified kinds,
+    /// using the specified discovery options. Optionally specify the local IP if on different
+    /// subnets.
+    ///
+    /// See [Device::list_filtered] for more info.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "list", skip_all, fields(local_ip = ?ip)))]
+    pub fn list_filtered_with_options(
+        ip: Option<Ipv4Addr>,
+        kinds: &[DeviceKind],
+        options: &DiscoveryOptions,
+    ) -> Result<Vec<Device>, RbroadlinkError> {
+        // Construct the discovery message
+        let port = UDP_PORT;
+        let msg = discovery_message(ip, Some(options.reported_time()))?;
+
+        let bind_ip = options.bind_addr.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        let bind_addr = SocketAddr::from((bind_ip, port));
+        let results = send_and_receive_many_from(
+            &msg,
+            bind_addr,
+            options.broadcast_addr.unwrap_or(Ipv4Addr::BROADCAST),
+            None,
             |bytes_received, bytes, addr| {
-                return Ok(create_device_from_packet(addr, bytes_received, &bytes)
-                    .map_err(|e| format!("Could not create device from packet! {}", e))?);
+                let mut device = match device_from_packet(addr, bytes_received, &bytes, true) {
+                    Ok(device) => device,
+                    Err(e) => {
+                        debug!("Skipping response from {}: {}", addr, e);
+                        return Ok(None);
+                    }
+                };
+
+                // Skip any devices that were not requested
+                let kind = DeviceKind::from_model_code(device.get_info().model_code);
+                if !kinds.contains(&kind) {
+                    return Ok(None);
+                }
+
+                // Get the auth key for this device, skipping devices which cannot be authenticated
+                if let Err(e) = device.authenticate() {
+                    warn!("Skipping device at {}: Could not authenticate device! {}", addr, e);
+                    return Ok(None);
+                }
+
+                return Ok(Some(device));
             },
+            options.timeout,
+            None,
         )
-            .map_err(|e| format!("Could not send discovery message! {}", e))?;
+            .map_err(|e| e.context("Could not send discovery message!"))?;
+
+        let mut results: Vec<Device> = results.into_iter().flatten().collect();
 
         // Remove duplicates
-        // TODO
+        dedup_devices(&mut results);
+
+        if let Some(interval) = options.learn_poll_interval {
+            results = results.into_iter().map(|d| d.with_learn_poll_interval(interval)).collect();
+        }
+
+        // Sort the results so that discovery order is stable between runs
+        sort_devices(&mut results);
 
         return Ok(results);
     }
 
     /// List all devices in the current network. Optionally specify the local IP if on different subnets.
-    pub async fn list_async(ip: Option<Ipv4Addr>, response_timeout: Duration) -> Result<Vec<Device>, String> {
-        // Grab the first non-loopback address
-        let selected_ip = local_ip_or(ip)?;
+    ///
+    /// Devices which cannot be authenticated are skipped.
+    ///
+    /// Note: Devices are returned sorted by IP address (and then by MAC address), not in the
+    /// order in which they responded.
+    pub async fn list_async(ip: Option<Ipv4Addr>, response_timeout: Duration) -> Result<Vec<Device>, RbroadlinkError> {
+        let options = DiscoveryOptions {
+            timeout: Some(response_timeout),
+            ..Default::default()
+        };
+
+        return Device::list_async_with_options(ip, &options).await;
+    }
 
+    /// List all devices in the current network using the specified discovery options, without
+    /// blocking. Optionally specify the local IP if on different subnets.
+    ///
+    /// The timeout also applies to the authentication of each device.
+    ///
+    /// Note: The socket is always bound to all interfaces, so [DiscoveryOptions::bind_addr]
+    /// is ignored.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "list", skip_all, fields(local_ip = ?ip)))]
+    pub async fn list_async_with_options(
+        ip: Option<Ipv4Addr>,
+        options: &DiscoveryOptions,
+    ) -> Result<Vec<Device>, RbroadlinkError> {
         // Construct the discovery message
         let port = UDP_PORT;
-        let discover = DiscoveryMessage::new(selected_ip, port, None)?;
-        let msg = discover
-            .pack()
-            .map_err(|e| format!("Could not pack DiscoveryMessage! {}", e))?;
+        let msg = discovery_message(ip, Some(options.reported_time()))?;
 
-        let results = send_and_receive_many_async(
+        // Authenticate with each device as soon as it responds
+        let response_timeout = options.timeout.unwrap_or(DEFAULT_READ_TIMEOUT);
+        let results = send_and_receive_many_async_with(
             &msg,
-            Ipv4Addr::BROADCAST,
+            options.broadcast_addr.unwrap_or(Ipv4Addr::BROADCAST),
             port,
-            |bytes_received, bytes, addr| {
-                return Ok(create_device_from_packet(addr, bytes_received, &bytes)
-                    .map_err(|e| format!("Could not create device from packet! {}", e))?);
+            None,
+            move |bytes_received, bytes, addr| async move {
+                // Responses which are not from a supported device are skipped
+                let mut device = match device_from_packet(addr, bytes_received, &bytes, false) {
+                    Ok(device) => device,
+                    Err(e) => {
+                        debug!("Skipping response from {}: {}", addr, e);
+                        return Ok(None);
+                    }
+                };
+                // Devices which cannot be authenticated are skipped as well
+                if let Err(e) = device.authenticate_async(response_timeout).await {
+                    warn!("Skipping device at {}: Could not authenticate device! {}", addr, e);
+                    return Ok(None);
+                }
+
+                return Ok(Some(device));
             },
-            response_timeout
+            response_timeout,
+            None,
         )
             .await
-            .map_err(|e| format!("Could not send discovery message! {}", e))?;
+            .map_err(|e| e.context("Could not send discovery message!"))?;
+        let mut results: Vec<Device> = results.into_iter().flatten().collect();
 
         // Remove duplicates
-        // TODO
+        dedup_devices(&mut results);
+
+        if let Some(interval) = options.learn_poll_interval {
+            results = results.into_iter().map(|d| d.with_learn_poll_interval(interval)).collect();
+        }
+
+        // Sort the results so that discovery order is stable between runs
+        sort_devices(&mut results);
 
         return Ok(results);
     }
 
+    /// Discover all devices in the current network, yielding each device as soon as it has
+    /// responded and been authenticated. Optionally specify the local IP if on different subnets.
+    ///
+    /// The stream ends once no response has been received within the timeout. Devices which
+    /// could not be authenticated are yielded as errors without ending the stream, and responses
+    /// which are not from a supported device are skipped.
+    ///
+    /// Note: This must be called from within a tokio runtime.
+    pub fn discover_stream(
+        local_ip: Option<Ipv4Addr>,
+        response_timeout: Duration,
+    ) -> impl Stream<Item = Result<Device, RbroadlinkError>> {
+        let (tx, rx) = mpsc::channel(16);
+
+        tokio::spawn(async move {
+            if let Err(e) = discover_into(local_ip, response_timeout, &tx).await {
+                let _ = tx.send(Err(e)).await;
+            }
+        });
+
+        return ReceiverStream::new(rx);
+    }
+
     /// Authenticate a device. This is needed before any commands can be sent.
     ///
     /// Note: This is automatically called when constructing a device.
-    pub fn authenticate(&mut self) -> Result<(), String> {
+    pub fn authenticate(&mut self) -> Result<(), RbroadlinkError> {
         let info = self.get_info();
 
         // Create the actual auth message
         let msg = AuthenticationMessage::new(&info.name);
         let packed = msg
             .pack()
-            .map_err(|e| format!("Could not pack Authentication message! {}", e))?;
+            .map_err(|e| RbroadlinkError::Encode(format!("Could not pack Authentication message! {}", e)))?;
 
         // Send the auth message command to the device
         let response = self
             .send_command::<AuthenticationMessage>(&packed)
-            .map_err(|e| format!("Could not send authentication command! {}", e))?;
+            .map_err(|e| e.context("Could not send authentication command!"))?;
+
+        // Unpack the response
+        let auth = AuthenticationResponse::unpack_from_slice(&response)
+            .map_err(|_| RbroadlinkError::AuthFailed.context("Could not unpack auth response!"))?;
+
+        // Save the returned key and ID
+        self.save_auth_pair(auth.id, auth.key);
+
+        return Ok(());
+    }
+
+    /// Authenticate a device without blocking. This is needed before any commands can be sent.
+    pub async fn authenticate_async(&mut self, response_timeout: Duration) -> Result<(), RbroadlinkError> {
+        let info = self.get_info();
+
+        // Create the actual auth message
+        let msg = AuthenticationMessage::new(&info.name);
+        let packed = msg
+            .pack()
+            .map_err(|e| RbroadlinkError::Encode(format!("Could not pack Authentication message! {}", e)))?;
+
+        // Send the auth message command to the device
+        let response = self
+            .send_command_async::<AuthenticationMessage>(&packed, response_timeout)
+            .await
+            .map_err(|e| e.context("Could not send authentication command!"))?;
 
         // Unpack the response
         let auth = AuthenticationResponse::unpack_from_slice(&response)
-            .map_err(|e| format!("Could not unpack auth response! {}", e))?;
+            .map_err(|_| RbroadlinkError::AuthFailed.context("Could not unpack auth response!"))?;
 
         // Save the returned key and ID
         self.save_auth_pair(auth.id, auth.key);
@@ -156,69 +815,488 @@ impl Device {
         return Ok(());
     }
 
+    /// Export a snapshot of the device's identity and current settings, e.g. for backups.
+    pub fn export_snapshot(&self) -> Result<DeviceSnapshot, RbroadlinkError> {
+        let info = self.get_info();
+
+        // Grab the current settings of configurable devices
+        let hvac_state = match self {
+            Device::Hvac { hvac } => Some(
+                hvac.get_state()
+                    .map_err(|e| e.context("Could not read HVAC state for snapshot!"))?,
+            ),
+            _ => None,
+        };
+        let plug_power = match self {
+            Device::Plug { plug } => Some(
+                plug.get_power()
+                    .map_err(|e| e.context("Could not read plug power for snapshot!"))?,
+            ),
+            _ => None,
+        };
+        let strip_states = match self {
+            Device::PowerStrip { strip } => Some(
+                strip
+                    .get_states()
+                    .map_err(|e| e.context("Could not read outlet states for snapshot!"))?,
+            ),
+            _ => None,
+        };
+        let (thermostat_status, thermostat_time) = match self {
+            Device::Thermostat { thermostat } => (
+                Some(
+                    thermostat
+                        .get_status()
+                        .map_err(|e| e.context("Could not read thermostat status for snapshot!"))?,
+                ),
+                Some(
+                    thermostat
+                        .get_time()
+                        .map_err(|e| e.context("Could not read thermostat time for snapshot!"))?,
+                ),
+            ),
+            _ => (None, None),
+        };
+        let switch_state = match self {
+            Device::WallSwitch { switch } => Some(
+                switch
+                    .get_state()
+                    .map_err(|e| e.context("Could not read switch state for snapshot!"))?,
+            ),
+            _ => None,
+        };
+
+        return Ok(DeviceSnapshot {
+            address: info.address,
+            mac: info.mac,
+            model_code: info.model_code,
+            friendly_model: info.friendly_model,
+            friendly_type: info.friendly_type,
+            name: info.name,
+            is_locked: info.is_locked,
+            firmware_version: info.firmware_version,
+            hvac_state,
+            plug_power,
+            strip_states,
+            thermostat_status,
+            thermostat_time,
+            switch_state,
+        });
+    }
+
+    /// Export the negotiated session of this device, so that it can later be restored with
+    /// [Device::from_session] without repeating the authentication handshake.
+    pub fn export_session(&self) -> SessionInfo {
+        let info = self.get_info();
+
+        let (id, key) = info.session.get();
+
+        return SessionInfo {
+            name: info.name.clone(),
+            mac: info.mac,
+            id,
+            key,
+            device_type: info.model_code,
+        };
+    }
+
+    /// Create a device from its individual parts, without communicating with it.
+    ///
+    /// This is useful for building commands deterministically (e.g. in tests or when replaying
+    /// captured traffic). Devices with unrecognized model codes are returned as [Device::Unknown].
+    ///
+    /// Warning: This is a low-level API. The authentication ID and key are not checked, so
+    /// commands fail if they do not match the device's current session. Prefer
+    /// [Device::from_session] for restoring a session of a live device.
+    #[doc(hidden)]
+    pub fn from_parts(
+        addr: Ipv4Addr,
+        mac: [u8; 6],
+        id: u32,
+        key: [u8; 16],
+        device_type: u16,
+    ) -> Result<Device, RbroadlinkError> {
+        return device_from_parts(addr, "", mac, id, key, device_type, true);
+    }
+
+    /// Create a device directly from an IP and a previously exported session.
+    ///
+    /// The session is checked with a status query. If the device reports that it is locked
+    /// (e.g. because it was restarted), a new authentication handshake is performed.
+    ///
+    /// Note: Remotes use the default learning poll interval, which can be changed with
+    /// [Device::with_learn_poll_interval].
+    pub fn from_session(addr: Ipv4Addr, session: SessionInfo) -> Result<Device, RbroadlinkError> {
+        let mut device = device_from_parts(
+            addr,
+            &session.name,
+            session.mac,
+            session.id,
+            *session.key,
+            session.device_type,
+            false,
+        )?;
+
+        // Make sure that the session is still valid, falling back to a new handshake
+        if let Err(e) = device.query_status() {
+            if !matches!(e.root_cause(), RbroadlinkError::DeviceLocked) {
+                return Err(e.context("Could not restore session!"));
+            }
+
+            device
+                .authenticate()
+                .map_err(|e| e.context("Could not authenticate device!"))?;
+        }
+
+        return Ok(device);
+    }
+
+    /// Probe whether the device is locked, by sending it a simple status query.
+    ///
+    /// Note: Locked devices reject all commands and must be unlocked in the Broadlink app.
+    pub fn is_locked(&self) -> Result<bool, RbroadlinkError> {
+        return match self.query_status() {
+            Ok(()) => Ok(false),
+            Err(e) if matches!(e.root_cause(), RbroadlinkError::DeviceLocked) => Ok(true),
+            Err(e) => Err(e.context("Could not probe lock status of device!")),
+        };
+    }
+
+    /// Query the firmware version of the device, saving it in the device's info.
+    pub fn query_firmware_version(&mut self) -> Result<u16, RbroadlinkError> {
+        let response = self
+            .send_command::<FirmwareVersionMessage>(&FIRMWARE_VERSION_QUERY)
+            .map_err(|e| e.context("Could not query firmware version!"))?;
+        let version = FirmwareVersionMessage::unpack_version(&response)?;
+        self.info_mut().firmware_version = Some(version);
+
+        return Ok(version);
+    }
+
+    /// Get the name of the device, as currently reported by the device itself.
+    /// Optionally specify the local IP if on different subnets.
+    pub fn get_name(&self, local_ip: Option<Ipv4Addr>) -> Result<String, RbroadlinkError> {
+        let info = self.get_info();
+
+        // The name is only part of the discovery response
+        let msg = discovery_message(local_ip, None)?;
+
+        let device = send_and_receive_one(&msg, info.address, Some(UDP_PORT), None, |bytes_received, bytes, addr| {
+            return device_from_packet(addr, bytes_received, bytes, true);
+        }, Some(info.read_timeout), Some(info.destination_port))
+            .map_err(|e| e.context("Could not get name of device!"))?;
+
+        return Ok(device.get_info().name.trim_end_matches('\0').to_string());
+    }
+
+    /// Rename the device, as shown in the Broadlink app.
+    ///
+    /// Names are limited to [crate::network::MAX_NAME_LENGTH] bytes.
+    pub fn set_name(&mut self, name: &str) -> Result<(), RbroadlinkError> {
+        let info = self.get_info();
+        let payload = SettingsMessage::pack_settings(name, info.is_locked)
+            .map_err(|e| e.context("Could not rename device!"))?;
+
+        self.send_command::<SettingsMessage>(&payload)
+            .map_err(|e| e.context("Could not rename device!"))?;
+        self.info_mut().name = name.into();
+
+        return Ok(());
+    }
+
+    /// Get mutable access to the core information about a device.
+    fn info_mut(&mut self) -> &mut DeviceInfo {
+        return match self {
+            Device::Remote { remote } => &mut remote.info,
+            Device::Hvac { hvac } => &mut hvac.info,
+            Device::Plug { plug } => &mut plug.info,
+            Device::PowerStrip { strip } => &mut strip.info,
+            Device::Sensor { sensor } => &mut sensor.info,
+            Device::Thermostat { thermostat } => &mut thermostat.info,
+            Device::Alarm { alarm } => &mut alarm.info,
+            Device::Curtain { curtain } => &mut curtain.info,
+            Device::Bulb { bulb } => &mut bulb.info,
+            Device::WallSwitch { switch } => &mut switch.info,
+            Device::Unknown { info } => info,
+        };
+    }
+
+    /// Sends a simple authenticated query to the device, discarding the response.
+    ///
+    /// Note: The query must not change the state of the device.
+    fn query_status(&self) -> Result<(), RbroadlinkError> {
+        return match self {
+            // Remotes have no status to query, and checking for a learned code would consume it
+            Device::Remote { .. } => self
+                .send_command::<FirmwareVersionMessage>(&FIRMWARE_VERSION_QUERY)
+                .map(|_| ()),
+            Device::Hvac { hvac } => hvac.get_info().map(|_| ()),
+            Device::Plug { plug } => plug.get_power().map(|_| ()),
+            Device::PowerStrip { strip } => strip.get_states().map(|_| ()),
+            Device::Sensor { sensor } => sensor.read().map(|_| ()),
+            Device::Thermostat { thermostat } => thermostat.get_status().map(|_| ()),
+            Device::Alarm { alarm } => alarm.get_sensors().map(|_| ()),
+            Device::Curtain { curtain } => curtain.get_position().map(|_| ()),
+            Device::Bulb { bulb } => bulb.get_state().map(|_| ()),
+            Device::WallSwitch { switch } => switch.get_state().map(|_| ()),
+            Device::Unknown { .. } => Err(RbroadlinkError::Unsupported(
+                "Cannot query the status of an unknown device!".into(),
+            )),
+        };
+    }
+
     /// Connects any found device to a specified network. Requires the host machine
     /// to connect to the device directly. Refer to -> <https://github.com/mjg59/python-broadlink#setup>
     pub fn connect_to_network(
         network: &WirelessConnection,
-    ) -> Result<WirelessConnectionMessage, String> {
+    ) -> Result<WirelessConnectionMessage, RbroadlinkError> {
         let msg = network
             .to_message()
-            .map_err(|e| format!("Could not create wireless connection message! {}", e))?;
-        let packed = msg
+            .map_err(|e| e.context("Could not create wireless connection message!"))?;
+        let mut packed = msg
             .pack()
-            .map_err(|e| format!("Could not pack wireless connection message! {}", e))?;
+            .map_err(|e| RbroadlinkError::Encode(format!("Could not pack wireless connection message! {}", e)))?;
 
         // We don't know the format of the response, so we just pass here.
-        send_and_receive_one(&packed, Ipv4Addr::BROADCAST, None, |_, _, _| {
+        let result = send_and_receive_one(&packed, Ipv4Addr::BROADCAST, None, None, |_, _, _| {
             return Ok(());
-        })
-            .map_err(|e| format!("Could not send connection message! {}", e))?;
+        }, None, None);
+
+        // Clear the packed password before reporting any errors
+        packed.zeroize();
+        result.map_err(|e| e.context("Could not send connection message!"))?;
 
         return Ok(msg);
     }
 
+    /// Sends an IR/RF code from several remotes at once (e.g. for an "all off" scene), returning
+    /// the result of each device in the same order as the devices.
+    ///
+    /// Devices which are not remotes fail with [RbroadlinkError::Unsupported] without affecting
+    /// the other devices.
+    pub fn blast_to_all(devices: &[Device], code: &[u8]) -> Vec<Result<(), RbroadlinkError>> {
+        return std::thread::scope(|scope| {
+            let handles: Vec<_> = devices
+                .iter()
+                .map(|device| scope.spawn(move || blast_one(device, code)))
+                .collect();
+
+            return handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect();
+        });
+    }
+
+    /// Sends an IR/RF code from several remotes at once without blocking.
+    ///
+    /// See [Device::blast_to_all] for more info.
+    pub async fn blast_to_all_async(
+        devices: &[Device],
+        code: &[u8],
+        response_timeout: Duration,
+    ) -> Vec<Result<(), RbroadlinkError>> {
+        let handles: Vec<_> = devices
+            .iter()
+            .map(|device| {
+                let device = device.clone();
+                let code = code.to_vec();
+
+                return tokio::spawn(async move {
+                    return match &device {
+                        Device::Remote { remote } => remote.send_code_async(&code, response_timeout).await,
+                        _ => Err(not_a_remote(&device)),
+                    };
+                });
+            })
+            .collect();
+
+        let mut results = vec![];
+        for handle in handles {
+            results.push(match handle.await {
+                Ok(result) => result,
+                Err(e) => std::panic::resume_unwind(e.into_panic()),
+            });
+        }
+
+        return results;
+    }
+
+    /// Connects a device to a specified network (see [Device::connect_to_network]) and waits
+    /// at most the specified amount of time for it to appear on the network, returning the
+    /// authenticated device.
+    ///
+    /// The device waiting to join is identified before sending the join message, so exactly
+    /// one such device must be reachable. Once the message is sent, the host machine should
+    /// rejoin the target network so that the device can be discovered there.
+    pub fn connect_and_discover(
+        network: &WirelessConnection,
+        timeout: Duration,
+    ) -> Result<Device, RbroadlinkError> {
+        let deadline = Instant::now() + timeout;
+
+        // Find out which device is about to join, so that it can be recognized afterwards
+        let waiting = discover_unauthenticated(None, JOIN_DISCOVERY_TIMEOUT)
+            .map_err(|e| e.context("Could not find device waiting to join!"))?;
+        let mac = match waiting.as_slice() {
+            [device] => device.get_info().mac,
+            [] => {
+                return Err(RbroadlinkError::Timeout(JOIN_DISCOVERY_TIMEOUT)
+                    .context("Could not find device waiting to join!"))
+            }
+            _ => {
+                return Err(RbroadlinkError::InvalidInput(format!(
+                    "Could not find device waiting to join! Found {} devices instead of 1.",
+                    waiting.len()
+                )))
+            }
+        };
+
+        Device::connect_to_network(network)?;
+
+        // Keep discovering until the device shows up on the new network
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(RbroadlinkError::Timeout(timeout)
+                    .context("Device did not appear on the network after joining!"));
+            }
+
+            // The host may still be switching networks, so failed discoveries are retried
+            let wait = JOIN_DISCOVERY_TIMEOUT.min(remaining);
+            let devices = match discover_unauthenticated(None, wait) {
+                Ok(devices) => devices,
+                Err(_) => {
+                    std::thread::sleep(wait);
+                    continue;
+                }
+            };
+
+            if let Some(mut device) = devices.into_iter().find(|device| device.get_info().mac == mac) {
+                device
+                    .authenticate()
+                    .map_err(|e| e.context("Could not authenticate device!"))?;
+
+                return Ok(device);
+            }
+        }
+    }
+
     /// Sends a raw command to a broadlink device.
     /// Note: Try to avoid using this method in favor of more specific methods (e.g. [Device::authenticate], etc.)
-    pub fn send_command<T>(&self, payload: &[u8]) -> Result<Vec<u8>, String>
+    pub fn send_command<T>(&self, payload: &[u8]) -> Result<Vec<u8>, RbroadlinkError>
         where
             T: CommandTrait,
     {
         let info = self.get_info();
 
-        // Construct the command.
-        let cmd = CommandMessage::new::<T>(info.model_code, info.mac, info.auth_id);
+        return self.send_command_with_device_type::<T>(payload, info.model_code);
+    }
 
-        // Pack the message with the payload
-        let packed = cmd
-            .pack_with_payload(&payload, &info.key)
-            .map_err(|e| format!("Could not pack command with payload! {}", e))?;
+    /// Sends a raw command to a broadlink device, overriding the device type embedded in the packet.
+    ///
+    /// This is useful for relayed setups or for devices whose command device type differs from
+    /// the one reported during discovery.
+    /// Note: Try to avoid using this method in favor of [Device::send_command].
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "send_command",
+        skip_all,
+        fields(ip = %self.get_info().address, packet_type = T::packet_type())
+    ))]
+    pub fn send_command_with_device_type<T>(
+        &self,
+        payload: &[u8],
+        device_type: u16,
+    ) -> Result<Vec<u8>, RbroadlinkError>
+        where
+            T: CommandTrait,
+    {
+        let info = self.get_info();
+        let packed = self.pack_command::<T>(payload, Some(device_type))?;
 
         // Send the message to the device
-        return send_and_receive_one(&packed, info.address, None, |_, bytes, _| {
-            return CommandMessage::unpack_with_payload(bytes.to_vec(), &info.key);
-        });
+        let addr = SocketAddrV4::new(info.address, info.destination_port);
+        let response = info.transport.send_and_receive(&packed, addr, info.read_timeout)?;
+
+        return CommandMessage::unpack_with_payload(response, &info.session.key());
     }
 
-    /// Sends a raw command to a broadlink device.
-    /// Note: Try to avoid using this method in favor of more specific methods (e.g. [Device::authenticate], etc.)
-    pub async fn send_command_async<T>(&self, payload: &[u8], response_timeout: Duration) -> Result<Vec<u8>, String>
+    /// Packs a command for this device, encrypting the payload with the session key.
+    /// Optionally override the device type embedded in the packet.
+    ///
+    /// This is the same packet sent by [Device::send_command], which is useful for inspecting
+    /// or replaying commands.
+    pub fn pack_command<T>(&self, payload: &[u8], device_type: Option<u16>) -> Result<Vec<u8>, RbroadlinkError>
         where
             T: CommandTrait,
     {
         let info = self.get_info();
+        let (auth_id, key) = info.session.get();
 
         // Construct the command.
-        let cmd = CommandMessage::new::<T>(info.model_code, info.mac, info.auth_id);
+        let cmd = CommandMessage::new::<T>(
+            device_type.unwrap_or(info.model_code),
+            info.mac,
+            auth_id,
+        );
 
         // Pack the message with the payload
-        let packed = cmd
-            .pack_with_payload(&payload, &info.key)
-            .map_err(|e| format!("Could not pack command with payload! {}", e))?;
+        return cmd
+            .pack_with_payload(&payload, &key)
+            .map_err(|e| e.context("Could not pack command with payload!"));
+    }
+
+    /// Sends an arbitrary payload to a broadlink device, waiting at most the specified amount
+    /// of time for a response, and returns the decrypted response payload.
+    ///
+    /// The payload is packed, encrypted and checksummed like any other command, with the
+    /// packet type of `T`. This is useful for experimenting with unsupported device features.
+    ///
+    /// Warning: This is a low-level escape hatch. The format of payloads and responses is
+    /// device specific and is not checked in any way.
+    pub fn send_raw_command<T>(&self, payload: &[u8], timeout: Duration) -> Result<Vec<u8>, RbroadlinkError>
+        where
+            T: CommandTrait,
+    {
+        return self
+            .clone()
+            .with_read_timeout(timeout)
+            .send_command::<T>(payload);
+    }
+
+    /// Sends a fully hand-crafted packet to a broadlink device and returns the raw response.
+    ///
+    /// Warning: This is an escape hatch for protocol experimentation only. The packet is sent
+    /// as-is (see [CommandMessage::pack_raw]) and the response is neither validated nor decrypted.
+    pub fn send_raw_packet(&self, packet: &[u8]) -> Result<Vec<u8>, RbroadlinkError> {
+        let info = self.get_info();
+        let addr = SocketAddrV4::new(info.address, info.destination_port);
+
+        return info.transport.send_and_receive(packet, addr, info.read_timeout);
+    }
+
+    /// Sends a raw command to a broadlink device.
+    /// Note: Try to avoid using this method in favor of more specific methods (e.g. [Device::authenticate], etc.)
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "send_command",
+        skip_all,
+        fields(ip = %self.get_info().address, packet_type = T::packet_type())
+    ))]
+    pub async fn send_command_async<T>(&self, payload: &[u8], response_timeout: Duration) -> Result<Vec<u8>, RbroadlinkError>
+        where
+            T: CommandTrait,
+    {
+        let info = self.get_info();
+        let packed = self.pack_command::<T>(payload, None)?;
 
         // Send the message to the device
-        return send_and_receive_one_async(&packed, info.address, UDP_PORT, |_, bytes, _| {
-            return CommandMessage::unpack_with_payload(bytes.to_vec(), &info.key);
-        },response_timeout).await;
+        let addr = SocketAddrV4::new(info.address, info.destination_port);
+        let response = info
+            .transport
+            .send_and_receive_async(&packed, addr, response_timeout)
+            .await?;
+
+        return CommandMessage::unpack_with_payload(response, &info.session.key());
     }
 }
 
@@ -229,21 +1307,22 @@ impl DeviceTrait for Device {
         return match self {
             Device::Remote { remote } => remote.info.clone(),
             Device::Hvac { hvac } => hvac.info.clone(),
+            Device::Plug { plug } => plug.info.clone(),
+            Device::PowerStrip { strip } => strip.info.clone(),
+            Device::Sensor { sensor } => sensor.info.clone(),
+            Device::Thermostat { thermostat } => thermostat.info.clone(),
+            Device::Alarm { alarm } => alarm.info.clone(),
+            Device::Curtain { curtain } => curtain.info.clone(),
+            Device::Bulb { bulb } => bulb.info.clone(),
+            Device::WallSwitch { switch } => switch.info.clone(),
+            Device::Unknown { info } => info.clone(),
         };
     }
 
     /// Save the authentication information
     fn save_auth_pair(&mut self, id: u32, key: [u8; 16]) {
-        return match self {
-            Device::Remote { remote } => {
-                remote.info.auth_id = id;
-                remote.info.key = key;
-            }
-            Device::Hvac { hvac } => {
-                hvac.info.auth_id = id;
-                hvac.info.key = key;
-            }
-        };
+        // The session is shared, so this updates every clone of the device
+        self.info_mut().session.set(id, key.into());
     }
 }
 
@@ -253,7 +1332,7 @@ impl fmt::Display for Device {
 
         write!(
             f,
-            "{} [{} {:?}] (address = {}, mac = {}, locked? = {})",
+            "{} [{} {:?}] (address = {}, mac = {}, locked? = {}",
             info.name,
             info.friendly_type,
             info.friendly_model,
@@ -264,54 +1343,356 @@ impl fmt::Display for Device {
                 .collect::<Vec<String>>()
                 .join(":"),
             info.is_locked,
-        )
+        )?;
+
+        if let Some(version) = info.firmware_version {
+            write!(f, ", firmware = {}", version)?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+/// Broadcasts a discovery message, returning every supported device which responded within
+/// the timeout, without authenticating them.
+fn discover_unauthenticated(
+    local_ip: Option<Ipv4Addr>,
+    timeout: Duration,
+) -> Result<Vec<Device>, RbroadlinkError> {
+    // Construct the discovery message
+    let port = UDP_PORT;
+    let msg = discovery_message(local_ip, None)?;
+
+    // Responses from other (possibly unsupported) devices are not an error here
+    let responses = send_and_receive_many(
+        &msg,
+        Ipv4Addr::BROADCAST,
+        Some(port),
+        None,
+        |bytes_received, bytes, addr| {
+            return Ok(device_from_packet(addr, bytes_received, bytes, false).ok());
+        },
+        Some(timeout),
+        None,
+    )
+        .map_err(|e| e.context("Could not send discovery message!"))?;
+
+    let mut devices = responses.into_iter().flatten().collect();
+    dedup_devices(&mut devices);
+
+    return Ok(devices);
+}
+
+/// Sends an IR/RF code from a single device, as part of [Device::blast_to_all].
+fn blast_one(device: &Device, code: &[u8]) -> Result<(), RbroadlinkError> {
+    return match device {
+        Device::Remote { remote } => remote.send_code(code),
+        _ => Err(not_a_remote(device)),
+    };
+}
+
+/// The error returned when blasting a code from a device which is not a remote.
+fn not_a_remote(device: &Device) -> RbroadlinkError {
+    return RbroadlinkError::Unsupported(format!("{} is not a remote!", device.get_info().name));
+}
+
+/// Removes devices that responded more than once, keeping the first response.
+///
+/// Note: Devices are keyed on their MAC address, since several devices may share
+/// the same IP (e.g. behind NAT).
+pub(crate) fn dedup_devices(devices: &mut Vec<Device>) {
+    let mut seen = HashSet::new();
+    devices.retain(|device| seen.insert(device.get_info().mac));
+}
+
+/// Sorts a list of devices by IP address and then by MAC address.
+fn sort_devices(devices: &mut Vec<Device>) {
+    devices.sort_by_key(|device| {
+        let info = device.get_info();
+
+        (info.address, info.mac)
+    });
+}
+
+/// Sends a discovery message, forwarding each authenticated device to the channel as soon as
+/// it responds.
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "list", skip_all, fields(local_ip = ?local_ip)))]
+async fn discover_into(
+    local_ip: Option<Ipv4Addr>,
+    response_timeout: Duration,
+    tx: &mpsc::Sender<Result<Device, RbroadlinkError>>,
+) -> Result<(), RbroadlinkError> {
+    // Construct the discovery message
+    let port = UDP_PORT;
+    let msg = discovery_message(local_ip, None)?;
+
+    // Skip devices that respond more than once
+    let mut seen = HashSet::new();
+    send_and_receive_many_async_with(
+        &msg,
+        Ipv4Addr::BROADCAST,
+        port,
+        None,
+        |bytes_received, bytes, addr| {
+            // Responses which are not from a supported device are skipped
+            let device = match device_from_packet(addr, bytes_received, &bytes, false) {
+                Ok(device) => Some(device),
+                Err(e) => {
+                    debug!("Skipping response from {}: {}", addr, e);
+                    None
+                }
+            };
+            let is_new = match &device {
+                Some(device) => seen.insert(device.get_info().mac),
+                None => false,
+            };
+            let tx = tx.clone();
+
+            async move {
+                let mut device = match device {
+                    Some(device) if is_new => device,
+                    _ => return Ok(()),
+                };
+
+                let result = device
+                    .authenticate_async(response_timeout)
+                    .await
+                    .map(|_| device)
+                    .map_err(|e| e.context("Could not authenticate device!"));
+
+                // The receiver may have been dropped, in which case there is nobody to notify
+                let _ = tx.send(result).await;
+
+                return Ok(());
+            }
+        },
+        response_timeout,
+        None,
+    )
+        .await
+        .map_err(|e| e.context("Could not send discovery message!"))?;
+
+    return Ok(());
+}
+
+/// Discovers and authenticates a device at a specified IP, retrying as described by the options.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    name = "from_ip",
+    skip_all,
+    fields(ip = %addr, attempts = tracing::field::Empty)
+))]
+pub(crate) fn connect(
+    addr: Ipv4Addr,
+    local_ip: Option<Ipv4Addr>,
+    options: &ConnectOptions,
+) -> Result<Device, RbroadlinkError> {
+    return retry_with_backoff(options, || {
+        return Device::connect_once(addr, local_ip, options);
+    });
+}
+
+/// Calls `attempt` until it succeeds or the amount of retries is exhausted, sleeping between
+/// attempts with an exponential backoff. Returns the last error if all attempts fail.
+///
+/// Only timeouts and I/O errors are retried. Any other error (e.g. an unsupported device) is
+/// returned immediately, since repeating the exchange would fail the same way.
+pub(crate) fn retry_with_backoff<T, F>(options: &ConnectOptions, mut attempt: F) -> Result<T, RbroadlinkError>
+where
+    F: FnMut() -> Result<T, RbroadlinkError>,
+{
+    let mut backoff = options.initial_backoff;
+    let mut attempts = 1;
+    loop {
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("attempts", attempts);
+
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempts >= options.retries || !is_transient(&e) => return Err(e),
+            Err(_) => {
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                attempts += 1;
+            }
+        }
     }
 }
 
-/// Creates a device from a received network packet.
-fn create_device_from_packet(
+/// Calls `attempt` until it succeeds or the amount of retries is exhausted, without blocking.
+///
+/// See [retry_with_backoff] for more info.
+pub(crate) async fn retry_with_backoff_async<T, F, Fut>(
+    options: &ConnectOptions,
+    mut attempt: F,
+) -> Result<T, RbroadlinkError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, RbroadlinkError>>,
+{
+    let mut backoff = options.initial_backoff;
+    let mut attempts = 1;
+    loop {
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("attempts", attempts);
+
+        match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempts >= options.retries || !is_transient(&e) => return Err(e),
+            Err(_) => {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempts += 1;
+            }
+        }
+    }
+}
+
+/// Returns true if an error may not occur again when retrying, e.g. a lost response.
+fn is_transient(e: &RbroadlinkError) -> bool {
+    return matches!(e.root_cause(), RbroadlinkError::Timeout(_) | RbroadlinkError::Io(_));
+}
+
+/// Builds a packed discovery message asking devices to respond to the local IP, or to the
+/// first non-loopback address if none is specified.
+fn discovery_message(local_ip: Option<Ipv4Addr>, time: Option<DateTime<Local>>) -> Result<Vec<u8>, RbroadlinkError> {
+    let selected_ip = local_ip_or(local_ip)?;
+    let msg = DiscoveryMessage::new(selected_ip, UDP_PORT, time)?
+        .pack()
+        .map_err(|e| RbroadlinkError::Encode(format!("Could not pack DiscoveryMessage! {}", e)))?;
+
+    return Ok(msg.to_vec());
+}
+
+/// Creates an authenticated device from a received network packet.
+///
+/// Returns `None` if the response is not from a supported device or if the device could not
+/// be authenticated, so that a single device cannot fail the whole discovery.
+fn create_device_from_packet(addr: SocketAddr, bytes_received: usize, bytes: &[u8]) -> Option<Device> {
+    let mut device = match device_from_packet(addr, bytes_received, bytes, false) {
+        Ok(device) => device,
+        Err(e) => {
+            debug!("Skipping response from {}: {}", addr, e);
+            return None;
+        }
+    };
+
+    // Get the auth key for this device
+    if let Err(e) = device.authenticate() {
+        warn!("Skipping device at {}: Could not authenticate device! {}", addr, e);
+        return None;
+    }
+
+    return Some(device);
+}
+
+/// Creates an unauthenticated device from a received network packet.
+///
+/// Unrecognized devices are returned as [Device::Unknown] if `allow_unknown` is set.
+pub(crate) fn device_from_packet(
     addr: SocketAddr,
     bytes_received: usize,
     bytes: &[u8],
-) -> Result<Device, String> {
+    allow_unknown: bool,
+) -> Result<Device, RbroadlinkError> {
     // Make sure that we have the required amount of bytes
     if bytes_received < 128 {
-        return Err("Received invalid response! Not enough data.".into());
+        return Err(RbroadlinkError::Decode(
+            "Received invalid response! Not enough data.".into(),
+        ));
     }
 
     // Short-circuit if the device is using an IPv6 address (should be impossible)
     let addr_ip = match addr.ip() {
         IpAddr::V4(a) => a,
-        _ => return Err("Device has an IPv6 Address! This should be impossible...".into()),
+        _ => {
+            return Err(RbroadlinkError::Decode(
+                "Device has an IPv6 Address! This should be impossible...".into(),
+            ))
+        }
     };
 
     let response = DiscoveryResponse::unpack_from_slice(&bytes[0..128])
-        .map_err(|e| format!("Could not unpack response from device! {}", e))?;
+        .map_err(|e| RbroadlinkError::Decode(format!("Could not unpack response from device! {}", e)))?;
 
     // Decode the name
     let raw_name = response.name.clone();
-    let name = from_utf8(&raw_name).map_err(|e| format!("Could not decode device name! {}", e))?;
+    let name = from_utf8(&raw_name).map_err(|e| RbroadlinkError::Decode(format!("Could not decode device name! {}", e)))?;
+
+    return device_from_response(addr_ip, name, response, allow_unknown);
+}
+
+/// Creates an unauthenticated device from a decoded discovery response.
+///
+/// Unrecognized devices are returned as [Device::Unknown] if `allow_unknown` is set.
+/// Creates a device from its individual parts, saving the specified session.
+///
+/// Unrecognized model codes are only accepted (as [Device::Unknown]) if `allow_unknown` is set.
+fn device_from_parts(
+    addr: Ipv4Addr,
+    name: &str,
+    mac: [u8; 6],
+    id: u32,
+    key: [u8; 16],
+    device_type: u16,
+    allow_unknown: bool,
+) -> Result<Device, RbroadlinkError> {
+    let response = DiscoveryResponse {
+        model_code: device_type,
+        mac: reverse_mac(mac),
+        name: [0u8; 62],
+        is_locked: false,
+    };
+    let mut device = device_from_response(addr, name, response, allow_unknown)?;
+    device.save_auth_pair(id, key);
+
+    return Ok(device);
+}
 
+fn device_from_response(
+    addr_ip: Ipv4Addr,
+    name: &str,
+    response: DiscoveryResponse,
+    allow_unknown: bool,
+) -> Result<Device, RbroadlinkError> {
     // Create the device conditionally based on the model code.
-    let mut device = match &response.model_code {
-        _ if REMOTE_CODES.contains_key(&response.model_code) => Device::Remote {
+    let device = match DeviceKind::from_model_code(response.model_code) {
+        DeviceKind::Remote => Device::Remote {
             remote: RemoteDevice::new(name, addr_ip, response),
         },
-        _ if HVAC_CODES.contains_key(&response.model_code) => Device::Hvac {
+        DeviceKind::Hvac => Device::Hvac {
             hvac: HvacDevice::new(name, addr_ip, response),
         },
-        _ => {
-            return Err(format!(
-                "Unknown device: {} ({:#06X})",
-                response.model_code, response.model_code
-            ));
+        DeviceKind::Plug => Device::Plug {
+            plug: SmartPlug::new(name, addr_ip, response),
+        },
+        DeviceKind::PowerStrip => Device::PowerStrip {
+            strip: PowerStrip::new(name, addr_ip, response),
+        },
+        DeviceKind::Sensor => Device::Sensor {
+            sensor: A1Sensor::new(name, addr_ip, response),
+        },
+        DeviceKind::Thermostat => Device::Thermostat {
+            thermostat: Thermostat::new(name, addr_ip, response),
+        },
+        DeviceKind::Alarm => Device::Alarm {
+            alarm: AlarmKit::new(name, addr_ip, response),
+        },
+        DeviceKind::Curtain => Device::Curtain {
+            curtain: CurtainMotor::new(name, addr_ip, response),
+        },
+        DeviceKind::Bulb => Device::Bulb {
+            bulb: SmartBulb::new(name, addr_ip, response),
+        },
+        DeviceKind::WallSwitch => Device::WallSwitch {
+            switch: WallSwitch::new(name, addr_ip, response),
+        },
+        DeviceKind::Unknown if allow_unknown => Device::Unknown {
+            info: DeviceInfo::from_response(name, addr_ip, &response, "Unknown", "Unknown"),
+        },
+        DeviceKind::Unknown => {
+            return Err(RbroadlinkError::UnsupportedDevice(response.model_code));
         }
     };
 
-    // Get the auth key for this device
-    device
-        .authenticate()
-        .map_err(|e| format!("Could not authenticate device! {}", e))?;
-
     return Ok(device);
 }
@@ -0,0 +1,81 @@
+use std::{net::Ipv4Addr, time::Duration};
+
+use crate::{device::connect, ConnectOptions, Device, RbroadlinkError};
+
+/// A builder for connecting to a device at a known IP with custom options.
+///
+/// Note: [Device::from_ip] is equivalent to `DeviceBuilder::new(addr).connect()`.
+#[derive(Debug, Clone)]
+pub struct DeviceBuilder {
+    /// The IP address of the device.
+    addr: Ipv4Addr,
+
+    /// The local IP to report to the device.
+    local_ip: Option<Ipv4Addr>,
+
+    /// The options used when connecting.
+    options: ConnectOptions,
+}
+
+impl DeviceBuilder {
+    /// Create a new DeviceBuilder for the device at the specified IP.
+    pub fn new(addr: Ipv4Addr) -> DeviceBuilder {
+        return DeviceBuilder {
+            addr,
+            local_ip: None,
+            options: ConnectOptions::default(),
+        };
+    }
+
+    /// Set the local IP to use. Useful if on different subnets.
+    pub fn local_ip(mut self, local_ip: Ipv4Addr) -> DeviceBuilder {
+        self.local_ip = Some(local_ip);
+
+        return self;
+    }
+
+    /// Set how long to wait for each response from the device.
+    pub fn timeout(mut self, timeout: Duration) -> DeviceBuilder {
+        self.options.timeout = Some(timeout);
+
+        return self;
+    }
+
+    /// Set the total amount of attempts of the discovery and authentication exchange.
+    pub fn retries(mut self, retries: u8) -> DeviceBuilder {
+        self.options.retries = retries;
+
+        return self;
+    }
+
+    /// Set how long to wait after the first failed attempt.
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> DeviceBuilder {
+        self.options.initial_backoff = initial_backoff;
+
+        return self;
+    }
+
+    /// Set the port that messages are sent to.
+    pub fn port(mut self, port: u16) -> DeviceBuilder {
+        self.options.destination_port = Some(port);
+
+        return self;
+    }
+
+    /// Set how long remotes wait between checks for a code when learning.
+    pub fn learn_poll_interval(mut self, interval: Duration) -> DeviceBuilder {
+        self.options.learn_poll_interval = Some(interval);
+
+        return self;
+    }
+
+    /// Get the options that will be used when connecting.
+    pub fn options(&self) -> &ConnectOptions {
+        return &self.options;
+    }
+
+    /// Discover and authenticate the device.
+    pub fn connect(self) -> Result<Device, RbroadlinkError> {
+        return connect(self.addr, self.local_ip, &self.options);
+    }
+}
@@ -1,12 +1,162 @@
-use std::net::Ipv4Addr;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    net::Ipv4Addr,
+    ops::Deref,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::{
+    constants,
+    network::{
+        util::{reverse_mac, DEFAULT_DESTINATION_PORT, DEFAULT_READ_TIMEOUT},
+        AirCondState, DiscoveryResponse, UdpTransport,
+    },
+    traits::TransportTrait,
+    DeviceTime, ThermostatStatus, WallSwitchState,
+};
+
+/// The key used for encrypted communication with a device.
+///
+/// The key is zeroed when dropped and redacted when formatted with [fmt::Debug].
+#[derive(Clone, PartialEq, Eq)]
+pub struct SessionKey([u8; 16]);
+
+impl From<[u8; 16]> for SessionKey {
+    fn from(key: [u8; 16]) -> SessionKey {
+        return SessionKey(key);
+    }
+}
+
+impl Deref for SessionKey {
+    type Target = [u8; 16];
+
+    fn deref(&self) -> &[u8; 16] {
+        return &self.0;
+    }
+}
+
+impl Drop for SessionKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.write_str("SessionKey(***)");
+    }
+}
+
+/// The authentication ID and key used for encrypted communication with a device.
+///
+/// Cloning a session does not copy it: every clone refers to the same session, so
+/// re-authenticating through one clone of a device updates all of its clones.
+#[derive(Clone)]
+pub struct SharedSession(Arc<RwLock<(u32, SessionKey)>>);
+
+impl SharedSession {
+    /// Create a new session from an authentication ID and key.
+    pub fn new(id: u32, key: SessionKey) -> SharedSession {
+        return SharedSession(Arc::new(RwLock::new((id, key))));
+    }
+
+    /// Get the authentication ID and key of the session.
+    pub fn get(&self) -> (u32, SessionKey) {
+        let pair = self.0.read().unwrap_or_else(|e| e.into_inner());
+
+        return (pair.0, pair.1.clone());
+    }
+
+    /// Get the authentication ID of the session.
+    pub fn auth_id(&self) -> u32 {
+        return self.get().0;
+    }
+
+    /// Get the key of the session.
+    pub fn key(&self) -> SessionKey {
+        return self.get().1;
+    }
+
+    /// Replace the authentication ID and key of the session, for every clone.
+    pub fn set(&self, id: u32, key: SessionKey) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = (id, key);
+    }
+}
+
+/// The session of a device which has not been authenticated yet.
+impl Default for SharedSession {
+    fn default() -> SharedSession {
+        return SharedSession::new(0, constants::INITIAL_KEY.into());
+    }
+}
+
+impl fmt::Debug for SharedSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (id, key) = self.get();
+
+        return f
+            .debug_struct("SharedSession")
+            .field("auth_id", &id)
+            .field("key", &key)
+            .finish();
+    }
+}
+
+/// The transport used to exchange packets with a device, shared between all clones of
+/// the device.
+///
+/// Defaults to [UdpTransport].
+#[derive(Clone)]
+pub struct SharedTransport(Arc<dyn TransportTrait + Send + Sync>);
+
+impl SharedTransport {
+    /// Create a new shared transport.
+    pub fn new(transport: Arc<dyn TransportTrait + Send + Sync>) -> SharedTransport {
+        return SharedTransport(transport);
+    }
+}
+
+impl Deref for SharedTransport {
+    type Target = dyn TransportTrait + Send + Sync;
+
+    fn deref(&self) -> &Self::Target {
+        return &*self.0;
+    }
+}
+
+impl Default for SharedTransport {
+    fn default() -> SharedTransport {
+        return SharedTransport::new(Arc::new(UdpTransport));
+    }
+}
+
+impl fmt::Debug for SharedTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.write_str("SharedTransport");
+    }
+}
 
 /// Represents a broadlink device core information.
+///
+/// Two infos are equal (and hash the same) if they have the same MAC address, since the
+/// MAC is the only stable identity of a device.
+///
+/// Note: With the `serde` feature, the session (authentication ID and key) is never
+/// serialized. Deserialized devices must be authenticated before commands can be sent.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DeviceInfo {
     /// The IP address of this device.
     pub address: Ipv4Addr,
 
     /// The MAC address of this device.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_helpers::mac_hex"))]
     pub mac: [u8; 6],
 
     /// The model code of this device.
@@ -24,9 +174,152 @@ pub struct DeviceInfo {
     /// The lock status of the device.
     pub is_locked: bool,
 
+    /// The firmware version of the device, if known.
+    ///
+    /// Note: The version is not part of the discovery response. It is only known after
+    /// calling [crate::Device::query_firmware_version].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub firmware_version: Option<u16>,
+
+    /// The port that commands are sent to.
+    #[cfg_attr(feature = "serde", serde(default = "default_destination_port"))]
+    pub destination_port: u16,
+
+    /// How long to wait for responses to commands.
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_read_timeout"))]
+    pub read_timeout: Duration,
+
+    /// The authentication ID and key used for encrypted communication, shared between all
+    /// clones of the device.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub session: SharedSession,
+
+    /// The transport used to send commands, shared between all clones of the device.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub transport: SharedTransport,
+}
+
+impl DeviceInfo {
+    /// Create the info of an unauthenticated device from its discovery response.
+    pub fn from_response(
+        name: &str,
+        addr: Ipv4Addr,
+        response: &DiscoveryResponse,
+        friendly_type: &str,
+        friendly_model: &str,
+    ) -> DeviceInfo {
+        return DeviceInfo {
+            address: addr,
+            mac: reverse_mac(response.mac),
+            model_code: response.model_code,
+            friendly_type: friendly_type.into(),
+            friendly_model: friendly_model.into(),
+            name: name.into(),
+            session: SharedSession::default(), // This will be populated when authenticated.
+            is_locked: response.is_locked,
+            firmware_version: None, // This will be populated when queried.
+            destination_port: DEFAULT_DESTINATION_PORT,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            transport: SharedTransport::default(),
+        };
+    }
+}
+
+impl PartialEq for DeviceInfo {
+    fn eq(&self, other: &DeviceInfo) -> bool {
+        return self.mac == other.mac;
+    }
+}
+
+impl Eq for DeviceInfo {}
+
+impl Hash for DeviceInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.mac.hash(state);
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_destination_port() -> u16 {
+    return DEFAULT_DESTINATION_PORT;
+}
+
+#[cfg(feature = "serde")]
+fn default_read_timeout() -> Duration {
+    return DEFAULT_READ_TIMEOUT;
+}
+
+/// The negotiated session of an authenticated device, used to reconnect without repeating
+/// the authentication handshake.
+///
+/// Note: Anyone holding this can send commands to the device, so store it accordingly. The
+/// key is zeroed when the session is dropped and redacted when formatted with [fmt::Debug].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// The name of the device.
+    pub name: String,
+
+    /// The MAC address of the device.
+    pub mac: [u8; 6],
+
     /// The authentication ID used for encrypted communication.
-    pub auth_id: u32,
+    pub id: u32,
+
+    /// The key used for encrypted communication.
+    pub key: SessionKey,
+
+    /// The model code of the device.
+    pub device_type: u16,
+}
+
+/// A snapshot of a device's identity and current settings, useful for backups.
+///
+/// Note: The authentication ID and key are intentionally left out, since they are
+/// only valid for the current session.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceSnapshot {
+    /// The IP address of this device.
+    pub address: Ipv4Addr,
+
+    /// The MAC address of this device.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_helpers::mac_hex"))]
+    pub mac: [u8; 6],
+
+    /// The model code of this device.
+    pub model_code: u16,
+
+    /// The friendly model type
+    pub friendly_model: String,
+
+    /// The friendly device type
+    pub friendly_type: String,
+
+    /// The name of the device.
+    pub name: String,
+
+    /// The lock status of the device.
+    pub is_locked: bool,
+
+    /// The firmware version of the device, if it has been queried.
+    pub firmware_version: Option<u16>,
+
+    /// The current state of the air conditioner, if the device is an HVAC unit.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_helpers::air_cond_state_hex"))]
+    pub hvac_state: Option<AirCondState>,
+
+    /// Whether the plug is on, if the device is a smart plug.
+    pub plug_power: Option<bool>,
+
+    /// Whether each outlet is on, if the device is a power strip.
+    pub strip_states: Option<[bool; 4]>,
+
+    /// The current status of the thermostat, if the device is a thermostat.
+    pub thermostat_status: Option<ThermostatStatus>,
+
+    /// The time of the thermostat's clock, if the device is a thermostat.
+    pub thermostat_time: Option<DeviceTime>,
 
-    /// The key used for encrypted communication
-    pub key: [u8; 16],
+    /// The current state of the switch, including its night light, if the device is a wall switch.
+    pub switch_state: Option<WallSwitchState>,
 }
@@ -0,0 +1,94 @@
+use std::{fmt, time::Duration};
+
+use crate::RbroadlinkError;
+
+/// The outcome of a single step of a [DiagnosticReport].
+#[derive(Debug, Clone)]
+pub struct DiagnosticStep {
+    /// How long the step took.
+    pub elapsed: Duration,
+
+    /// The reason the step failed, if it did.
+    pub error: Option<String>,
+}
+
+impl DiagnosticStep {
+    /// Create a new DiagnosticStep from the result of the step.
+    pub fn new<T>(result: &Result<T, RbroadlinkError>, elapsed: Duration) -> DiagnosticStep {
+        return DiagnosticStep {
+            elapsed,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+    }
+
+    /// Returns true if the step succeeded.
+    pub fn succeeded(&self) -> bool {
+        return self.error.is_none();
+    }
+}
+
+/// A report of which steps of communicating with a device succeeded. See [crate::Device::diagnose].
+///
+/// Later steps are only attempted if all of the previous steps succeeded.
+#[derive(Debug, Clone)]
+pub struct DiagnosticReport {
+    /// Whether the device responded to a discovery message.
+    pub discovery: DiagnosticStep,
+
+    /// Whether the device accepted the authentication handshake.
+    pub authentication: Option<DiagnosticStep>,
+
+    /// Whether the device answered an authenticated status query.
+    pub status_query: Option<DiagnosticStep>,
+}
+
+impl DiagnosticReport {
+    /// Returns true if every step succeeded.
+    pub fn succeeded(&self) -> bool {
+        return self.discovery.succeeded()
+            && self.authentication.as_ref().map_or(false, |s| s.succeeded())
+            && self.status_query.as_ref().map_or(false, |s| s.succeeded());
+    }
+
+    /// A human readable summary of where communication broke down.
+    pub fn summary(&self) -> &'static str {
+        if !self.discovery.succeeded() {
+            return "No response at all (wrong IP or subnet?)";
+        }
+
+        if !self.authentication.as_ref().map_or(false, |s| s.succeeded()) {
+            return "Device reachable, but authentication failed (locked?)";
+        }
+
+        if !self.status_query.as_ref().map_or(false, |s| s.succeeded()) {
+            return "Device authenticated, but did not answer a status query";
+        }
+
+        return "Device is working correctly";
+    }
+}
+
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.summary())?;
+
+        let steps = [
+            ("discovery", Some(&self.discovery)),
+            ("authentication", self.authentication.as_ref()),
+            ("status query", self.status_query.as_ref()),
+        ];
+        for (name, step) in steps {
+            match step {
+                Some(DiagnosticStep { elapsed, error: None }) => {
+                    writeln!(f, "  {}: ok ({:?})", name, elapsed)?
+                }
+                Some(DiagnosticStep { elapsed, error: Some(e) }) => {
+                    writeln!(f, "  {}: failed after {:?}: {}", name, elapsed, e)?
+                }
+                None => writeln!(f, "  {}: skipped", name)?,
+            };
+        }
+
+        return Ok(());
+    }
+}
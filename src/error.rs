@@ -0,0 +1,121 @@
+use std::{error::Error, fmt, io, time::Duration};
+
+/// An error returned when communicating with a broadlink device.
+///
+/// Note: Most errors are wrapped in one or more [RbroadlinkError::Context] layers describing
+/// what was being attempted. Use [RbroadlinkError::root_cause] to match on the underlying
+/// failure.
+#[derive(Debug)]
+pub enum RbroadlinkError {
+    /// A network or filesystem operation failed.
+    Io(io::Error),
+
+    /// No response was received within the specified timeout.
+    Timeout(Duration),
+
+    /// A received checksum did not match the checksum of the received data.
+    ChecksumMismatch { expected: u16, got: u16 },
+
+    /// The device answered with an empty command, which usually means that it is locked.
+    ///
+    /// Note: Locked devices must be unlocked in the Broadlink app before they can be controlled.
+    DeviceLocked,
+
+    /// The device did not accept the authentication handshake.
+    AuthFailed,
+
+    /// The device has a model code which is not supported.
+    UnsupportedDevice(u16),
+
+    /// Data received from a device (or a code) could not be decoded.
+    Decode(String),
+
+    /// A message (or a code) could not be encoded to be sent to a device.
+    Encode(String),
+
+    /// An argument was not valid for the requested operation.
+    InvalidInput(String),
+
+    /// The device does not support the requested operation.
+    Unsupported(String),
+
+    /// The device answered with an error code (e.g. -10 when it has nothing to read).
+    DeviceError(i16),
+
+    /// The device answered, but did not capture anything while learning.
+    NothingLearned(String),
+
+    /// The operation was cancelled by the caller.
+    Cancelled,
+
+    /// This machine has no IPv4 address which can be used to reach devices.
+    NoIpv4Interface,
+
+    /// An error with a description of what was being attempted.
+    Context {
+        context: String,
+        source: Box<RbroadlinkError>,
+    },
+}
+
+impl RbroadlinkError {
+    /// Wrap this error with a description of what was being attempted.
+    pub fn context(self, context: impl Into<String>) -> RbroadlinkError {
+        return RbroadlinkError::Context {
+            context: context.into(),
+            source: Box::new(self),
+        };
+    }
+
+    /// Get the underlying error, skipping over any context.
+    pub fn root_cause(&self) -> &RbroadlinkError {
+        return match self {
+            RbroadlinkError::Context { source, .. } => source.root_cause(),
+            _ => self,
+        };
+    }
+}
+
+impl fmt::Display for RbroadlinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            RbroadlinkError::Io(e) => write!(f, "{}", e),
+            RbroadlinkError::Timeout(timeout) => {
+                write!(f, "No response within timeout ({:?})!", timeout)
+            }
+            RbroadlinkError::ChecksumMismatch { expected, got } => {
+                write!(f, "Expected {:#06X} got {:#06X}", expected, got)
+            }
+            RbroadlinkError::DeviceLocked => write!(f, "Device locked?"),
+            RbroadlinkError::AuthFailed => write!(f, "Device rejected the authentication handshake!"),
+            RbroadlinkError::UnsupportedDevice(code) => {
+                write!(f, "Unknown device: {} ({:#06X})", code, code)
+            }
+            RbroadlinkError::Decode(msg) => write!(f, "{}", msg),
+            RbroadlinkError::Encode(msg) => write!(f, "{}", msg),
+            RbroadlinkError::InvalidInput(msg) => write!(f, "{}", msg),
+            RbroadlinkError::Unsupported(msg) => write!(f, "{}", msg),
+            RbroadlinkError::DeviceError(code) => write!(f, "Device reported error {}!", code),
+            RbroadlinkError::NothingLearned(msg) => write!(f, "{}", msg),
+            RbroadlinkError::Cancelled => write!(f, "Operation was cancelled!"),
+            RbroadlinkError::NoIpv4Interface => write!(f, "No usable IPv4 network interface found!"),
+            RbroadlinkError::Context { context, source } => write!(f, "{} {}", context, source),
+        };
+    }
+}
+
+impl Error for RbroadlinkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        return match self {
+            RbroadlinkError::Io(e) => Some(e),
+            RbroadlinkError::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        };
+    }
+}
+
+impl From<io::Error> for RbroadlinkError {
+    fn from(e: io::Error) -> RbroadlinkError {
+        return RbroadlinkError::Io(e);
+    }
+}
@@ -4,12 +4,10 @@ use packed_struct::PackedStructSlice;
 use phf::phf_map;
 
 use crate::{
-    constants,
     network::{
-        util::reverse_mac, AirCondInfo, AirCondState, DiscoveryResponse, HvacDataCommand,
-        HvacDataMessage,
+        AirCondInfo, AirCondState, DiscoveryResponse, HvacDataCommand, HvacDataMessage,
     },
-    Device, DeviceInfo,
+    Device, DeviceInfo, RbroadlinkError,
 };
 
 /// A mapping of hvac device codes to their friendly model equivalent.
@@ -37,47 +35,46 @@ impl HvacDevice {
             .to_string();
 
         return Self {
-            info: DeviceInfo {
-                address: addr,
-                mac: reverse_mac(response.mac),
-                model_code: response.model_code,
-                friendly_type: "HVAC".into(),
-                friendly_model: friendly_model,
-                name: name.into(),
-                auth_id: 0, // This will be populated when authenticated.
-                key: constants::INITIAL_KEY,
-                is_locked: response.is_locked,
-            },
+            info: DeviceInfo::from_response(name, addr, &response, "HVAC", &friendly_model),
         };
     }
 
     /// Get basic information from the air conditioner.
-    pub fn get_info(&self) -> Result<AirCondInfo, String> {
+    pub fn get_info(&self) -> Result<AirCondInfo, RbroadlinkError> {
         let data = self
             .send_command(&[], HvacDataCommand::GetAcInfo)
-            .map_err(|e| format!("Could not obtain AC info from device! {}", e))?;
+            .map_err(|e| e.context("Could not obtain AC info from device!"))?;
         let info = AirCondInfo::unpack_from_slice(&data)
-            .map_err(|e| format!("Could not unpack command from bytes! {}", e))?;
+            .map_err(|e| RbroadlinkError::Decode(format!("Could not unpack command from bytes! {}", e)))?;
 
         return Ok(info);
     }
 
+    /// Build the complete, encrypted packet used by [HvacDevice::get_info] to query the
+    /// air conditioner's basic information.
+    pub fn build_info_query(&self) -> Result<Vec<u8>, RbroadlinkError> {
+        let generic_device = Device::Hvac { hvac: self.clone() };
+        let payload = HvacDataMessage::info_query()?;
+
+        return generic_device.pack_command::<HvacDataMessage>(&payload, None);
+    }
+
     /// Get current air conditioner state into AirCondState structure.
-    pub fn get_state(&self) -> Result<AirCondState, String> {
+    pub fn get_state(&self) -> Result<AirCondState, RbroadlinkError> {
         let data = self
             .send_command(&[], HvacDataCommand::GetState)
-            .map_err(|e| format!("Could not obtain AC state from device! {}", e))?;
+            .map_err(|e| e.context("Could not obtain AC state from device!"))?;
         let state = AirCondState::unpack_from_slice(&data)
-            .map_err(|e| format!("Could not unpack command from bytes! {}", e))?;
+            .map_err(|e| RbroadlinkError::Decode(format!("Could not unpack command from bytes! {}", e)))?;
 
         return Ok(state);
     }
 
     /// Set new air conditioner state based on passed structure.
-    pub fn set_state(&self, state: &mut AirCondState) -> Result<Vec<u8>, String> {
+    pub fn set_state(&self, state: &mut AirCondState) -> Result<Vec<u8>, RbroadlinkError> {
         let payload = state
             .prepare_and_pack()
-            .map_err(|e| format!("Could not pack message! {}", e))?;
+            .map_err(|e| e.context("Could not pack message!"))?;
         let response = self.send_command(&payload, HvacDataCommand::SetState)?;
 
         return Ok(response);
@@ -89,7 +86,7 @@ impl HvacDevice {
         &self,
         payload: &[u8],
         command: HvacDataCommand,
-    ) -> Result<Vec<u8>, String> {
+    ) -> Result<Vec<u8>, RbroadlinkError> {
         // We cast this object to a generic device in order to make use of the shared
         // helper utilities.
         let generic_device = Device::Hvac { hvac: self.clone() };
@@ -98,11 +95,11 @@ impl HvacDevice {
         let msg = HvacDataMessage::new(command);
         let packed = msg
             .pack_with_payload(&payload)
-            .map_err(|e| format!("Could not pack HVAC data message! {}", e))?;
+            .map_err(|e| e.context("Could not pack HVAC data message!"))?;
 
         let response = generic_device
             .send_command::<HvacDataMessage>(&packed)
-            .map_err(|e| format!("Could not send command! {}", e))?;
+            .map_err(|e| e.context("Could not send command!"))?;
 
         // TODO: check if there is some relation between
         // msg.command and the same return field from the response
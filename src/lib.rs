@@ -1,17 +1,42 @@
 // Include testing
 mod test;
 
+mod alarm;
+mod bulb;
 mod constants;
+mod curtain;
 mod device;
+mod device_builder;
 mod device_info;
+mod diagnostics;
+mod error;
 mod hvac;
+mod plug;
+mod power_strip;
 mod remote;
+#[cfg(feature = "serde")]
+mod serde_helpers;
+mod sensor;
+mod thermostat;
+mod wall_switch;
 
 // Manage exports
+pub mod codes;
 pub mod network;
 pub mod traits;
 
+pub use alarm::*;
+pub use bulb::*;
+pub use curtain::*;
 pub use device::*;
+pub use device_builder::*;
 pub use device_info::*;
+pub use diagnostics::*;
+pub use error::*;
 pub use hvac::*;
+pub use plug::*;
+pub use power_strip::*;
 pub use remote::*;
+pub use sensor::*;
+pub use thermostat::*;
+pub use wall_switch::*;
@@ -0,0 +1,16 @@
+use crate::traits::CommandTrait;
+
+/// The payload used to query the sensors paired with an alarm kit.
+pub const ALARM_SENSORS_QUERY: [u8; 0x10] = [
+    0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// A message used to query an alarm kit.
+#[derive(Debug)]
+pub struct AlarmDataMessage;
+
+impl CommandTrait for AlarmDataMessage {
+    fn packet_type() -> u16 {
+        return 0x006A;
+    }
+}
@@ -0,0 +1,97 @@
+use serde_json::{json, Value};
+
+use crate::{
+    network::{pack_json_payload, JsonDataFlag},
+    traits::CommandTrait,
+    RbroadlinkError,
+};
+
+/// The range of color temperatures supported by smart bulbs, in kelvin.
+pub const BULB_COLOR_TEMPERATURE_RANGE: std::ops::RangeInclusive<u16> = 2700..=6500;
+
+/// The color mode used when the bulb shows an RGB color.
+pub const BULB_COLOR_MODE_RGB: u8 = 0;
+
+/// The color mode used when the bulb shows a white color temperature.
+pub const BULB_COLOR_MODE_WHITE: u8 = 1;
+
+/// A message used to control a smart bulb.
+#[derive(Debug, Clone)]
+pub struct BulbDataMessage {
+    /// Whether the message reads or changes the state of the bulb.
+    flag: JsonDataFlag,
+
+    /// The state values to change.
+    state: Value,
+}
+
+impl BulbDataMessage {
+    /// Create a message which queries the state of the bulb.
+    pub fn query() -> BulbDataMessage {
+        return BulbDataMessage {
+            flag: JsonDataFlag::Read,
+            state: json!({}),
+        };
+    }
+
+    /// Create a message which turns the bulb on or off.
+    pub fn power(on: bool) -> BulbDataMessage {
+        return BulbDataMessage::write(json!({ "pwr": u8::from(on) }));
+    }
+
+    /// Create a message which sets the brightness of the bulb, as a percentage.
+    pub fn brightness(brightness: u8) -> Result<BulbDataMessage, RbroadlinkError> {
+        if brightness > 100 {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Bulb brightness must be between 0 and 100, got {}.",
+                brightness
+            )));
+        }
+
+        return Ok(BulbDataMessage::write(json!({ "brightness": brightness })));
+    }
+
+    /// Create a message which sets the bulb to an RGB color.
+    pub fn color(red: u8, green: u8, blue: u8) -> BulbDataMessage {
+        return BulbDataMessage::write(json!({
+            "red": red,
+            "green": green,
+            "blue": blue,
+            "bulb_colormode": BULB_COLOR_MODE_RGB,
+        }));
+    }
+
+    /// Create a message which sets the bulb to a white color temperature, in kelvin.
+    pub fn color_temperature(kelvin: u16) -> Result<BulbDataMessage, RbroadlinkError> {
+        if !BULB_COLOR_TEMPERATURE_RANGE.contains(&kelvin) {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Bulb color temperature must be between 2700 and 6500K, got {}K.",
+                kelvin
+            )));
+        }
+
+        return Ok(BulbDataMessage::write(json!({
+            "colortemp": kelvin,
+            "bulb_colormode": BULB_COLOR_MODE_WHITE,
+        })));
+    }
+
+    /// Pack the message into the payload sent to the bulb.
+    pub fn pack(&self) -> Result<Vec<u8>, RbroadlinkError> {
+        return pack_json_payload(self.flag, &self.state);
+    }
+
+    /// Create a message which changes the specified state values.
+    fn write(state: Value) -> BulbDataMessage {
+        return BulbDataMessage {
+            flag: JsonDataFlag::Write,
+            state,
+        };
+    }
+}
+
+impl CommandTrait for BulbDataMessage {
+    fn packet_type() -> u16 {
+        return 0x006A;
+    }
+}
@@ -8,6 +8,7 @@ use crate::{
     constants,
     network::util::{checksum, reverse_mac},
     traits::CommandTrait,
+    RbroadlinkError,
 };
 
 /// Represents a block-based AES 128-bit encryption cipher.
@@ -46,6 +47,10 @@ pub struct CommandMessage {
     #[packed_field(bytes = "0x20:0x21")]
     checksum: u16,
 
+    /// The error code reported by the device in its response. Zero means success.
+    #[packed_field(bytes = "0x22:0x23")]
+    error_code: u16,
+
     /// The checksum of just the payload, before encryption
     #[packed_field(bytes = "0x34:0x35")]
     payload_checksum: u16,
@@ -74,6 +79,7 @@ impl CommandMessage {
             mac_reversed: reverse_mac(mac),
             id: id,
             checksum: 0,         // This will be populated later.
+            error_code: 0,
             payload_checksum: 0, // This will be populated later.
         };
     }
@@ -89,10 +95,32 @@ impl CommandMessage {
         return CommandMessage::with_count::<T>(random_count, device_model_code, mac, id);
     }
 
+    /// Describe the fields of the command header in a human-friendly way, e.g. to diagnose
+    /// checksum mismatches.
+    ///
+    /// The count is shown without its 0x8000 flag, and the MAC is shown in its usual order.
+    pub fn describe(&self) -> String {
+        return format!(
+            "device_type = {:#06X}, packet_type = {:#06X}, count = {:#06X}, mac = {}, id = {:#010X}, \
+             checksum = {:#06X}, payload_checksum = {:#06X}",
+            self.device_type,
+            self.packet_type,
+            self.count & !0x8000,
+            reverse_mac(self.mac_reversed)
+                .iter()
+                .map(|x| format!("{:02X}", x))
+                .collect::<Vec<String>>()
+                .join(":"),
+            self.id,
+            self.checksum,
+            self.payload_checksum,
+        );
+    }
+
     /// Pack the command message while appending the payload.
-    pub fn pack_with_payload(mut self, payload: &[u8], key: &[u8; 16]) -> Result<Vec<u8>, String> {
+    pub fn pack_with_payload(mut self, payload: &[u8], key: &[u8; 16]) -> Result<Vec<u8>, RbroadlinkError> {
         let cipher = AesCbc::new_from_slices(key, &constants::INITIAL_VECTOR)
-            .map_err(|e| format!("Could not construct cipher! {}", e))?;
+            .map_err(|e| RbroadlinkError::InvalidInput(format!("Could not construct cipher! {}", e)))?;
 
         // Save the checksum of the payload before encrypting
         self.payload_checksum = checksum(&payload);
@@ -103,7 +131,7 @@ impl CommandMessage {
         // Pack the command with the payload appended
         let packed = self
             .pack()
-            .map_err(|e| format!("Could not pack command header! {}", e))?;
+            .map_err(|e| RbroadlinkError::Encode(format!("Could not pack command header! {}", e)))?;
 
         let mut appended = packed.to_vec();
         appended.extend(&encrypted);
@@ -114,7 +142,7 @@ impl CommandMessage {
         // Construct the final message
         let completely_packed = self
             .pack()
-            .map_err(|e| format!("Could not pack completed command! {}", e))?;
+            .map_err(|e| RbroadlinkError::Encode(format!("Could not pack completed command! {}", e)))?;
 
         let mut complete_command: Vec<u8> = completely_packed.to_vec();
         complete_command.extend(&encrypted);
@@ -122,24 +150,44 @@ impl CommandMessage {
         return Ok(complete_command);
     }
 
+    /// Pack a hand-crafted command header with a payload, without touching either.
+    ///
+    /// Warning: This is an escape hatch for protocol experimentation only. Unlike
+    /// [CommandMessage::pack_with_payload], no checksums are computed and the payload is
+    /// not encrypted, so the resulting packet is sent exactly as given. Devices will
+    /// silently drop packets with invalid checksums.
+    pub fn pack_raw(header: &[u8], payload: &[u8]) -> Result<Vec<u8>, RbroadlinkError> {
+        if header.len() != 0x38 {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Command header has the wrong size! Expected 0x38 bytes, got {}",
+                header.len()
+            )));
+        }
+
+        let mut packet = header.to_vec();
+        packet.extend(payload);
+
+        return Ok(packet);
+    }
+
     /// Unpack the command message with the associated payload.
-    pub fn unpack_with_payload(mut bytes: Vec<u8>, key: &[u8; 16]) -> Result<Vec<u8>, String> {
+    pub fn unpack_with_payload(mut bytes: Vec<u8>, key: &[u8; 16]) -> Result<Vec<u8>, RbroadlinkError> {
 
         if bytes.len() == 0x38 {
-            return Err("Device locked?".to_string())
+            return Err(RbroadlinkError::DeviceLocked);
         }
 
         // Ensure that the data is correct
         if bytes.len() < 0x38 {
-            return Err(format!(
+            return Err(RbroadlinkError::Decode(format!(
                 "Command is too short! Expected 0x38 bytes, got {}",
                 bytes.len()
-            ));
+            )));
         }
 
         // Unpack the header
         let command_header = CommandMessage::unpack_from_slice(&bytes[0..0x38])
-            .map_err(|e| format!("Could not unpack command from bytes! {}", e))?;
+            .map_err(|e| RbroadlinkError::Decode(format!("Could not unpack command from bytes! {}", e)))?;
 
         // Zero out the checksum from the header for verification
         // TODO: Is there a nicer way to do this?
@@ -149,27 +197,34 @@ impl CommandMessage {
         // Ensure that the checksums match
         let real_checksum = checksum(&bytes);
         if command_header.checksum != real_checksum {
-            return Err(format!(
-                "Command checksum does not match actual checksum! Expected {:#06X} got {:#06X}",
-                real_checksum, command_header.checksum,
-            ));
+            let mismatch = RbroadlinkError::ChecksumMismatch {
+                expected: real_checksum,
+                got: command_header.checksum,
+            };
+            return Err(mismatch.context("Command checksum does not match actual checksum!"));
+        }
+
+        // The payload of a response with an error code is meaningless
+        if command_header.error_code != 0 {
+            return Err(RbroadlinkError::DeviceError(command_header.error_code as i16));
         }
 
         // Decrypt the message
         let cipher = AesCbc::new_from_slices(key, &constants::INITIAL_VECTOR)
-            .map_err(|e| format!("Could not construct cipher! {}", e))?;
+            .map_err(|e| RbroadlinkError::InvalidInput(format!("Could not construct cipher! {}", e)))?;
 
         let decrypted = cipher
             .decrypt_vec(&bytes[0x38..])
-            .map_err(|e| format!("Could not decrypt command payload! {}", e))?;
+            .map_err(|e| RbroadlinkError::Decode(format!("Could not decrypt command payload! {}", e)))?;
 
         // Ensure that the payload checksums match
         let real_checksum = checksum(&decrypted);
         if command_header.payload_checksum != real_checksum {
-            return Err(format!(
-                "Payload checksum does not match actual checksum! Expected {:#06X} got {:#06X}",
-                real_checksum, command_header.payload_checksum,
-            ));
+            let mismatch = RbroadlinkError::ChecksumMismatch {
+                expected: real_checksum,
+                got: command_header.payload_checksum,
+            };
+            return Err(mismatch.context("Payload checksum does not match actual checksum!"));
         }
 
         return Ok(decrypted);
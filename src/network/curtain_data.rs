@@ -0,0 +1,84 @@
+use packed_struct::prelude::{PackedStruct, PrimitiveEnum_u8};
+
+use crate::{traits::CommandTrait, RbroadlinkError};
+
+/// The type of command to send to a curtain motor.
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone)]
+pub enum CurtainDataCommand {
+    /// Inform the motor to start opening the curtain.
+    Open = 0x01,
+
+    /// Inform the motor to start closing the curtain.
+    Close = 0x02,
+
+    /// Inform the motor to stop moving.
+    Stop = 0x03,
+
+    /// Inform the motor to return the position of the curtain.
+    GetPosition = 0x06,
+}
+
+/// A message used to control a curtain motor.
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb", size_bytes = "0x10")]
+pub struct CurtainDataMessage {
+    /// Magic value which marks the message as a motor command. Always 0x09.
+    #[packed_field(bytes = "0x00")]
+    magic1: u8,
+
+    /// Magic value. Always 0xBB.
+    #[packed_field(bytes = "0x02")]
+    magic2: u8,
+
+    /// Command flag for the message
+    #[packed_field(bytes = "0x03", ty = "enum")]
+    command: CurtainDataCommand,
+
+    /// An argument to the command.
+    #[packed_field(bytes = "0x04")]
+    attribute: u8,
+
+    /// Magic value. Always 0xFA.
+    #[packed_field(bytes = "0x09")]
+    magic3: u8,
+
+    /// Magic value. Always 0x44.
+    #[packed_field(bytes = "0x0A")]
+    magic4: u8,
+}
+
+impl CurtainDataMessage {
+    /// Create a new CurtainDataMessage.
+    pub fn new(command_type: CurtainDataCommand) -> CurtainDataMessage {
+        // Position queries need a fixed attribute
+        let attribute = match command_type {
+            CurtainDataCommand::GetPosition => 0x5D,
+            _ => 0x00,
+        };
+
+        return CurtainDataMessage {
+            magic1: 0x09,
+            magic2: 0xBB,
+            command: command_type,
+            attribute: attribute,
+            magic3: 0xFA,
+            magic4: 0x44,
+        };
+    }
+
+    /// Unpack the position of the curtain, as a percentage, from a motor's response.
+    pub fn unpack_position(bytes: &[u8]) -> Result<u8, RbroadlinkError> {
+        return bytes.get(0x04).copied().ok_or_else(|| {
+            RbroadlinkError::Decode(format!(
+                "Curtain response is too short! Expected at least 5 bytes, got {}",
+                bytes.len()
+            ))
+        });
+    }
+}
+
+impl CommandTrait for CurtainDataMessage {
+    fn packet_type() -> u16 {
+        return 0x006A;
+    }
+}
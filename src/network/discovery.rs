@@ -1,9 +1,12 @@
-use std::net::IpAddr;
+use std::{
+    net::Ipv4Addr,
+    time::Duration,
+};
 
-use chrono::prelude::{DateTime, Datelike, Local, Timelike};
+use chrono::prelude::{DateTime, Datelike, FixedOffset, Local, NaiveDateTime, Timelike};
 use packed_struct::prelude::PackedStruct;
 
-use crate::network::util::checksum;
+use crate::{network::util::checksum, RbroadlinkError};
 
 /// A message used to discover all broadlink devices on the network.
 #[derive(PackedStruct, Debug)]
@@ -79,39 +82,73 @@ pub struct DiscoveryResponse {
     pub is_locked: bool,
 }
 
+/// Options used when discovering devices on the network.
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryOptions {
+    /// The local time reported in the discovery message. Defaults to the current system time.
+    pub time: Option<NaiveDateTime>,
+
+    /// The timezone offset reported in the discovery message. Defaults to the system timezone.
+    pub utc_offset: Option<FixedOffset>,
+
+    /// How long to wait for further responses before ending discovery. Defaults to 10 seconds.
+    pub timeout: Option<Duration>,
+
+    /// The local address to bind the discovery socket to. Defaults to all interfaces.
+    pub bind_addr: Option<Ipv4Addr>,
+
+    /// The address to send the discovery message to, e.g. the directed broadcast address of
+    /// another subnet. Defaults to the limited broadcast address (255.255.255.255).
+    pub broadcast_addr: Option<Ipv4Addr>,
+
+    /// How long discovered remotes wait between checks for a code when learning. Defaults to
+    /// [crate::DEFAULT_LEARN_POLL_INTERVAL].
+    pub learn_poll_interval: Option<Duration>,
+}
+
+impl DiscoveryOptions {
+    /// Get the time to report in the discovery message, falling back to the system time
+    /// and timezone for any unset fields.
+    pub fn reported_time(&self) -> DateTime<Local> {
+        let now = Local::now();
+        let offset = self.utc_offset.unwrap_or(*now.offset());
+        let local_time = self.time.unwrap_or(now.naive_local());
+
+        return DateTime::from_naive_utc_and_offset(local_time - offset, offset);
+    }
+}
+
 impl DiscoveryMessage {
-    /// Create a new DiscoveryMessage.
+    /// Create a new DiscoveryMessage, asking devices to respond to the specified address.
+    ///
+    /// Note: The device only supports IPv4.
     pub fn new(
-        addr: IpAddr,
+        addr: Ipv4Addr,
         port: u16,
         time: Option<DateTime<Local>>,
-    ) -> Result<DiscoveryMessage, String> {
+    ) -> Result<DiscoveryMessage, RbroadlinkError> {
         // Get the time
         let time = match time {
             Some(t) => t,
             None => Local::now(),
         };
 
-        // Get the ip addr. Note: The device only supports IPv4
-        let selected_ip = match addr {
-            IpAddr::V4(ipv4) => ipv4,
-            _ => return Err("Could not construct DiscoveryMessage! IP address is not IPv4".into()),
-        };
-
         // Reverse the IP octet
         // Note: This is needed since the packet expects it to be reversed due to LSB
-        let octets = selected_ip.octets();
+        let octets = addr.octets();
         let reversed_ip: [u8; 4] = [octets[3], octets[2], octets[1], octets[0]];
 
         // Chrono returns the information in u32, so we need to convert them here.
         // These conversions should, in theory, not fail. But we check nonetheless.
         let mut msg = construct_message(reversed_ip, port, time)
-            .map_err(|e| format!("Could not construct DiscoveryMessage! {}", e))?;
+            .map_err(|e| {
+                RbroadlinkError::InvalidInput(format!("Could not construct DiscoveryMessage! {}", e))
+            })?;
 
         // Calculate the checksum
         msg.checksum = checksum(
             &msg.pack()
-                .map_err(|e| format!("Could not pack DiscoveryMessage! {}", e))?,
+                .map_err(|e| RbroadlinkError::Encode(format!("Could not pack DiscoveryMessage! {}", e)))?,
         );
 
         return Ok(msg);
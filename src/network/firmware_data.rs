@@ -0,0 +1,27 @@
+use crate::{traits::CommandTrait, RbroadlinkError};
+
+/// The payload used to query the firmware version of a device.
+pub const FIRMWARE_VERSION_QUERY: [u8; 0x10] = [0x00; 0x10];
+
+/// A message used to query the firmware version of any device.
+#[derive(Debug)]
+pub struct FirmwareVersionMessage;
+
+impl FirmwareVersionMessage {
+    /// Unpack the firmware version from a device's response to [FIRMWARE_VERSION_QUERY].
+    pub fn unpack_version(bytes: &[u8]) -> Result<u16, RbroadlinkError> {
+        return match bytes.get(0x04..0x06) {
+            Some(version) => Ok(u16::from_le_bytes([version[0], version[1]])),
+            None => Err(RbroadlinkError::Decode(format!(
+                "Firmware version response is too short! Expected at least 6 bytes, got {}",
+                bytes.len()
+            ))),
+        };
+    }
+}
+
+impl CommandTrait for FirmwareVersionMessage {
+    fn packet_type() -> u16 {
+        return 0x0068;
+    }
+}
@@ -2,7 +2,7 @@ use packed_struct::prelude::{
     packed_bits, Integer, PackedStruct, PackedStructSlice, PrimitiveEnum_u8,
 };
 
-use crate::{network::util::compute_generic_checksum, traits::CommandTrait};
+use crate::{network::util::compute_generic_checksum, traits::CommandTrait, RbroadlinkError};
 
 /// The type of command to send to the unit.
 #[derive(PrimitiveEnum_u8, Debug, Copy, Clone)]
@@ -131,13 +131,13 @@ pub struct AirCondState {
 }
 
 impl AirCondState {
-    pub fn prepare_and_pack(&mut self) -> Result<Vec<u8>, String> {
+    pub fn prepare_and_pack(&mut self) -> Result<Vec<u8>, RbroadlinkError> {
         // set magic values before sending
         self.magic1 = 0x0f.into();
 
         Ok(self
             .pack()
-            .map_err(|e| format!("Could not pack message! {}", e))?
+            .map_err(|e| RbroadlinkError::Encode(format!("Could not pack message! {}", e)))?
             .to_vec())
     }
 
@@ -147,9 +147,11 @@ impl AirCondState {
     }
 
     /// Set target temperature from input.
-    pub fn set_target_temp(&mut self, input: f32) -> Result<(), String> {
+    pub fn set_target_temp(&mut self, input: f32) -> Result<(), RbroadlinkError> {
         if input < 16.0 || input > 32.0 {
-            return Err("Target temperature is out of range (16-32)".into());
+            return Err(RbroadlinkError::InvalidInput(
+                "Target temperature is out of range (16-32)".into(),
+            ));
         }
         // TODO: some units also have a 0.5 degree resolution, so in this
         // case the formula would be:
@@ -216,22 +218,32 @@ impl HvacDataMessage {
         };
     }
 
+    /// Build the payload of the query for the air conditioner's basic information.
+    ///
+    /// This still needs to be wrapped in a [crate::network::CommandMessage], e.g. by using
+    /// [crate::HvacDevice::build_info_query].
+    pub fn info_query() -> Result<Vec<u8>, RbroadlinkError> {
+        return HvacDataMessage::new(HvacDataCommand::GetAcInfo).pack_with_payload(&[]);
+    }
+
     /// Pack the HvacDataMessage with an associated payload.
-    pub fn pack_with_payload(mut self, payload: &[u8]) -> Result<Vec<u8>, String> {
+    pub fn pack_with_payload(mut self, payload: &[u8]) -> Result<Vec<u8>, RbroadlinkError> {
         // Calculate tyhe length of the payload
         self.data_length += <usize as TryInto<u16>>::try_into(payload.len())
-            .map_err(|e| format!("Payload is too long! {}", e))?;
+            .map_err(|e| RbroadlinkError::InvalidInput(format!("Payload is too long! {}", e)))?;
 
         // Add 10 bytes for the header
         self.payload_length = self
             .data_length
             .checked_add(10u16)
-            .ok_or_else(|| "Could not add the start buffer! Payload is too long")?;
+            .ok_or_else(|| {
+                RbroadlinkError::InvalidInput("Could not add the start buffer! Payload is too long".into())
+            })?;
 
         // Append the payload to the header
         let mut result = self
             .pack()
-            .map_err(|e| format!("Could not pack message! {}", e))?
+            .map_err(|e| RbroadlinkError::Encode(format!("Could not pack message! {}", e)))?
             .to_vec();
         result.extend(payload);
 
@@ -243,19 +255,19 @@ impl HvacDataMessage {
     }
 
     /// Unpack a HvacDataMessage and return the associated payload.
-    pub fn unpack_with_payload(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    pub fn unpack_with_payload(bytes: &[u8]) -> Result<Vec<u8>, RbroadlinkError> {
         // Unpack the header
         let command_header = HvacDataMessage::unpack_from_slice(&bytes[0..12])
-            .map_err(|e| format!("Could not unpack command from bytes! {}", e))?;
+            .map_err(|e| RbroadlinkError::Decode(format!("Could not unpack command from bytes! {}", e)))?;
 
         // Check total payload length:
         // get real size and substract 2 bytes length field for correct comparision
         let real_size: u16 = (bytes.len() as u16) - 2;
         if real_size != command_header.payload_length {
-            return Err(format!(
+            return Err(RbroadlinkError::Decode(format!(
                 "Command checksum does not match actual checksum! Expected {:#06X} got {:#06X}",
                 command_header.payload_length, real_size,
-            ));
+            )));
         }
 
         // Ensure that the checksums match
@@ -263,10 +275,11 @@ impl HvacDataMessage {
         let data_crc = u16::from_le_bytes([bytes[crc_offset], bytes[crc_offset + 1]]);
         let real_checksum = compute_generic_checksum(&bytes[0x02..crc_offset]);
         if data_crc != real_checksum {
-            return Err(format!(
-                "Data checksum does not match actual checksum! Expected {:#06X} got {:#06X}",
-                data_crc, real_checksum,
-            ));
+            let mismatch = RbroadlinkError::ChecksumMismatch {
+                expected: data_crc,
+                got: real_checksum,
+            };
+            return Err(mismatch.context("Data checksum does not match actual checksum!"));
         }
 
         // Extract the data:
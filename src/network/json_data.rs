@@ -0,0 +1,75 @@
+use serde_json::Value;
+
+use crate::RbroadlinkError;
+
+/// The size of the header which precedes the JSON of a message.
+const JSON_HEADER_SIZE: usize = 0x0E;
+
+/// The kind of a JSON message.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JsonDataFlag {
+    /// Inform the device to return its current state.
+    Read = 0x01,
+
+    /// Inform the device to change its state.
+    Write = 0x02,
+}
+
+/// The initial value of the checksum of a JSON message.
+const JSON_CHECKSUM_SEED: u16 = 0xC0AD;
+
+/// The offset of the first byte included in the checksum of a JSON message.
+const JSON_CHECKSUM_START: usize = 0x08;
+
+/// Packs a JSON object into the framing used by JSON-based devices (e.g. smart bulbs).
+///
+/// The frame is made of a header with the length of the message, a checksum, the flag and
+/// the length of the JSON, followed by the JSON itself. The checksum is the same for all
+/// JSON-based devices, and covers everything after it.
+pub fn pack_json_payload(flag: JsonDataFlag, state: &Value) -> Result<Vec<u8>, RbroadlinkError> {
+    let json = serde_json::to_vec(state)
+        .map_err(|e| RbroadlinkError::Encode(format!("Could not serialize JSON payload! {}", e)))?;
+    let length = u16::try_from(JSON_HEADER_SIZE - 2 + json.len()).map_err(|_| {
+        RbroadlinkError::InvalidInput(format!("JSON payload is too long! Got {} bytes", json.len()))
+    })?;
+
+    let mut packet = Vec::with_capacity(JSON_HEADER_SIZE + json.len());
+    packet.extend(length.to_le_bytes());
+    packet.extend([0xA5, 0xA5, 0x5A, 0x5A]);
+    packet.extend([0x00, 0x00]); // The checksum, calculated below
+    packet.extend([flag as u8, 0x0B]);
+    packet.extend((json.len() as u32).to_le_bytes());
+    packet.extend(json);
+
+    let sum = packet[JSON_CHECKSUM_START..]
+        .iter()
+        .fold(JSON_CHECKSUM_SEED, |sum, &byte| sum.wrapping_add(u16::from(byte)));
+    packet[0x06..0x08].copy_from_slice(&sum.to_le_bytes());
+
+    return Ok(packet);
+}
+
+/// Unpacks the JSON object from a JSON-based device's response.
+pub fn unpack_json_payload(bytes: &[u8]) -> Result<Value, RbroadlinkError> {
+    if bytes.len() < JSON_HEADER_SIZE {
+        return Err(RbroadlinkError::Decode(format!(
+            "JSON response is too short! Expected at least {} bytes, got {}",
+            JSON_HEADER_SIZE,
+            bytes.len()
+        )));
+    }
+
+    let length = u32::from_le_bytes([bytes[0x0A], bytes[0x0B], bytes[0x0C], bytes[0x0D]]) as usize;
+    let json = bytes
+        .get(JSON_HEADER_SIZE..JSON_HEADER_SIZE.saturating_add(length))
+        .ok_or_else(|| {
+            RbroadlinkError::Decode(format!(
+                "JSON response length field says {} but payload has {} bytes",
+                length,
+                bytes.len() - JSON_HEADER_SIZE
+            ))
+        })?;
+
+    return serde_json::from_slice(json)
+        .map_err(|e| RbroadlinkError::Decode(format!("Could not parse JSON response! {}", e)));
+}
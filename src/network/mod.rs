@@ -2,18 +2,42 @@
 //!
 //! Refer to the following for protocol information -> <https://github.com/mjg59/python-broadlink/blob/9ff6b2d48e58f005765088cdf3dc5cc553cdb01a/protocol.md>
 
+mod alarm_data;
 mod authentication;
+mod bulb_data;
 mod command;
+mod curtain_data;
 mod discovery;
+mod firmware_data;
 mod hvac_data;
+mod json_data;
+mod plug_data;
+mod power_strip_data;
 mod remote_data;
+mod sensor_data;
+mod settings_data;
+mod thermostat_data;
+mod transport;
+mod wall_switch_data;
 mod wireless_connection;
 
 pub mod util;
 
+pub use alarm_data::*;
 pub use authentication::*;
+pub use bulb_data::*;
 pub use command::*;
+pub use curtain_data::*;
 pub use discovery::*;
+pub use firmware_data::*;
 pub use hvac_data::*;
+pub use json_data::*;
+pub use plug_data::*;
+pub use power_strip_data::*;
 pub use remote_data::*;
+pub use sensor_data::*;
+pub use settings_data::*;
+pub use thermostat_data::*;
+pub use transport::*;
+pub use wall_switch_data::*;
 pub use wireless_connection::*;
@@ -0,0 +1,127 @@
+use packed_struct::prelude::{PackedStruct, PrimitiveEnum_u8};
+
+use crate::{traits::CommandTrait, RbroadlinkError};
+
+/// The type of command to send to a smart plug.
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone)]
+pub enum PlugDataCommand {
+    /// Inform the plug to return its current state.
+    GetState = 0x01,
+
+    /// Inform the plug to change its state.
+    SetState = 0x02,
+}
+
+/// The payload used to query the power draw of a plug reporting it as BCD (SP3S).
+pub const PLUG_ENERGY_QUERY: [u8; 10] =
+    [0x08, 0x00, 0xFE, 0x01, 0x05, 0x01, 0x00, 0x00, 0x00, 0x2D];
+
+/// The payload used to query the power draw of a plug reporting it as a little endian
+/// integer (SP mini+).
+pub const PLUG_MINI_ENERGY_QUERY: [u8; 16] = [
+    0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// The way in which a plug reports its power draw.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EnergyEncoding {
+    /// Hundredths of a watt as big endian BCD (SP3S).
+    Bcd,
+
+    /// Thousandths of a watt as a little endian integer (SP mini+).
+    LittleEndian,
+}
+
+impl EnergyEncoding {
+    /// Get the energy encoding used by a plug from its model code, or None if the plug
+    /// cannot measure its power draw.
+    pub fn from_model_code(model_code: u16) -> Option<EnergyEncoding> {
+        return match model_code {
+            0x9479 | 0x947A => Some(EnergyEncoding::Bcd),
+            0x2736 => Some(EnergyEncoding::LittleEndian),
+            _ => None,
+        };
+    }
+
+    /// Get the payload used to query the power draw of a plug using this encoding.
+    pub fn query(&self) -> &'static [u8] {
+        return match self {
+            EnergyEncoding::Bcd => &PLUG_ENERGY_QUERY,
+            EnergyEncoding::LittleEndian => &PLUG_MINI_ENERGY_QUERY,
+        };
+    }
+
+    /// Decode the power draw, in watts, from the response to [EnergyEncoding::query].
+    pub fn decode(&self, bytes: &[u8]) -> Result<f32, RbroadlinkError> {
+        if bytes.len() < 0x08 {
+            return Err(RbroadlinkError::Decode(format!(
+                "Energy response is too short! Expected at least 8 bytes, got {}",
+                bytes.len()
+            )));
+        }
+
+        return match self {
+            EnergyEncoding::Bcd => {
+                // Each nibble is a single decimal digit, most significant byte last
+                let mut value = 0u32;
+                for &byte in bytes[0x05..0x08].iter().rev() {
+                    let (high, low) = (byte >> 4, byte & 0x0F);
+                    if high > 9 || low > 9 {
+                        return Err(RbroadlinkError::Decode(format!(
+                            "Energy response is not valid BCD! Got {:#04X}",
+                            byte
+                        )));
+                    }
+
+                    value = value * 100 + u32::from(high) * 10 + u32::from(low);
+                }
+
+                Ok(value as f32 / 100.0)
+            }
+            EnergyEncoding::LittleEndian => {
+                let value = u32::from_le_bytes([bytes[0x04], bytes[0x05], bytes[0x06], 0x00]);
+
+                Ok(value as f32 / 1000.0)
+            }
+        };
+    }
+}
+
+/// A message used to control a smart plug.
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb", size_bytes = "0x10")]
+pub struct PlugDataMessage {
+    /// Command flag for the message
+    #[packed_field(bytes = "0x00", ty = "enum")]
+    command: PlugDataCommand,
+
+    /// The state of the plug. Bit 0 is the power.
+    #[packed_field(bytes = "0x04")]
+    state: u8,
+}
+
+impl PlugDataMessage {
+    /// Create a new PlugDataMessage.
+    pub fn new(command_type: PlugDataCommand, state: u8) -> PlugDataMessage {
+        return PlugDataMessage {
+            command: command_type,
+            state: state,
+        };
+    }
+
+    /// Unpack the state byte from a plug's response.
+    pub fn unpack_state(bytes: &[u8]) -> Result<u8, RbroadlinkError> {
+        return bytes.get(0x04).copied().ok_or_else(|| {
+            RbroadlinkError::Decode(format!(
+                "Plug response is too short! Expected at least 5 bytes, got {}",
+                bytes.len()
+            ))
+        });
+    }
+}
+
+impl CommandTrait for PlugDataMessage {
+    fn packet_type() -> u16 {
+        return 0x006A;
+    }
+}
@@ -0,0 +1,101 @@
+use packed_struct::prelude::{PackedStruct, PrimitiveEnum_u8};
+
+use crate::{traits::CommandTrait, RbroadlinkError};
+
+/// The type of command to send to a power strip.
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone)]
+pub enum PowerStripDataCommand {
+    /// Inform the power strip to return the state of its outlets.
+    GetState = 0x01,
+
+    /// Inform the power strip to change the state of some of its outlets.
+    SetState = 0x02,
+}
+
+/// A message used to control a power strip.
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb", size_bytes = "0x10")]
+pub struct PowerStripDataMessage {
+    /// The length of the message, excluding the length field
+    #[packed_field(bytes = "0x00")]
+    length: u8,
+
+    /// Magic bytes. Always 0xA5A55A5A
+    #[packed_field(bytes = "0x02:0x05", endian = "msb")]
+    magic: u32,
+
+    /// Checksum of the message, seeded with 0xAE
+    #[packed_field(bytes = "0x06")]
+    checksum: u8,
+
+    /// Unknown flag. Always 0xC0
+    #[packed_field(bytes = "0x07")]
+    _unknown: u8,
+
+    /// Command flag for the message
+    #[packed_field(bytes = "0x08", ty = "enum")]
+    command: PowerStripDataCommand,
+
+    /// Unknown flag. 0x03 when setting the state
+    #[packed_field(bytes = "0x0A")]
+    _unknown_set: u8,
+
+    /// A mask with one bit per outlet to change.
+    #[packed_field(bytes = "0x0D")]
+    mask: u8,
+
+    /// The new state of the outlets in the mask, one bit per outlet.
+    #[packed_field(bytes = "0x0E")]
+    state: u8,
+}
+
+impl PowerStripDataMessage {
+    /// Create a new message querying the state of all outlets.
+    pub fn get_state() -> PowerStripDataMessage {
+        return PowerStripDataMessage {
+            length: 0x0A,
+            magic: 0xA5A55A5A,
+            checksum: 0xAE,
+            _unknown: 0xC0,
+            command: PowerStripDataCommand::GetState,
+            _unknown_set: 0x00,
+            mask: 0x00,
+            state: 0x00,
+        };
+    }
+
+    /// Create a new message turning the outlets in the mask on or off.
+    pub fn set_state(mask: u8, on: bool) -> PowerStripDataMessage {
+        let state = if on { mask } else { 0x00 };
+
+        return PowerStripDataMessage {
+            length: 0x0D,
+            magic: 0xA5A55A5A,
+            checksum: 0xAEu8
+                .wrapping_add(0x04)
+                .wrapping_add(mask)
+                .wrapping_add(state),
+            _unknown: 0xC0,
+            command: PowerStripDataCommand::SetState,
+            _unknown_set: 0x03,
+            mask: mask,
+            state: state,
+        };
+    }
+
+    /// Unpack the state byte from a power strip's response. Bit N is the state of outlet N.
+    pub fn unpack_state(bytes: &[u8]) -> Result<u8, RbroadlinkError> {
+        return bytes.get(0x0E).copied().ok_or_else(|| {
+            RbroadlinkError::Decode(format!(
+                "Power strip response is too short! Expected at least 15 bytes, got {}",
+                bytes.len()
+            ))
+        });
+    }
+}
+
+impl CommandTrait for PowerStripDataMessage {
+    fn packet_type() -> u16 {
+        return 0x006A;
+    }
+}
@@ -1,6 +1,6 @@
 use packed_struct::prelude::{PackedStruct, PackedStructSlice, PrimitiveEnum_u8};
 
-use crate::traits::CommandTrait;
+use crate::{traits::CommandTrait, RbroadlinkError};
 
 /// The type of command to send with the remote's data.
 #[derive(PrimitiveEnum_u8, Debug, Copy, Clone)]
@@ -16,17 +16,22 @@ pub enum RemoteDataCommand {
 
     /// Inform the device to return the code learned.
     ///
-    /// The device will send back no data if no code has been learned.
+    /// The device answers with an error code (or no data) if no code has been learned.
     GetCode = 0x04,
 
     /// Inform the device to start sweeping for RF frequencies.
     SweepRfFrequencies = 0x19,
 
     /// Inform the device to stop sweeping for RF frequencies.
+    ///
+    /// This also makes the device exit learning mode.
     StopRfSweep = 0x1E,
 
     /// Inform the device to see if an RF frequency has been found during the sweep.
     CheckFrequency = 0x1A,
+
+    /// Inform the device to return the readings of its built-in sensors.
+    CheckSensors = 0x24,
 }
 
 /// A message used to inform a remote of data to blast.
@@ -52,23 +57,25 @@ impl RemoteDataMessage {
     }
 
     /// Pack the RemoteDataMessage with an associated payload.
-    pub fn pack_with_payload(mut self, payload: &[u8]) -> Result<Vec<u8>, String> {
+    pub fn pack_with_payload(mut self, payload: &[u8]) -> Result<Vec<u8>, RbroadlinkError> {
         // Calculate tyhe length of the payload
         self.payload_length = payload
             .len()
             .try_into()
-            .map_err(|e| format!("Payload is too long! {}", e))?;
+            .map_err(|e| RbroadlinkError::InvalidInput(format!("Payload is too long! {}", e)))?;
 
         // Add 4 for the needed stop sequence
         self.payload_length = self
             .payload_length
             .checked_add(4u16)
-            .ok_or_else(|| "Could not add the start buffer! Payload is too long")?;
+            .ok_or_else(|| {
+                RbroadlinkError::InvalidInput("Could not add the start buffer! Payload is too long".into())
+            })?;
 
         // Append the payload to the header
         let mut result = self
             .pack()
-            .map_err(|e| format!("Could not pack message! {}", e))?
+            .map_err(|e| RbroadlinkError::Encode(format!("Could not pack message! {}", e)))?
             .to_vec();
         result.extend(payload);
 
@@ -80,7 +87,7 @@ impl RemoteDataMessage {
     /// Note: The RemoteDataMessage will sometimes respond with unknown data,
     /// so this method returns no data at all if the response is not at least
     /// as large as the header.
-    pub fn unpack_with_payload(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    pub fn unpack_with_payload(bytes: &[u8]) -> Result<Vec<u8>, RbroadlinkError> {
         // This is somewhat different than other messages. If there is no data, the
         // device will send us anywhere from 1 to 3 bytes, which is useless. So
         // we just discard anything that is below the threshold.
@@ -90,11 +97,12 @@ impl RemoteDataMessage {
 
         // Attempt to unpack the header
         let info = RemoteDataMessage::unpack_from_slice(&bytes[0x00..0x06])
-            .map_err(|e| format!("Could not unpack remote data response! {}", e))?;
+            .map_err(|e| RbroadlinkError::Decode(format!("Could not unpack remote data response! {}", e)))?;
 
         // Extract the payload
-        let payload_length = usize::from(info.payload_length + 1);
-        let payload = &bytes[0x06..payload_length];
+        // Note: The length covers everything after the length field itself.
+        let payload_end = (usize::from(info.payload_length) + 2).clamp(0x06, bytes.len());
+        let payload = &bytes[0x06..payload_end];
 
         return Ok(payload.to_vec());
     }
@@ -0,0 +1,34 @@
+use packed_struct::prelude::{PackedStruct, PrimitiveEnum_u8};
+
+use crate::traits::CommandTrait;
+
+/// The type of command to send to an environment sensor.
+#[derive(PrimitiveEnum_u8, Debug, Copy, Clone)]
+pub enum SensorDataCommand {
+    /// Inform the sensor to return its current readings.
+    CheckSensors = 0x01,
+}
+
+/// A message used to query an environment sensor.
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb", size_bytes = "0x10")]
+pub struct SensorDataMessage {
+    /// Command flag for the message
+    #[packed_field(bytes = "0x00", ty = "enum")]
+    command: SensorDataCommand,
+}
+
+impl SensorDataMessage {
+    /// Create a new SensorDataMessage.
+    pub fn new(command_type: SensorDataCommand) -> SensorDataMessage {
+        return SensorDataMessage {
+            command: command_type,
+        };
+    }
+}
+
+impl CommandTrait for SensorDataMessage {
+    fn packet_type() -> u16 {
+        return 0x006A;
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{traits::CommandTrait, RbroadlinkError};
+
+/// The maximum length of a device name, in bytes.
+pub const MAX_NAME_LENGTH: usize = 62;
+
+/// A message used to change the name and lock status of any device.
+#[derive(Debug)]
+pub struct SettingsMessage;
+
+impl SettingsMessage {
+    /// Pack the payload which sets the name and lock status of a device.
+    ///
+    /// Note: The device always receives both settings, so the current lock status must be
+    /// passed in when only renaming the device.
+    pub fn pack_settings(name: &str, is_locked: bool) -> Result<Vec<u8>, RbroadlinkError> {
+        let name = name.as_bytes();
+        if name.len() > MAX_NAME_LENGTH {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Device name is too long! Expected at most {} bytes, got {}",
+                MAX_NAME_LENGTH,
+                name.len()
+            )));
+        }
+
+        let mut payload = vec![0u8; 0x50];
+        payload[0x04..0x04 + name.len()].copy_from_slice(name);
+        payload[0x43] = u8::from(is_locked);
+
+        return Ok(payload);
+    }
+}
+
+impl CommandTrait for SettingsMessage {
+    fn packet_type() -> u16 {
+        return 0x006A;
+    }
+}
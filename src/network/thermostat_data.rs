@@ -0,0 +1,99 @@
+use packed_struct::prelude::{PackedStruct, PackedStructSlice};
+
+use crate::{network::util::compute_modbus_crc16, traits::CommandTrait, RbroadlinkError};
+
+/// The Modbus request used to read the full status of a thermostat.
+pub const THERMOSTAT_STATUS_QUERY: [u8; 6] = [0x01, 0x03, 0x00, 0x00, 0x00, 0x16];
+
+/// A message used to communicate with a Hysen thermostat.
+///
+/// The payload is a Modbus request (or response) prefixed with its length and followed by
+/// its Modbus CRC16 (see [compute_modbus_crc16]).
+#[derive(PackedStruct, Debug, Default)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb", size_bytes = "0x02")]
+pub struct ThermostatDataMessage {
+    /// The length of the payload, including the trailing checksum
+    #[packed_field(bytes = "0x00:0x01")]
+    payload_length: u16,
+}
+
+impl ThermostatDataMessage {
+    /// Create a new ThermostatDataMessage.
+    pub fn new() -> ThermostatDataMessage {
+        return ThermostatDataMessage { payload_length: 0 };
+    }
+
+    /// Create the Modbus request used to set the target temperature, in half degrees Celsius.
+    pub fn target_temperature_request(half_degrees: u8) -> [u8; 6] {
+        return [0x01, 0x06, 0x00, 0x01, 0x00, half_degrees];
+    }
+
+    /// Create the Modbus request used to set the clock, with the day of the week counted from
+    /// Monday (1) to Sunday (7).
+    pub fn time_request(hour: u8, minute: u8, second: u8, weekday: u8) -> [u8; 11] {
+        return [0x01, 0x10, 0x00, 0x08, 0x00, 0x02, 0x04, hour, minute, second, weekday];
+    }
+
+    /// Pack the ThermostatDataMessage with an associated Modbus request.
+    pub fn pack_with_payload(mut self, payload: &[u8]) -> Result<Vec<u8>, RbroadlinkError> {
+        // Add 2 bytes for the checksum
+        self.payload_length = u16::try_from(payload.len() + 2)
+            .map_err(|e| RbroadlinkError::InvalidInput(format!("Payload is too long! {}", e)))?;
+
+        // Append the payload to the header
+        let mut result = self
+            .pack()
+            .map_err(|e| RbroadlinkError::Encode(format!("Could not pack message! {}", e)))?
+            .to_vec();
+        result.extend(payload);
+
+        // Compute and add the final payload checksum
+        let checksum = compute_modbus_crc16(payload);
+        result.extend(checksum.to_le_bytes().to_vec());
+
+        return Ok(result);
+    }
+
+    /// Unpack a ThermostatDataMessage and return the associated Modbus response.
+    pub fn unpack_with_payload(bytes: &[u8]) -> Result<Vec<u8>, RbroadlinkError> {
+        if bytes.len() < 2 {
+            return Err(RbroadlinkError::Decode(format!(
+                "Thermostat response is too short! Expected at least 2 bytes, got {}",
+                bytes.len()
+            )));
+        }
+
+        // Unpack the header
+        let header = ThermostatDataMessage::unpack_from_slice(&bytes[0..2])
+            .map_err(|e| RbroadlinkError::Decode(format!("Could not unpack command from bytes! {}", e)))?;
+
+        // The length covers the response and its checksum, but not the length itself
+        let crc_offset = usize::from(header.payload_length);
+        if crc_offset < 2 || crc_offset + 2 > bytes.len() {
+            return Err(RbroadlinkError::Decode(format!(
+                "Thermostat response length is invalid! Expected at most {:#06X} got {:#06X}",
+                bytes.len() - 2,
+                header.payload_length,
+            )));
+        }
+
+        // Ensure that the checksums match
+        let data_crc = u16::from_le_bytes([bytes[crc_offset], bytes[crc_offset + 1]]);
+        let real_checksum = compute_modbus_crc16(&bytes[0x02..crc_offset]);
+        if data_crc != real_checksum {
+            let mismatch = RbroadlinkError::ChecksumMismatch {
+                expected: data_crc,
+                got: real_checksum,
+            };
+            return Err(mismatch.context("Data checksum does not match actual checksum!"));
+        }
+
+        return Ok(bytes[0x02..crc_offset].to_vec());
+    }
+}
+
+impl CommandTrait for ThermostatDataMessage {
+    fn packet_type() -> u16 {
+        return 0x006A;
+    }
+}
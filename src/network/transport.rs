@@ -0,0 +1,98 @@
+use std::{future::Future, net::SocketAddrV4, pin::Pin, time::Duration};
+#[cfg(any(test, feature = "testing"))]
+use std::{collections::VecDeque, sync::Mutex};
+
+use crate::{
+    network::util::{send_and_receive_one, send_and_receive_one_async},
+    traits::TransportTrait,
+    RbroadlinkError,
+};
+
+/// A transport which exchanges packets with a device over UDP.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UdpTransport;
+
+impl TransportTrait for UdpTransport {
+    fn send_and_receive(
+        &self,
+        packet: &[u8],
+        addr: SocketAddrV4,
+        read_timeout: Duration,
+    ) -> Result<Vec<u8>, RbroadlinkError> {
+        return send_and_receive_one(packet, *addr.ip(), None, None, |_, bytes, _| {
+            return Ok(bytes.to_vec());
+        }, Some(read_timeout), Some(addr.port()));
+    }
+
+    fn send_and_receive_async<'a>(
+        &'a self,
+        packet: &'a [u8],
+        addr: SocketAddrV4,
+        read_timeout: Duration,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, RbroadlinkError>> + Send + 'a>> {
+        return Box::pin(send_and_receive_one_async(packet, *addr.ip(), 0, None, |_, bytes, _| {
+            return Ok(bytes.to_vec());
+        }, read_timeout, Some(addr.port())));
+    }
+}
+
+/// A transport which records sent packets and answers with canned responses, for testing
+/// without a physical device.
+///
+/// Responses are returned in the order they were added. Once all responses are used, every
+/// further exchange times out.
+#[cfg(any(test, feature = "testing"))]
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    /// The responses which have not been returned yet.
+    responses: Mutex<VecDeque<Vec<u8>>>,
+
+    /// The packets sent so far, along with their destination.
+    sent: Mutex<Vec<(SocketAddrV4, Vec<u8>)>>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl MockTransport {
+    /// Create a new MockTransport with no responses.
+    pub fn new() -> MockTransport {
+        return MockTransport::default();
+    }
+
+    /// Add a raw response to return to the next unanswered packet.
+    pub fn push_response(&self, response: Vec<u8>) {
+        self.responses.lock().unwrap().push_back(response);
+    }
+
+    /// Get the packets sent so far, along with their destination.
+    pub fn sent(&self) -> Vec<(SocketAddrV4, Vec<u8>)> {
+        return self.sent.lock().unwrap().clone();
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl TransportTrait for MockTransport {
+    fn send_and_receive(
+        &self,
+        packet: &[u8],
+        addr: SocketAddrV4,
+        read_timeout: Duration,
+    ) -> Result<Vec<u8>, RbroadlinkError> {
+        self.sent.lock().unwrap().push((addr, packet.to_vec()));
+
+        return self
+            .responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or(RbroadlinkError::Timeout(read_timeout));
+    }
+
+    fn send_and_receive_async<'a>(
+        &'a self,
+        packet: &'a [u8],
+        addr: SocketAddrV4,
+        read_timeout: Duration,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, RbroadlinkError>> + Send + 'a>> {
+        return Box::pin(std::future::ready(self.send_and_receive(packet, addr, read_timeout)));
+    }
+}
@@ -1,13 +1,32 @@
 //! Set of utility methods useful when working with network requests.
 
 use std::{
+    future::{ready, Future},
+    io,
     net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    pin::Pin,
     slice::ChunksExact,
     time::Duration,
 };
 
+use log::{debug, warn};
+use socket2::{Domain, Protocol, Socket, Type};
 use tokio::time::timeout;
 
+use crate::RbroadlinkError;
+
+/// The default size of the buffer used for receiving responses, in bytes.
+pub const DEFAULT_RECV_BUFFER_SIZE: usize = 8092;
+
+/// The default port that messages are sent to.
+pub const DEFAULT_DESTINATION_PORT: u16 = 80;
+
+/// The default amount of time to wait for responses when blocking.
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The amount of receive errors in a row after which collecting responses gives up.
+pub const MAX_CONSECUTIVE_RECEIVE_ERRORS: u32 = 8;
+
 /// Computes the checksum of a slice of bytes.
 ///
 /// The checksum is computed by summing all of the bytes with 0xBEAF and masking
@@ -46,49 +65,153 @@ pub fn compute_generic_checksum(buf: &[u8]) -> u16 {
     state as u16
 }
 
-/// Returns the first available non-local address or the passed IP, if present.
-pub fn local_ip_or(ip: Option<Ipv4Addr>) -> Result<IpAddr, String> {
-    Ok(match ip {
-        Some(ip) => IpAddr::V4(ip),
-        None => get_if_addrs::get_if_addrs()
-            .map_err(|e| {
-                format!(
-                    "Could not automatically determine machine IP address. {}",
-                    e
-                )
-            })?
+/// Computes the Modbus CRC16 of a slice of bytes.
+///
+/// This is the reflected CRC16 with polynomial 0xA001 and an initial value of 0xFFFF,
+/// used by Hysen thermostats instead of [compute_generic_checksum].
+pub fn compute_modbus_crc16(buf: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in buf {
+        crc ^= u16::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 0x0001 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 };
+        }
+    }
+
+    return crc;
+}
+
+/// Returns the passed IP, if present, or else the best IPv4 address of this machine as
+/// chosen by [select_local_ipv4].
+///
+/// Note: Broadlink devices only support IPv4, so IPv6 addresses are never returned.
+pub fn local_ip_or(ip: Option<Ipv4Addr>) -> Result<Ipv4Addr, RbroadlinkError> {
+    if let Some(ip) = ip {
+        return Ok(ip);
+    }
+
+    let addrs: Vec<IpAddr> = get_if_addrs::get_if_addrs()
+        .map_err(|e| {
+            RbroadlinkError::from(e).context("Could not automatically determine machine IP address.")
+        })?
+        .iter()
+        .map(|x| x.ip())
+        .collect();
+
+    return select_local_ipv4(&addrs).ok_or(RbroadlinkError::NoIpv4Interface);
+}
+
+/// Chooses the IPv4 address to use for talking to devices out of the addresses of this
+/// machine, regardless of their order.
+///
+/// IPv6, loopback and unspecified addresses are skipped, and link-local (169.254.x.x)
+/// addresses are only used if there is no other address.
+pub fn select_local_ipv4(addrs: &[IpAddr]) -> Option<Ipv4Addr> {
+    let mut candidates = addrs.iter().filter_map(|addr| match addr {
+        IpAddr::V4(ip) if !ip.is_loopback() && !ip.is_unspecified() => Some(*ip),
+        _ => None,
+    });
+
+    let first = candidates.clone().next()?;
+
+    return Some(candidates.find(|ip| !ip.is_link_local()).unwrap_or(first));
+}
+
+/// Allocates a buffer for receiving responses, defaulting to [DEFAULT_RECV_BUFFER_SIZE] bytes.
+///
+/// The buffer is allocated once per call of the send helpers and reused for every response.
+pub fn recv_buffer(size: Option<usize>) -> Result<Vec<u8>, RbroadlinkError> {
+    let size = size.unwrap_or(DEFAULT_RECV_BUFFER_SIZE);
+    if size == 0 {
+        return Err(RbroadlinkError::InvalidInput(
+            "Receive buffer size must be greater than zero!".into(),
+        ));
+    }
+
+    return Ok(vec![0u8; size]);
+}
+
+/// Ensures that a received datagram fit into the receive buffer, returning its length.
+///
+/// The excess of a datagram larger than the buffer is silently discarded, so responses which
+/// fill the whole buffer are reported as truncated.
+pub fn check_truncation(len: usize, buffer: &[u8]) -> Result<usize, RbroadlinkError> {
+    if len >= buffer.len() {
+        return Err(RbroadlinkError::Decode(format!(
+            "Response was truncated! It did not fit into the receive buffer of {} bytes.",
+            buffer.len()
+        )));
+    }
+
+    return Ok(len);
+}
+
+/// Returns the IPv4 address of the network interface with the specified name.
+pub fn interface_ip(name: &str) -> Result<Ipv4Addr, RbroadlinkError> {
+    let interfaces = get_if_addrs::get_if_addrs().map_err(|e| {
+        RbroadlinkError::from(e).context("Could not list network interfaces.")
+    })?;
+
+    let found = interfaces.iter().find_map(|x| match x.ip() {
+        IpAddr::V4(ip) if x.name == name => Some(ip),
+        _ => None,
+    });
+
+    return found.ok_or_else(|| {
+        let mut available: Vec<&str> = interfaces
             .iter()
-            .find(|x| x.ip().is_ipv4() && !x.ip().is_loopback())
-            .ok_or("Could not find a local IPv4 address!")?
-            .ip(),
-    })
+            .filter(|x| x.ip().is_ipv4())
+            .map(|x| x.name.as_str())
+            .collect();
+        available.dedup();
+
+        RbroadlinkError::InvalidInput(format!(
+            "Could not find an IPv4 network interface named '{}'! Available interfaces: {}",
+            name,
+            available.join(", ")
+        ))
+    });
+}
+
+/// Binds a UDP socket which allows the address to be reused, so that sockets can be created
+/// in quick succession (or side by side) on the same port.
+pub(crate) fn bind_reusable(bind_addr: SocketAddr) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&bind_addr.into())?;
+
+    return Ok(socket.into());
 }
 
 /// Sends a message and returns the received response.
 fn send_and_receive_impl(
     msg: &[u8],
+    bind_addr: SocketAddr,
     addr: Ipv4Addr,
-    port: Option<u16>,
-) -> Result<UdpSocket, String> {
+    read_timeout: Duration,
+    destination_port: Option<u16>,
+) -> Result<UdpSocket, RbroadlinkError> {
     // Set up the socket addresses
-    let unspecified_addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, port.unwrap_or(0)));
-    let destination_addr = SocketAddr::from((addr, 80));
+    let destination_addr =
+        SocketAddr::from((addr, destination_port.unwrap_or(DEFAULT_DESTINATION_PORT)));
 
     // Set up the communication socket
     // Note: We need to enable support for broadcast
-    let socket = UdpSocket::bind(unspecified_addr)
-        .map_err(|e| format!("Could not bind to any port. {}", e))?;
+    let socket = bind_reusable(bind_addr)
+        .map_err(|e| RbroadlinkError::from(e).context("Could not bind to any port."))?;
     socket
         .set_broadcast(true)
-        .map_err(|e| format!("Could not enable broadcast. {}", e))?;
+        .map_err(|e| RbroadlinkError::from(e).context("Could not enable broadcast."))?;
 
     // Send the message
     socket
-        .set_read_timeout(Some(Duration::new(10, 0)))
-        .map_err(|e| format!("Could not set read timeout! {}", e))?;
+        .set_read_timeout(Some(read_timeout))
+        .map_err(|e| RbroadlinkError::from(e).context("Could not set read timeout!"))?;
     socket
         .send_to(&msg, destination_addr)
-        .map_err(|e| format!("Could not broadcast message! {}", e))?;
+        .map_err(|e| RbroadlinkError::from(e).context("Could not broadcast message!"))?;
 
     return Ok(socket);
 }
@@ -98,50 +221,99 @@ async fn send_and_receive_impl_async(
     msg: &[u8],
     addr: Ipv4Addr,
     port: u16,
-) -> Result<tokio::net::UdpSocket, String> {
+    destination_port: Option<u16>,
+) -> Result<tokio::net::UdpSocket, RbroadlinkError> {
     // Set up the socket addresses
     let unspecified_addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, port));
-    let destination_addr = SocketAddr::from((addr, 80));
+    let destination_addr =
+        SocketAddr::from((addr, destination_port.unwrap_or(DEFAULT_DESTINATION_PORT)));
 
     // Set up the communication socket
     // Note: We need to enable support for broadcast
 
-    // std::net::UdpSocket::set_nonblocking()
-    let socket = tokio::net::UdpSocket::bind(unspecified_addr).await
-        .map_err(|e| format!("Could not bind to any port. {}", e))?;
+    let socket = bind_reusable(unspecified_addr)
+        .and_then(|socket| {
+            socket.set_nonblocking(true)?;
+            return tokio::net::UdpSocket::from_std(socket);
+        })
+        .map_err(|e| RbroadlinkError::from(e).context("Could not bind to any port."))?;
 
     socket
         .set_broadcast(true)
-        .map_err(|e| format!("Could not enable broadcast. {}", e))?;
+        .map_err(|e| RbroadlinkError::from(e).context("Could not enable broadcast."))?;
 
     // Send the message
     // socket.set_read_timeout(Duration::from_secs(3))
     //     .map_err(|e| format!("Could not set read timeout! {}", e))?;
     socket
         .send_to(&msg, destination_addr).await
-        .map_err(|e| format!("Could not broadcast message! {}", e))?;
+        .map_err(|e| RbroadlinkError::from(e).context("Could not broadcast message!"))?;
 
     return Ok(socket);
 }
 
 /// Sends a message and returns the as many received responses as possible (within a timeout).
+///
+/// The read timeout defaults to [DEFAULT_READ_TIMEOUT] and is reset after each response.
+/// The destination port defaults to [DEFAULT_DESTINATION_PORT].
 pub fn send_and_receive_many<I, T>(
     msg: &[u8],
     addr: Ipv4Addr,
     port: Option<u16>,
+    buffer_size: Option<usize>,
+    cb: T,
+    read_timeout: Option<Duration>,
+    destination_port: Option<u16>,
+) -> Result<Vec<I>, RbroadlinkError>
+    where
+        T: Fn(usize, &[u8], SocketAddr) -> Result<I, RbroadlinkError>,
+{
+    let bind_addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, port.unwrap_or(0)));
+
+    return send_and_receive_many_from(
+        msg,
+        bind_addr,
+        addr,
+        buffer_size,
+        cb,
+        read_timeout,
+        destination_port,
+    );
+}
+
+/// Sends a message from a socket bound to the specified local address and returns as many
+/// received responses as possible (within a timeout).
+///
+/// See [send_and_receive_many] for the defaults of the optional arguments.
+pub fn send_and_receive_many_from<I, T>(
+    msg: &[u8],
+    bind_addr: SocketAddr,
+    addr: Ipv4Addr,
+    buffer_size: Option<usize>,
     cb: T,
-) -> Result<Vec<I>, String>
+    read_timeout: Option<Duration>,
+    destination_port: Option<u16>,
+) -> Result<Vec<I>, RbroadlinkError>
     where
-        T: Fn(usize, &[u8], SocketAddr) -> Result<I, String>,
+        T: Fn(usize, &[u8], SocketAddr) -> Result<I, RbroadlinkError>,
 {
     // Get the socket
-    let socket = send_and_receive_impl(msg, addr, port)
-        .map_err(|e| format!("Could not create socket for message sending! {}", e))?;
+    let read_timeout = read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT);
+    let socket = send_and_receive_impl(msg, bind_addr, addr, read_timeout, destination_port)
+        .map_err(|e| e.context("Could not create socket for message sending!"))?;
 
     // Transform the results
     let mut results: Vec<I> = vec![];
-    let mut recv_buffer = [0u8; 8092];
+    let mut recv_buffer = recv_buffer(buffer_size)?;
     while let Ok((bytes_received, addr)) = socket.recv_from(&mut recv_buffer) {
+        // A truncated response must not end the collection of the others
+        let bytes_received = match check_truncation(bytes_received, &recv_buffer) {
+            Ok(len) => len,
+            Err(e) => {
+                warn!("Skipping response from {}: {}", addr, e);
+                continue;
+            }
+        };
         results.push(cb(bytes_received, &recv_buffer[0..bytes_received], addr)?);
     }
     drop(socket);
@@ -149,70 +321,173 @@ pub fn send_and_receive_many<I, T>(
 }
 
 /// Sends a message and returns the as many received responses as possible (within a timeout).
+///
+/// See [send_and_receive_many_async_with] for when collection ends.
 pub async fn send_and_receive_many_async<I, T>(
     msg: &[u8],
     addr: Ipv4Addr,
     port: u16,
+    buffer_size: Option<usize>,
+    cb: T,
+    read_timeout: Duration,
+    destination_port: Option<u16>,
+) -> Result<Vec<I>, RbroadlinkError>
+    where
+        T: Fn(usize, &[u8], SocketAddr) -> Result<I, RbroadlinkError>,
+{
+    return send_and_receive_many_async_with(
+        msg,
+        addr,
+        port,
+        buffer_size,
+        |len, bytes, addr| ready(cb(len, &bytes, addr)),
+        read_timeout,
+        destination_port,
+    ).await;
+}
+
+/// Sends a message and returns as many received responses as possible (within a timeout),
+/// awaiting an async callback for each response as soon as it is received.
+///
+/// Receive errors are logged and skipped, so collection ends once no response has been
+/// received within the timeout, or after [MAX_CONSECUTIVE_RECEIVE_ERRORS] errors in a row.
+pub async fn send_and_receive_many_async_with<I, T, F>(
+    msg: &[u8],
+    addr: Ipv4Addr,
+    port: u16,
+    buffer_size: Option<usize>,
     cb: T,
     read_timeout: Duration,
-) -> Result<Vec<I>, String>
+    destination_port: Option<u16>,
+) -> Result<Vec<I>, RbroadlinkError>
     where
-        T: Fn(usize, &[u8], SocketAddr) -> Result<I, String>,
+        T: FnMut(usize, Vec<u8>, SocketAddr) -> F,
+        F: Future<Output = Result<I, RbroadlinkError>>,
 {
     // Get the socket
-    let socket = send_and_receive_impl_async(msg, addr, port).await
-        .map_err(|e| format!("Could not create socket for message sending! {}", e))?;
+    let socket = send_and_receive_impl_async(msg, addr, port, destination_port).await
+        .map_err(|e| e.context("Could not create socket for message sending!"))?;
 
-    // Transform the results
+    let results = collect_responses_async(&socket, buffer_size, cb, read_timeout).await;
+
+    drop(socket);
+    return results;
+}
+
+/// A socket which datagrams can be received from without blocking.
+pub(crate) trait DatagramSocket {
+    /// Receives a single datagram into the buffer, returning its length and sender.
+    fn recv_datagram<'a>(
+        &'a self,
+        buffer: &'a mut [u8],
+    ) -> Pin<Box<dyn Future<Output = io::Result<(usize, SocketAddr)>> + Send + 'a>>;
+}
+
+impl DatagramSocket for tokio::net::UdpSocket {
+    fn recv_datagram<'a>(
+        &'a self,
+        buffer: &'a mut [u8],
+    ) -> Pin<Box<dyn Future<Output = io::Result<(usize, SocketAddr)>> + Send + 'a>> {
+        return Box::pin(self.recv_from(buffer));
+    }
+}
+
+/// Receives datagrams from a socket until none arrives within the timeout, awaiting an async
+/// callback for each of them.
+///
+/// Receive errors are logged and skipped, unless there are [MAX_CONSECUTIVE_RECEIVE_ERRORS]
+/// of them in a row. Truncated datagrams are logged and skipped as well.
+pub(crate) async fn collect_responses_async<S, I, T, F>(
+    socket: &S,
+    buffer_size: Option<usize>,
+    mut cb: T,
+    read_timeout: Duration,
+) -> Result<Vec<I>, RbroadlinkError>
+    where
+        S: DatagramSocket,
+        T: FnMut(usize, Vec<u8>, SocketAddr) -> F,
+        F: Future<Output = Result<I, RbroadlinkError>>,
+{
     let mut results: Vec<I> = vec![];
-    let mut recv_buffer = [0u8; 8092];
+    let mut recv_buffer = recv_buffer(buffer_size)?;
+    let mut consecutive_errors = 0;
     loop {
-        match timeout(read_timeout, socket.recv_from(&mut recv_buffer)).await {
+        match timeout(read_timeout, socket.recv_datagram(&mut recv_buffer)).await {
             Ok(Ok((len, addr))) => {
-                // println!("received MSG");
-                results.push(cb(len, &recv_buffer[0..len], addr)?)
-                // Process the received data
+                consecutive_errors = 0;
+                debug!("Received {} bytes from {}", len, addr);
+                let len = match check_truncation(len, &recv_buffer) {
+                    Ok(len) => len,
+                    Err(e) => {
+                        warn!("Skipping response from {}: {}", addr, e);
+                        continue;
+                    }
+                };
+                results.push(cb(len, recv_buffer[0..len].to_vec(), addr).await?);
             }
             Ok(Err(e)) => {
-                // eprintln!("Error receiving data: {}", e);
-                break;
+                // Keep listening for the remaining responses, unless the socket keeps failing
+                warn!("Error receiving data: {}", e);
+                consecutive_errors += 1;
+                if consecutive_errors >= MAX_CONSECUTIVE_RECEIVE_ERRORS {
+                    warn!("Giving up after {} receive errors in a row", consecutive_errors);
+                    break;
+                }
             }
             Err(_) => {
-                // println!("Receive operation timed out");
+                debug!("Receive operation timed out after {:?}", read_timeout);
                 break;
             }
         };
     }
 
-    // while let Ok((bytes_received, addr)) = socket.recv_from(&mut recv_buffer).await {
-    //     results.push(cb(bytes_received, &recv_buffer[0..bytes_received], addr)?);
-    // }
-    drop(socket);
     return Ok(results);
 }
 
 /// Sends a message and returns the first received response.
+///
+/// The read timeout defaults to [DEFAULT_READ_TIMEOUT] and the destination port defaults to
+/// [DEFAULT_DESTINATION_PORT].
 pub fn send_and_receive_one<I, T>(
     msg: &[u8],
     addr: Ipv4Addr,
     port: Option<u16>,
+    buffer_size: Option<usize>,
     cb: T,
-) -> Result<I, String>
+    read_timeout: Option<Duration>,
+    destination_port: Option<u16>,
+) -> Result<I, RbroadlinkError>
     where
-        T: Fn(usize, &[u8], SocketAddr) -> Result<I, String>,
+        T: Fn(usize, &[u8], SocketAddr) -> Result<I, RbroadlinkError>,
 {
     // Get the socket
-    let socket = send_and_receive_impl(msg, addr, port)
-        .map_err(|e| format!("Could not create socket for message sending! {}", e))?;
+    let read_timeout = read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT);
+    let bind_addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, port.unwrap_or(0)));
+    let socket = send_and_receive_impl(msg, bind_addr, addr, read_timeout, destination_port)
+        .map_err(|e| e.context("Could not create socket for message sending!"))?;
 
     // Transform the result
-    let mut recv_buffer = [0u8; 8092];
-    if let Ok((bytes_received, addr)) = socket.recv_from(&mut recv_buffer) {
-        drop(socket);
-        return Ok(cb(bytes_received, &recv_buffer[0..bytes_received], addr)?);
-    }
+    let mut recv_buffer = recv_buffer(buffer_size)?;
+    let received = socket.recv_from(&mut recv_buffer);
     drop(socket);
-    return Err("No response within timeout!".into());
+
+    return match received {
+        Ok((bytes_received, addr)) => {
+            let bytes_received = check_truncation(bytes_received, &recv_buffer)?;
+            Ok(cb(bytes_received, &recv_buffer[0..bytes_received], addr)?)
+        }
+        Err(e) => Err(recv_error(e, read_timeout)),
+    };
+}
+
+/// Converts an error returned while waiting for a response, keeping timeouts distinct from
+/// other failures (e.g. connection refused).
+pub(crate) fn recv_error(e: io::Error, read_timeout: Duration) -> RbroadlinkError {
+    return match e.kind() {
+        // Blocking sockets report an elapsed read timeout as either of these, depending on the OS
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => RbroadlinkError::Timeout(read_timeout),
+        _ => RbroadlinkError::from(e).context("Error receiving!"),
+    };
 }
 
 /// Sends a message and returns the first received response.
@@ -220,30 +495,33 @@ pub async fn send_and_receive_one_async<I, T>(
     msg: &[u8],
     addr: Ipv4Addr,
     port: u16,
+    buffer_size: Option<usize>,
     cb: T,
     response_timeout: Duration,
-) -> Result<I, String>
+    destination_port: Option<u16>,
+) -> Result<I, RbroadlinkError>
     where
-        T: Fn(usize, &[u8], SocketAddr) -> Result<I, String>,
+        T: Fn(usize, &[u8], SocketAddr) -> Result<I, RbroadlinkError>,
 {
     // Get the socket
-    let socket = send_and_receive_impl_async(msg, addr, port).await
-        .map_err(|e| format!("Could not create socket for message sending! {}", e))?;
+    let socket = send_and_receive_impl_async(msg, addr, port, destination_port).await
+        .map_err(|e| e.context("Could not create socket for message sending!"))?;
 
     // Transform the result
-    let mut recv_buffer = [0u8; 8092];
+    let mut recv_buffer = recv_buffer(buffer_size)?;
     let result = match timeout(response_timeout, socket.recv_from(&mut recv_buffer)).await {
         Ok(Ok((len, addr))) => {
+            let len = check_truncation(len, &recv_buffer)?;
             Ok(cb(len, &recv_buffer[0..len], addr)?)
             // Process the received data
         }
         Ok(Err(e)) => {
-            // eprintln!("Error receiving data: {}", e);
-            Err("Error receiving".to_string())
+            warn!("Error receiving data: {}", e);
+            Err(RbroadlinkError::from(e).context("Error receiving!"))
         }
         Err(_) => {
-            // println!("Receive operation timed out");
-            Err("timeout".to_string())
+            debug!("Receive operation timed out after {:?}", response_timeout);
+            Err(RbroadlinkError::Timeout(response_timeout))
         }
     };
 
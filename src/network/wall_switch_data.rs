@@ -0,0 +1,51 @@
+use serde_json::{json, Value};
+
+use crate::{
+    network::{pack_json_payload, JsonDataFlag},
+    traits::CommandTrait,
+    RbroadlinkError,
+};
+
+/// A message used to control a wall switch.
+#[derive(Debug, Clone)]
+pub struct WallSwitchDataMessage {
+    /// Whether the message reads or changes the state of the switch.
+    flag: JsonDataFlag,
+
+    /// The state values to change.
+    state: Value,
+}
+
+impl WallSwitchDataMessage {
+    /// Create a message which queries the state of the switch.
+    pub fn query() -> WallSwitchDataMessage {
+        return WallSwitchDataMessage {
+            flag: JsonDataFlag::Read,
+            state: json!({}),
+        };
+    }
+
+    /// Create a message which turns a single gang on or off. Gangs are indexed from 0.
+    ///
+    /// Note: The index is not checked, see [crate::WallSwitch::set_gang].
+    pub fn gang(index: u8, on: bool) -> WallSwitchDataMessage {
+        let mut state = serde_json::Map::new();
+        state.insert(format!("pwr{}", u16::from(index) + 1), json!(u8::from(on)));
+
+        return WallSwitchDataMessage {
+            flag: JsonDataFlag::Write,
+            state: Value::Object(state),
+        };
+    }
+
+    /// Pack the message into the payload sent to the switch.
+    pub fn pack(&self) -> Result<Vec<u8>, RbroadlinkError> {
+        return pack_json_payload(self.flag, &self.state);
+    }
+}
+
+impl CommandTrait for WallSwitchDataMessage {
+    fn packet_type() -> u16 {
+        return 0x006A;
+    }
+}
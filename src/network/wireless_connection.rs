@@ -1,10 +1,14 @@
+use std::fmt;
+
 use packed_struct::prelude::PackedStruct;
+use zeroize::Zeroize;
 
-use crate::network::util::checksum;
+use crate::{network::util::checksum, RbroadlinkError};
 
 /// WirelessConnection represents the credentials for connecting to a wireless
 /// network.
-#[derive(Debug)]
+///
+/// Note: The password is redacted when formatted with [fmt::Debug].
 pub enum WirelessConnection<'a> {
     /// None represents a network with no security
     None(&'a str),
@@ -22,8 +26,30 @@ pub enum WirelessConnection<'a> {
     WPA(&'a str, &'a str),
 }
 
+impl fmt::Debug for WirelessConnection<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mode, ssid, has_password) = match self {
+            WirelessConnection::None(ssid) => ("None", ssid, false),
+            WirelessConnection::WEP(ssid, _) => ("WEP", ssid, true),
+            WirelessConnection::WPA1(ssid, _) => ("WPA1", ssid, true),
+            WirelessConnection::WPA2(ssid, _) => ("WPA2", ssid, true),
+            WirelessConnection::WPA(ssid, _) => ("WPA", ssid, true),
+        };
+
+        let mut debug = f.debug_struct(mode);
+        debug.field("ssid", ssid);
+        if has_password {
+            debug.field("password", &"***");
+        }
+
+        return debug.finish();
+    }
+}
+
 /// WirelessConnectionMessage represents a message used for instructing a device
 /// to connect to a specified wireless network.
+///
+/// Note: The password is zeroed when the message is dropped.
 #[derive(PackedStruct, Debug)]
 #[packed_struct(bit_numbering = "msb0", endian = "lsb", size_bytes = "136")]
 pub struct WirelessConnectionMessage {
@@ -56,9 +82,15 @@ pub struct WirelessConnectionMessage {
     security_mode: u8,
 }
 
+impl Drop for WirelessConnectionMessage {
+    fn drop(&mut self) {
+        self.password.zeroize();
+    }
+}
+
 impl WirelessConnection<'_> {
     /// Pack a WirelessCOnnection into its network transport format.
-    pub fn to_message(&self) -> Result<WirelessConnectionMessage, String> {
+    pub fn to_message(&self) -> Result<WirelessConnectionMessage, RbroadlinkError> {
         let empty_pass = "";
         let (ssid, pass, security_mode) = match self {
             WirelessConnection::None(ssid) => (ssid, &empty_pass, 0),
@@ -68,9 +100,40 @@ impl WirelessConnection<'_> {
             WirelessConnection::WPA(ssid, pass) => (ssid, pass, 4),
         };
 
-        // Ensure that the fields aren't too long
-        if ssid.len() > 32 {
-            return Err("Could not use provided SSID! SSID longer than 32 characters.".into());
+        // Ensure that the fields fit in the message
+        if ssid.is_empty() || ssid.len() > 32 {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Could not use provided SSID! SSID must be between 1 and 32 bytes, got {}.",
+                ssid.len()
+            )));
+        }
+        if pass.len() > 32 {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Could not use provided password! Password must be at most 32 bytes, got {}.",
+                pass.len()
+            )));
+        }
+
+        // Ensure that the password is valid for the security mode
+        match self {
+            WirelessConnection::None(_) => {}
+            WirelessConnection::WEP(_, _) => {
+                // WEP keys are either 5 / 13 characters or 10 / 26 hex digits
+                if ![5, 10, 13, 26].contains(&pass.len()) {
+                    return Err(RbroadlinkError::InvalidInput(format!(
+                        "Could not use provided password! WEP keys must be 5, 10, 13 or 26 bytes, got {}.",
+                        pass.len()
+                    )));
+                }
+            }
+            _ => {
+                if pass.len() < 8 {
+                    return Err(RbroadlinkError::InvalidInput(format!(
+                        "Could not use provided password! WPA passwords must be at least 8 bytes, got {}.",
+                        pass.len()
+                    )));
+                }
+            }
         }
 
         // Copy over the strings into their fixed buffers
@@ -95,16 +158,16 @@ impl WirelessConnection<'_> {
             ssid: ssid_fixed,
             password: pass_fixed,
             ssid_length: u8::try_from(ssid.len()).map_err(|e| {
-                format!(
+                RbroadlinkError::InvalidInput(format!(
                     "Could not use provided SSID! SSID is too long (max 32 characters). {}",
                     e
-                )
+                ))
             })?,
             password_length: u8::try_from(pass.len()).map_err(|e| {
-                format!(
+                RbroadlinkError::InvalidInput(format!(
                     "Could not use provided password! Password is too long (max 32 characters). {}",
                     e
-                )
+                ))
             })?,
 
             security_mode: security_mode,
@@ -113,7 +176,9 @@ impl WirelessConnection<'_> {
         // Add the checksum into the msg
         msg.checksum = checksum(
             &msg.pack()
-                .map_err(|e| format!("Could not pack WirelessConnectionMessage! {}", e))?,
+                .map_err(|e| {
+                    RbroadlinkError::Encode(format!("Could not pack WirelessConnectionMessage! {}", e))
+                })?,
         );
 
         // Return the newly created message
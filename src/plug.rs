@@ -0,0 +1,106 @@
+use std::net::Ipv4Addr;
+
+use packed_struct::PackedStruct;
+use phf::phf_map;
+
+use crate::{
+    network::{
+        DiscoveryResponse, EnergyEncoding, PlugDataCommand, PlugDataMessage,
+    },
+    Device, DeviceInfo, RbroadlinkError,
+};
+
+/// A mapping of smart plug device codes to their friendly model equivalent.
+pub const PLUG_CODES: phf::Map<u16, &'static str> = phf_map! {
+    0x2711u16 => "SP2",
+    0x2720u16 => "SP mini",
+    0x2736u16 => "SP mini+",
+    0x753Eu16 => "SP3",
+    0x7D00u16 => "SP3 EU",
+    0x9479u16 => "SP3S-US",
+    0x947Au16 => "SP3S-EU",
+};
+
+/// A broadlink smart plug.
+#[derive(Debug, Clone)]
+pub struct SmartPlug {
+    /// Base information about the plug.
+    pub info: DeviceInfo,
+}
+
+impl SmartPlug {
+    /// Create a new SmartPlug.
+    ///
+    /// Note: This should not be called directly. Please use [Device::from_ip] or
+    /// [Device::list] instead.
+    pub fn new(name: &str, addr: Ipv4Addr, response: DiscoveryResponse) -> SmartPlug {
+        // Get the type of plug
+        let friendly_model: String = PLUG_CODES
+            .get(&response.model_code)
+            .unwrap_or(&"Unknown")
+            .to_string();
+
+        return Self {
+            info: DeviceInfo::from_response(name, addr, &response, "Plug", &friendly_model),
+        };
+    }
+
+    /// Turn the plug on or off.
+    pub fn set_power(&self, on: bool) -> Result<(), RbroadlinkError> {
+        self.send_command(PlugDataCommand::SetState, u8::from(on))
+            .map_err(|e| e.context("Could not set power state of plug!"))?;
+
+        return Ok(());
+    }
+
+    /// Returns true if the plug is currently on.
+    pub fn get_power(&self) -> Result<bool, RbroadlinkError> {
+        let response = self
+            .send_command(PlugDataCommand::GetState, 0)
+            .map_err(|e| e.context("Could not get power state of plug!"))?;
+        let state = PlugDataMessage::unpack_state(&response)?;
+
+        return Ok(state & 0x01 != 0);
+    }
+
+    /// Read the current power draw of the plug, in watts.
+    ///
+    /// Note: Only plugs with metering support this (SP3S and SP mini+).
+    pub fn get_energy(&self) -> Result<f32, RbroadlinkError> {
+        let encoding = EnergyEncoding::from_model_code(self.info.model_code)
+            .ok_or(RbroadlinkError::UnsupportedDevice(self.info.model_code))?;
+
+        let response = self
+            .send_payload(encoding.query())
+            .map_err(|e| e.context("Could not get energy from plug!"))?;
+
+        return encoding.decode(&response);
+    }
+
+    /// Sends a raw command to the plug.
+    /// Note: Try to avoid using this method in favor of [SmartPlug::set_power], [SmartPlug::get_power], etc.
+    pub fn send_command(
+        &self,
+        command: PlugDataCommand,
+        state: u8,
+    ) -> Result<Vec<u8>, RbroadlinkError> {
+        // Construct the data message
+        let msg = PlugDataMessage::new(command, state);
+        let packed = msg.pack().map_err(|e| {
+            RbroadlinkError::Encode(format!("Could not pack plug data message! {}", e))
+        })?;
+
+        return self.send_payload(&packed);
+    }
+
+    /// Sends an already packed payload to the plug.
+    fn send_payload(&self, payload: &[u8]) -> Result<Vec<u8>, RbroadlinkError> {
+        // We cast this object to a generic device in order to make use of the shared
+        // helper utilities.
+        let generic_device = Device::Plug { plug: self.clone() };
+
+        return generic_device
+            .send_command::<PlugDataMessage>(payload)
+            .map_err(|e| e.context("Could not send command!"));
+    }
+}
@@ -0,0 +1,102 @@
+use std::net::Ipv4Addr;
+
+use packed_struct::PackedStruct;
+use phf::phf_map;
+
+use crate::{
+    network::{
+        DiscoveryResponse, PowerStripDataMessage,
+    },
+    Device, DeviceInfo, RbroadlinkError,
+};
+
+/// A mapping of power strip device codes to their friendly model equivalent.
+pub const POWER_STRIP_CODES: phf::Map<u16, &'static str> = phf_map! {
+    0x4EB5u16 => "MP1",
+    0x4EF7u16 => "MP1 (Honyar)",
+    0x4F1Bu16 => "MP1-1K3S2U",
+    0x4F65u16 => "MP1-1K3S2U",
+};
+
+/// The amount of independently switchable outlets on a power strip.
+pub const POWER_STRIP_OUTLETS: u8 = 4;
+
+/// A broadlink power strip with multiple outlets.
+#[derive(Debug, Clone)]
+pub struct PowerStrip {
+    /// Base information about the power strip.
+    pub info: DeviceInfo,
+}
+
+impl PowerStrip {
+    /// Create a new PowerStrip.
+    ///
+    /// Note: This should not be called directly. Please use [Device::from_ip] or
+    /// [Device::list] instead.
+    pub fn new(name: &str, addr: Ipv4Addr, response: DiscoveryResponse) -> PowerStrip {
+        // Get the type of power strip
+        let friendly_model: String = POWER_STRIP_CODES
+            .get(&response.model_code)
+            .unwrap_or(&"Unknown")
+            .to_string();
+
+        return Self {
+            info: DeviceInfo::from_response(name, addr, &response, "PowerStrip", &friendly_model),
+        };
+    }
+
+    /// Turn a single outlet on or off. Outlets are indexed from 0.
+    pub fn set_outlet(&self, index: u8, on: bool) -> Result<(), RbroadlinkError> {
+        if index >= POWER_STRIP_OUTLETS {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Outlet index must be less than {}! Got {}",
+                POWER_STRIP_OUTLETS, index
+            )));
+        }
+
+        let msg = PowerStripDataMessage::set_state(1 << index, on);
+        self.send_command(msg)
+            .map_err(|e| e.context("Could not set outlet state of power strip!"))?;
+
+        return Ok(());
+    }
+
+    /// Returns whether each of the outlets is currently on.
+    pub fn get_states(&self) -> Result<[bool; 4], RbroadlinkError> {
+        let response = self
+            .send_command(PowerStripDataMessage::get_state())
+            .map_err(|e| e.context("Could not get outlet states of power strip!"))?;
+        let state = PowerStripDataMessage::unpack_state(&response)?;
+
+        return Ok(decode_outlet_states(state));
+    }
+
+    /// Sends a raw command to the power strip.
+    /// Note: Try to avoid using this method in favor of [PowerStrip::set_outlet], [PowerStrip::get_states], etc.
+    pub fn send_command(&self, msg: PowerStripDataMessage) -> Result<Vec<u8>, RbroadlinkError> {
+        // We cast this object to a generic device in order to make use of the shared
+        // helper utilities.
+        let generic_device = Device::PowerStrip {
+            strip: self.clone(),
+        };
+
+        // Pack the data message
+        let packed = msg.pack().map_err(|e| {
+            RbroadlinkError::Encode(format!("Could not pack power strip data message! {}", e))
+        })?;
+
+        return generic_device
+            .send_command::<PowerStripDataMessage>(&packed)
+            .map_err(|e| e.context("Could not send command!"));
+    }
+}
+
+/// Decodes the state byte of a power strip into the state of each outlet.
+pub(crate) fn decode_outlet_states(state: u8) -> [bool; 4] {
+    return [
+        state & 0x01 != 0,
+        state & 0x02 != 0,
+        state & 0x04 != 0,
+        state & 0x08 != 0,
+    ];
+}
@@ -1,11 +1,19 @@
-use std::{net::Ipv4Addr, time::Duration};
+use std::{
+    future::Future,
+    net::Ipv4Addr,
+    time::{Duration, Instant},
+};
 
+use log::warn;
 use phf::phf_map;
+use tokio::sync::oneshot;
 
 use crate::{
-    constants,
-    network::{util::reverse_mac, DiscoveryResponse, RemoteDataCommand, RemoteDataMessage},
-    Device, DeviceInfo,
+    codes::{is_code_type, try_encode_durations, BroadlinkCode, CodeKind, CodeLibrary, RfBand, IR_CODE_TYPE},
+    network::{
+        DiscoveryResponse, RemoteDataCommand, RemoteDataMessage,
+    },
+    Device, DeviceInfo, RbroadlinkError,
 };
 
 /// A mapping of remote device codes to their friendly model equivalent.
@@ -14,19 +22,125 @@ pub const REMOTE_CODES: phf::Map<u16, &'static str> = phf_map! {
     0x5213u16 => "RM4 Pro",
     0x5218u16 => "RM4C Pro",
     0x6026u16 => "RM4 Pro",
-    0x6184u16 => "RMC4 Pro",
+    0x6184u16 => "RM4C Pro",
     0x61A2u16 => "RM4 Pro",
     0x649Bu16 => "RM4 Pro",
     0x653Cu16 => "RM4 Pro",
+    0x51DAu16 => "RM4 Mini",
+    0x520Cu16 => "RM4 Mini",
     0x5216u16 => "RM4 Mini",
+    0x521Cu16 => "RM4 Mini",
+    0x610Eu16 => "RM4 Mini",
+    0x62BCu16 => "RM4 Mini",
+    0x648Du16 => "RM4 Mini",
+    0x653Au16 => "RM4 Mini",
     0x520Du16 => "RM4C Mini",
+    0x6070u16 => "RM4C Mini",
+    0x610Fu16 => "RM4C Mini",
+    0x62BEu16 => "RM4C Mini",
+    0x6539u16 => "RM4C Mini",
+    0x5211u16 => "RM4C Mate",
+    0x5209u16 => "RM4 TV Mate",
+    0x5212u16 => "RM4 TV Mate",
+    0x6364u16 => "RM4S",
 };
 
+/// The features supported by a remote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteCapabilities {
+    /// Whether the remote can learn and transmit IR codes.
+    pub ir: bool,
+
+    /// Whether the remote can learn and transmit RF codes.
+    pub rf: bool,
+
+    /// Whether the remote has a built-in temperature / humidity sensor.
+    pub sensors: bool,
+}
+
+impl RemoteCapabilities {
+    /// Get the capabilities of a remote from its model code.
+    ///
+    /// Note: Only the Pro models support RF and sensors. Every other remote (Mini, Mate,
+    /// TV Mate, etc.) is IR only.
+    pub fn from_model_code(model_code: u16) -> RemoteCapabilities {
+        return match model_code {
+            0x520B | 0x5213 | 0x5218 | 0x6026 | 0x6184 | 0x61A2 | 0x649B | 0x653C => {
+                RemoteCapabilities {
+                    ir: true,
+                    rf: true,
+                    sensors: true,
+                }
+            }
+            _ => RemoteCapabilities {
+                ir: true,
+                rf: false,
+                sensors: false,
+            },
+        };
+    }
+}
+
+/// A reading of the built-in sensors of a remote.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorReading {
+    /// The temperature, in degrees Celsius.
+    pub temperature: f32,
+
+    /// The relative humidity, as a percentage.
+    pub humidity: f32,
+}
+
+/// The progress of learning a code, as reported to the callback of
+/// [RemoteDevice::learn_ir_with_status] and [RemoteDevice::learn_rf_with_status].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LearnStatus {
+    /// The device is being put into learning mode.
+    EnteringLearnMode,
+
+    /// The device is sweeping for the RF frequency in use. Reported on each poll.
+    SweepingFrequency,
+
+    /// The device found the RF frequency in use.
+    FrequencyFound,
+
+    /// The device is waiting for a code. Reported on each poll.
+    WaitingForSignal,
+
+    /// The device captured a code.
+    DataCaptured,
+}
+
+/// The default amount of time to wait for a code when learning.
+const LEARN_DEADLINE: Duration = Duration::from_secs(30);
+
+/// The default amount of time to wait between checks when learning.
+pub const DEFAULT_LEARN_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// The shortest amount of time waited between checks when learning. Shorter intervals are
+/// raised to this, as polling faster floods the device without capturing codes any sooner.
+pub const MIN_LEARN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The amount of time to wait for each response from the device when learning without blocking.
+const LEARN_RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The amount of time to wait for the device to acknowledge exiting learning mode when
+/// learning is cancelled.
+const CANCEL_RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The error codes with which the device answers a check for a learned code while it has
+/// not captured one yet (read error and storage error).
+const NOTHING_CAPTURED_ERRORS: [i16; 2] = [-10, -5];
+
 /// A broadlink device capable of transmitting IR / RF codes.
 #[derive(Debug, Clone)]
 pub struct RemoteDevice {
     /// Base information about the remote.
     pub info: DeviceInfo,
+
+    /// How long to wait between checks for a code when learning. Defaults to
+    /// [DEFAULT_LEARN_POLL_INTERVAL]. See [RemoteDevice::with_learn_poll_interval].
+    learn_poll_interval: Duration,
 }
 
 impl RemoteDevice {
@@ -42,47 +156,103 @@ impl RemoteDevice {
             .to_string();
 
         return Self {
-            info: DeviceInfo {
-                address: addr,
-                mac: reverse_mac(response.mac),
-                model_code: response.model_code,
-                friendly_type: "Remote".into(),
-                friendly_model: friendly_model,
-                name: name.into(),
-                auth_id: 0, // This will be populated when authenticated.
-                key: constants::INITIAL_KEY,
-                is_locked: response.is_locked,
-            },
+            info: DeviceInfo::from_response(name, addr, &response, "Remote", &friendly_model),
+            learn_poll_interval: DEFAULT_LEARN_POLL_INTERVAL,
         };
     }
 
+    /// Wait the specified amount of time between checks for a code when learning.
+    ///
+    /// Shorter intervals make learning more responsive, at the cost of more traffic to the
+    /// device. Intervals shorter than [MIN_LEARN_POLL_INTERVAL] are raised to it.
+    pub fn with_learn_poll_interval(mut self, interval: Duration) -> RemoteDevice {
+        self.learn_poll_interval = interval;
+
+        return self;
+    }
+
+    /// Get the amount of time waited between checks for a code when learning, which is
+    /// never shorter than [MIN_LEARN_POLL_INTERVAL].
+    pub fn learn_poll_interval(&self) -> Duration {
+        return self.learn_poll_interval.max(MIN_LEARN_POLL_INTERVAL);
+    }
+
+    /// Get the features supported by this remote.
+    pub fn capabilities(&self) -> RemoteCapabilities {
+        return RemoteCapabilities::from_model_code(self.info.model_code);
+    }
+
     /// Attempt to learn an IR code.
     ///
     /// When learning, the remote's LED will light up orange. Simply long press
     /// (and release) the IR button while pointing the control at the device until the light
     /// turns off.
-    pub fn learn_ir(&self) -> Result<Vec<u8>, String> {
+    pub fn learn_ir(&self) -> Result<Vec<u8>, RbroadlinkError> {
+        return self.learn_ir_with_deadline(LEARN_DEADLINE);
+    }
+
+    /// Attempt to learn an IR code, giving up once the deadline has passed.
+    ///
+    /// See [RemoteDevice::learn_ir] for more info.
+    ///
+    /// Returns [RbroadlinkError::NothingLearned] if the device kept answering without
+    /// capturing a code, and [RbroadlinkError::Timeout] if the device stopped answering.
+    ///
+    /// Note: The deadline is checked between polls, so this may overshoot the deadline by
+    /// at most the time taken by a single request to the device.
+    pub fn learn_ir_with_deadline(&self, deadline: Duration) -> Result<Vec<u8>, RbroadlinkError> {
+        return self.learn_ir_with_status(deadline, |_| {});
+    }
+
+    /// Attempt to learn an IR code, giving up once the deadline has passed. The progress of
+    /// learning is reported to the callback.
+    ///
+    /// See [RemoteDevice::learn_ir] for more info.
+    ///
+    /// Note: The callback is called from the polling loop, so it should return well within
+    /// the poll interval.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "learn_ir", skip_all, fields(ip = %self.info.address)))]
+    pub fn learn_ir_with_status<F>(&self, deadline: Duration, mut on_status: F) -> Result<Vec<u8>, RbroadlinkError>
+    where
+        F: FnMut(LearnStatus),
+    {
+        let deadline = Instant::now() + deadline;
+
         // First enter learning...
+        on_status(LearnStatus::EnteringLearnMode);
         self.send_command(&[], RemoteDataCommand::StartLearningIR)
-            .map_err(|e| format!("Could not enter learning mode! {}", e))?;
+            .map_err(|e| e.context("Could not enter learning mode!"))?;
 
         // Block until we learn the code or timeout
-        let attempts = 10;
-        let interval = Duration::from_secs(3);
-        for _ in 0..attempts {
-            // Sleep before trying again
-            std::thread::sleep(interval);
-
-            let code: Vec<u8> = self
-                .send_command(&[], RemoteDataCommand::GetCode)
-                .map_err(|e| format!("Could not check code status of device! {}", e))?;
-            if code.len() != 0 {
-                return Ok(code);
-            }
+        let code = poll_until(deadline, self.learn_poll_interval(), || {
+            on_status(LearnStatus::WaitingForSignal);
+            return self.check_code();
+        })?;
+        if code.is_some() {
+            on_status(LearnStatus::DataCaptured);
+        }
+
+        // If we haven't gotten anything up until now, then the device never heard a code
+        return code.ok_or_else(|| {
+            RbroadlinkError::NothingLearned(
+                "Could not learn IR code! The device reported that no code was captured.".into(),
+            )
+        });
+    }
+
+    /// Attempt to learn an IR code, giving up once the deadline has passed, and then
+    /// optionally send the captured code once so that the appliance's reaction can be checked.
+    ///
+    /// See [RemoteDevice::learn_ir] for more info.
+    pub fn learn_and_verify_ir(&self, deadline: Duration, verify: bool) -> Result<Vec<u8>, RbroadlinkError> {
+        let code = self.learn_ir_with_deadline(deadline)?;
+
+        if verify {
+            self.send_code(&code)
+                .map_err(|e| e.context("Could not send learned code for verification!"))?;
         }
 
-        // If we haven't gotten anything up until now, then we failed
-        return Err("Could not learn IR code! Operation timed out.".into());
+        return Ok(code);
     }
 
     /// Attempts to learn an RF code.
@@ -96,82 +266,529 @@ impl RemoteDevice {
     ///   2) Long press (and release) the RF button until the orange LED turns off
     ///      and then back on.
     ///   3) Press the RF button once more normally until the orange LED turns off.
-    pub fn learn_rf(&self) -> Result<Vec<u8>, String> {
+    pub fn learn_rf(&self) -> Result<Vec<u8>, RbroadlinkError> {
+        return self.learn_rf_with_deadline(LEARN_DEADLINE);
+    }
+
+    /// Attempts to learn an RF code, giving up once the deadline has passed. The deadline
+    /// covers both stages of learning.
+    ///
+    /// See [RemoteDevice::learn_rf] for more info.
+    ///
+    /// Note: The deadline is checked between polls, so this may overshoot the deadline by
+    /// at most the time taken by a single request to the device.
+    pub fn learn_rf_with_deadline(&self, deadline: Duration) -> Result<Vec<u8>, RbroadlinkError> {
+        return self.learn_rf_with_status(deadline, |_| {});
+    }
+
+    /// Attempts to learn an RF code, giving up once the deadline has passed. The deadline
+    /// covers both stages of learning, and the progress of learning is reported to the callback.
+    ///
+    /// See [RemoteDevice::learn_rf] for more info.
+    ///
+    /// Note: The callback is called from the polling loop, so it should return well within
+    /// the poll interval.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "learn_rf", skip_all, fields(ip = %self.info.address)))]
+    pub fn learn_rf_with_status<F>(&self, deadline: Duration, mut on_status: F) -> Result<Vec<u8>, RbroadlinkError>
+    where
+        F: FnMut(LearnStatus),
+    {
+        let deadline = Instant::now() + deadline;
+
+        self.sweep_frequency_until(deadline, &mut on_status)?;
+        return self.learn_rf_data_until(deadline, &mut on_status);
+    }
+
+    /// Sweeps for the RF frequency in use, giving up once the timeout has passed. This is the
+    /// first stage of [RemoteDevice::learn_rf].
+    ///
+    /// Long press (and release) the RF button while the device is sweeping. Once this returns,
+    /// call [RemoteDevice::learn_rf_data] to capture the code itself.
+    pub fn sweep_frequency(&self, timeout: Duration) -> Result<(), RbroadlinkError> {
+        return self.sweep_frequency_until(Instant::now() + timeout, &mut |_| {});
+    }
+
+    /// Captures an RF code once its frequency has been found by [RemoteDevice::sweep_frequency],
+    /// giving up once the timeout has passed. This is the second stage of [RemoteDevice::learn_rf].
+    ///
+    /// Press the RF button once more normally while the device is learning.
+    pub fn learn_rf_data(&self, timeout: Duration) -> Result<Vec<u8>, RbroadlinkError> {
+        return self.learn_rf_data_until(Instant::now() + timeout, &mut |_| {});
+    }
+
+    /// Attempts to learn an RF code in a known band, giving up once the timeout has passed.
+    ///
+    /// Unlike [RemoteDevice::learn_rf], the frequency is not swept, which some devices need in
+    /// order to learn codes reliably. Simply press the RF button once while the device is
+    /// learning. The learned code is tagged with the band, so that [RemoteDevice::send_code]
+    /// transmits it on the right frequency.
+    ///
+    /// Note: Only the Pro models support RF. They can learn codes in both bands.
+    pub fn learn_rf_band(&self, band: RfBand, timeout: Duration) -> Result<Vec<u8>, RbroadlinkError> {
+        if !self.capabilities().rf {
+            return Err(RbroadlinkError::Unsupported(format!(
+                "{} does not support RF codes!",
+                self.info.friendly_model
+            )));
+        }
+
+        let deadline = Instant::now() + timeout;
+
+        // Enter RF learning mode on the requested frequency
+        self.send_command(&band.frequency_khz().to_le_bytes(), RemoteDataCommand::StartLearningRF)
+            .map_err(|e| e.context(format!("Could not enter learning mode for {}!", band)))?;
+
+        // Block until we learn the code or timeout
+        if let Some(code) = poll_until(deadline, self.learn_poll_interval(), || self.check_code())? {
+            return Ok(tag_rf_band(code, band));
+        }
+
+        // If we haven't gotten anything up until now, then the device never heard a code
+        self.send_command(&[], RemoteDataCommand::StopRfSweep)
+            .map_err(|e| e.context("Could not exit learning mode!"))?;
+        return Err(RbroadlinkError::NothingLearned(
+            "Could not learn RF code! The device reported that no code was captured.".into(),
+        ));
+    }
+
+    /// Sweeps for the RF frequency in use until the deadline.
+    fn sweep_frequency_until<F>(&self, deadline: Instant, on_status: &mut F) -> Result<(), RbroadlinkError>
+    where
+        F: FnMut(LearnStatus),
+    {
+        if !self.capabilities().rf {
+            return Err(RbroadlinkError::Unsupported(format!(
+                "{} does not support RF codes!",
+                self.info.friendly_model
+            )));
+        }
+
         // Start sweeping for the type of frequency in use
+        on_status(LearnStatus::EnteringLearnMode);
         self.send_command(&[], RemoteDataCommand::SweepRfFrequencies)
-            .map_err(|e| format!("Could not start sweeping frequencies! {}", e))?;
+            .map_err(|e| e.context("Could not start sweeping frequencies!"))?;
 
         // Wait for the frequency to be identified
-        let attempts = 10;
-        let interval = Duration::from_secs(3);
-        let mut frequency_found = false;
-        for _ in 0..attempts {
-            // Sleep before trying again
-            std::thread::sleep(interval);
-
+        let frequency_found = poll_until(deadline, self.learn_poll_interval(), || {
+            on_status(LearnStatus::SweepingFrequency);
             let frequency: Vec<u8> = self
                 .send_command(&[], RemoteDataCommand::CheckFrequency)
-                .map_err(|e| format!("Could not check code status of device! {}", e))?;
-            if frequency[0] == 1 {
-                frequency_found = true;
-                break;
-            }
-        }
+                .map_err(|e| e.context("Device did not respond while checking for a frequency!"))?;
+
+            return Ok(if frequency.first() == Some(&1) { Some(()) } else { None });
+        })?;
 
         // Error out if no frequency is found
-        if !frequency_found {
+        if frequency_found.is_none() {
             self.send_command(&[], RemoteDataCommand::StopRfSweep)
-                .map_err(|e| format!("Could not cancel RF sweep! {}", e))?;
-            return Err("Could not determine frequency!".into());
+                .map_err(|e| e.context("Could not cancel RF sweep!"))?;
+            return Err(RbroadlinkError::NothingLearned(
+                "Could not determine frequency! The device reported that no RF signal was found.".into(),
+            ));
         }
 
+        on_status(LearnStatus::FrequencyFound);
+        return Ok(());
+    }
+
+    /// Captures an RF code on the previously found frequency until the deadline.
+    fn learn_rf_data_until<F>(&self, deadline: Instant, on_status: &mut F) -> Result<Vec<u8>, RbroadlinkError>
+    where
+        F: FnMut(LearnStatus),
+    {
         // Enter RF learning mode
         self.send_command(&[], RemoteDataCommand::StartLearningRF)
-            .map_err(|e| format!("Could not enter learning mode! {}", e))?;
+            .map_err(|e| e.context("Could not enter learning mode!"))?;
 
         // Block until we learn the code or timeout
-        for _ in 0..attempts {
-            // Sleep before trying again
-            std::thread::sleep(interval);
-
-            let code: Vec<u8> = self
-                .send_command(&[], RemoteDataCommand::GetCode)
-                .map_err(|e| format!("Could not check code status of device! {}", e))?;
-            if code.len() != 0 {
-                return Ok(code);
-            }
+        let code = poll_until(deadline, self.learn_poll_interval(), || {
+            on_status(LearnStatus::WaitingForSignal);
+            return self.check_code();
+        })?;
+        if let Some(code) = code {
+            on_status(LearnStatus::DataCaptured);
+            return Ok(code);
         }
 
-        // If we haven't gotten anything up until now, then we failed
+        // If we haven't gotten anything up until now, then the device never heard a code
         self.send_command(&[], RemoteDataCommand::StopRfSweep)
-            .map_err(|e| format!("Could not cancel RF sweep! {}", e))?;
-        return Err("Could not learn RF code! Operation timed out.".into());
+            .map_err(|e| e.context("Could not cancel RF sweep!"))?;
+        return Err(RbroadlinkError::NothingLearned(
+            "Could not learn RF code! The device reported that no code was captured.".into(),
+        ));
+    }
+
+    /// Attempt to learn an IR code without blocking, giving up once the timeout has passed.
+    ///
+    /// See [RemoteDevice::learn_ir] for more info.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "learn_ir", skip_all, fields(ip = %self.info.address)))]
+    pub async fn learn_ir_async(&self, timeout: Duration) -> Result<Vec<u8>, RbroadlinkError> {
+        let deadline = Instant::now() + timeout;
+
+        // First enter learning...
+        self.send_command_async(&[], RemoteDataCommand::StartLearningIR, LEARN_RESPONSE_TIMEOUT)
+            .await
+            .map_err(|e| e.context("Could not enter learning mode!"))?;
+
+        // Wait until we learn the code or timeout
+        if let Some(code) = poll_until_async(deadline, self.learn_poll_interval(), || self.check_code_async()).await? {
+            return Ok(code);
+        }
+
+        // If we haven't gotten anything up until now, then the device never heard a code
+        return Err(RbroadlinkError::NothingLearned(
+            "Could not learn IR code! The device reported that no code was captured.".into(),
+        ));
+    }
+
+    /// Attempts to learn an RF code without blocking, giving up once the timeout has passed.
+    /// The timeout covers both stages of learning.
+    ///
+    /// See [RemoteDevice::learn_rf] for more info.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "learn_rf", skip_all, fields(ip = %self.info.address)))]
+    pub async fn learn_rf_async(&self, timeout: Duration) -> Result<Vec<u8>, RbroadlinkError> {
+        if !self.capabilities().rf {
+            return Err(RbroadlinkError::Unsupported(format!(
+                "{} does not support RF codes!",
+                self.info.friendly_model
+            )));
+        }
+
+        let deadline = Instant::now() + timeout;
+
+        // Start sweeping for the type of frequency in use
+        self.send_command_async(&[], RemoteDataCommand::SweepRfFrequencies, LEARN_RESPONSE_TIMEOUT)
+            .await
+            .map_err(|e| e.context("Could not start sweeping frequencies!"))?;
+
+        // Wait for the frequency to be identified
+        let frequency_found = poll_until_async(deadline, self.learn_poll_interval(), || async {
+            let frequency: Vec<u8> = self
+                .send_command_async(&[], RemoteDataCommand::CheckFrequency, LEARN_RESPONSE_TIMEOUT)
+                .await
+                .map_err(|e| e.context("Device did not respond while checking for a frequency!"))?;
+
+            return Ok::<_, RbroadlinkError>(if frequency.first() == Some(&1) { Some(()) } else { None });
+        })
+            .await?;
+
+        // Error out if no frequency is found
+        if frequency_found.is_none() {
+            self.send_command_async(&[], RemoteDataCommand::StopRfSweep, LEARN_RESPONSE_TIMEOUT)
+                .await
+                .map_err(|e| e.context("Could not cancel RF sweep!"))?;
+            return Err(RbroadlinkError::NothingLearned(
+                "Could not determine frequency! The device reported that no RF signal was found.".into(),
+            ));
+        }
+
+        // Enter RF learning mode
+        self.send_command_async(&[], RemoteDataCommand::StartLearningRF, LEARN_RESPONSE_TIMEOUT)
+            .await
+            .map_err(|e| e.context("Could not enter learning mode!"))?;
+
+        // Wait until we learn the code or timeout
+        if let Some(code) = poll_until_async(deadline, self.learn_poll_interval(), || self.check_code_async()).await? {
+            return Ok(code);
+        }
+
+        // If we haven't gotten anything up until now, then the device never heard a code
+        self.send_command_async(&[], RemoteDataCommand::StopRfSweep, LEARN_RESPONSE_TIMEOUT)
+            .await
+            .map_err(|e| e.context("Could not cancel RF sweep!"))?;
+        return Err(RbroadlinkError::NothingLearned(
+            "Could not learn RF code! The device reported that no code was captured.".into(),
+        ));
+    }
+
+    /// Attempt to learn an IR code without blocking, giving up once the timeout has passed or
+    /// returning [RbroadlinkError::Cancelled] as soon as a value is sent on `cancel`.
+    ///
+    /// See [RemoteDevice::learn_ir_async] for more info.
+    pub async fn learn_ir_async_cancellable(
+        &self,
+        timeout: Duration,
+        cancel: oneshot::Receiver<()>,
+    ) -> Result<Vec<u8>, RbroadlinkError> {
+        return self.cancellable(self.learn_ir_async(timeout), cancel).await;
+    }
+
+    /// Attempts to learn an RF code without blocking, giving up once the timeout has passed or
+    /// returning [RbroadlinkError::Cancelled] as soon as a value is sent on `cancel`.
+    ///
+    /// See [RemoteDevice::learn_rf_async] for more info.
+    pub async fn learn_rf_async_cancellable(
+        &self,
+        timeout: Duration,
+        cancel: oneshot::Receiver<()>,
+    ) -> Result<Vec<u8>, RbroadlinkError> {
+        return self.cancellable(self.learn_rf_async(timeout), cancel).await;
+    }
+
+    /// Runs a learning future until it completes or is cancelled, taking the device out of
+    /// learning mode when cancelled.
+    ///
+    /// Note: Dropping the sender without sending a value does not cancel learning.
+    async fn cancellable<F>(&self, learn: F, cancel: oneshot::Receiver<()>) -> Result<Vec<u8>, RbroadlinkError>
+    where
+        F: std::future::Future<Output = Result<Vec<u8>, RbroadlinkError>>,
+    {
+        tokio::select! {
+            result = learn => return result,
+            Ok(()) = cancel => {},
+        };
+
+        // Make sure that the device does not stay stuck in learning mode. This is best effort,
+        // since the caller should not have to wait for an unresponsive device.
+        if let Err(e) = self
+            .send_command_async(&[], RemoteDataCommand::StopRfSweep, CANCEL_RESPONSE_TIMEOUT)
+            .await
+        {
+            warn!("Could not exit learning mode: {}", e);
+        }
+
+        return Err(RbroadlinkError::Cancelled);
+    }
+
+    /// Checks whether the device has captured a code while in learning mode, without blocking.
+    async fn check_code_async(&self) -> Result<Option<Vec<u8>>, RbroadlinkError> {
+        let response = self
+            .send_command_async(&[], RemoteDataCommand::GetCode, LEARN_RESPONSE_TIMEOUT)
+            .await;
+
+        return parse_check_code(response);
+    }
+
+    /// Checks whether the device has captured a code while in learning mode.
+    fn check_code(&self) -> Result<Option<Vec<u8>>, RbroadlinkError> {
+        return parse_check_code(self.send_command(&[], RemoteDataCommand::GetCode));
     }
 
     /// Sends an IR/RF code to the world.
-    pub fn send_code(&self, code: &[u8]) -> Result<(), String> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "send_code", skip_all, fields(ip = %self.info.address)))]
+    pub fn send_code(&self, code: &[u8]) -> Result<(), RbroadlinkError> {
+        self.validate_code(code)
+            .map_err(|e| e.context("Could not send IR code to device!"))?;
         self.send_command(code, RemoteDataCommand::SendCode)
-            .map_err(|e| format!("Could not send IR code to device! {}", e))?;
+            .map_err(|e| e.context("Could not send IR code to device!"))?;
 
         return Ok(());
     }
 
-    /// Sends an IR/RF code to the world.
-    pub async fn send_code_async(&self, code: &[u8], response_timeout: Duration) -> Result<(), String> {
+    /// Sends an IR/RF code to the world, overriding the amount of times it is repeated.
+    ///
+    /// This is useful for devices which need a code to be sent several times, without
+    /// having to learn the code again.
+    pub fn send_code_repeated(&self, code: &[u8], repeat: u8) -> Result<(), RbroadlinkError> {
+        // Only codes with a known type have a repeat count
+        match code.first() {
+            Some(&code_type) if is_code_type(code_type) && code.len() > 0x01 => {}
+            _ => {
+                return Err(RbroadlinkError::InvalidInput(
+                    "Could not send repeated code! Code has an unknown type.".into(),
+                ))
+            }
+        }
+
+        // The repeat count directly follows the type byte
+        let mut repeated = code.to_vec();
+        repeated[0x01] = repeat;
+
+        return self.send_code(&repeated);
+    }
+
+    /// Sends a parsed IR/RF code to the world.
+    pub fn send_broadlink_code(&self, code: &BroadlinkCode) -> Result<(), RbroadlinkError> {
+        return self.send_code(&code.to_bytes());
+    }
+
+    /// Sends an IR/RF code to the world, overriding the device type embedded in the packet.
+    ///
+    /// Note: Try to avoid using this method in favor of [RemoteDevice::send_code], unless the
+    /// device expects a different device type than the one reported during discovery.
+    pub fn send_code_with_device_type(&self, code: &[u8], device_type: u16) -> Result<(), RbroadlinkError> {
+        self.validate_code(code)
+            .map_err(|e| e.context("Could not send IR code to device!"))?;
+        self.send_command_with_device_type(code, RemoteDataCommand::SendCode, device_type)
+            .map_err(|e| e.context("Could not send IR code to device!"))?;
+
+        return Ok(());
+    }
+
+    /// Checks that a code can be sent by a remote: it must start with a known type byte, and
+    /// its embedded length must fit in the payload.
+    ///
+    /// Note: Learned codes are padded with zeros past the end, so the payload may be longer
+    /// than the embedded length.
+    pub fn validate_code(&self, code: &[u8]) -> Result<(), RbroadlinkError> {
+        let code_type = *code
+            .first()
+            .ok_or_else(|| RbroadlinkError::InvalidInput("Code is empty!".into()))?;
+        if !is_code_type(code_type) {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Code has an unknown type! Got {:#04X}",
+                code_type
+            )));
+        }
+
+        if code.len() < 0x04 {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Code is too short! Expected at least 4 bytes, got {}",
+                code.len()
+            )));
+        }
+
+        let length = usize::from(u16::from_le_bytes([code[0x02], code[0x03]]));
+        let payload_length = code.len() - 0x04;
+        if length > payload_length {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Code length field says {} but payload has {} bytes",
+                length, payload_length
+            )));
+        }
+
+        return Ok(());
+    }
+
+    /// Sends an IR code built from raw durations (e.g. from an oscilloscope capture), in
+    /// microseconds, alternating between on and off.
+    ///
+    /// Durations must be non-zero and at most [crate::codes::MAX_DURATION_MICROSECONDS].
+    pub fn send_raw_ir(&self, durations: &[u32], repeat: u8) -> Result<(), RbroadlinkError> {
+        let code = try_encode_durations(IR_CODE_TYPE, repeat, durations)
+            .map_err(|e| e.context("Could not send raw IR code!"))?;
+
+        return self.send_code(&code);
+    }
+
+    /// Sends an RF code in the specified band built from raw durations, in microseconds,
+    /// alternating between on and off.
+    ///
+    /// See [RemoteDevice::send_raw_ir] for more info.
+    pub fn send_raw_rf(&self, durations: &[u32], band: RfBand, repeat: u8) -> Result<(), RbroadlinkError> {
+        if !self.capabilities().rf {
+            return Err(RbroadlinkError::Unsupported(format!(
+                "{} does not support RF codes!",
+                self.info.friendly_model
+            )));
+        }
+
+        let code = try_encode_durations(band.type_byte(), repeat, durations)
+            .map_err(|e| e.context("Could not send raw RF code!"))?;
+
+        return self.send_code(&code);
+    }
+
+    /// Sends the code with the specified name from a library.
+    ///
+    /// See [CodeLibrary::blast] for more info.
+    pub fn send_named(&self, library: &CodeLibrary, name: &str) -> Result<(), RbroadlinkError> {
+        return library.blast(self, name);
+    }
+
+    /// Sends a sequence of IR/RF codes (e.g. a macro such as "turn on TV, switch input"),
+    /// waiting for the delay paired with each code before sending the next one.
+    ///
+    /// Note: Sending stops at the first code which fails, and the error mentions which step
+    /// of the sequence failed.
+    pub fn send_sequence(&self, steps: &[(Vec<u8>, Duration)]) -> Result<(), RbroadlinkError> {
+        for (index, (code, delay)) in steps.iter().enumerate() {
+            self.send_code(code)
+                .map_err(|e| e.context(format!("Could not send step {} of {}!", index + 1, steps.len())))?;
+
+            // Give the receiving device time to handle the code before the next one
+            if index + 1 < steps.len() {
+                std::thread::sleep(*delay);
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Sends a sequence of IR/RF codes without blocking.
+    ///
+    /// See [RemoteDevice::send_sequence] for more info.
+    pub async fn send_sequence_async(
+        &self,
+        steps: &[(Vec<u8>, Duration)],
+        response_timeout: Duration,
+    ) -> Result<(), RbroadlinkError> {
+        for (index, (code, delay)) in steps.iter().enumerate() {
+            self.send_code_async(code, response_timeout)
+                .await
+                .map_err(|e| e.context(format!("Could not send step {} of {}!", index + 1, steps.len())))?;
+
+            // Give the receiving device time to handle the code before the next one
+            if index + 1 < steps.len() {
+                tokio::time::sleep(*delay).await;
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Sends an IR/RF code to the world without blocking.
+    ///
+    /// This uses the same packing as [RemoteDevice::send_code], but awaits the device's
+    /// acknowledgement for at most `response_timeout` before returning an error.
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "send_code", skip_all, fields(ip = %self.info.address)))]
+    pub async fn send_code_async(&self, code: &[u8], response_timeout: Duration) -> Result<(), RbroadlinkError> {
+        self.validate_code(code)
+            .map_err(|e| e.context("Could not send IR code to device!"))?;
         self.send_command_async(code, RemoteDataCommand::SendCode, response_timeout)
             .await
-            .map_err(|e| format!("Could not send IR code to device! {}", e))?;
+            .map_err(|e| e.context("Could not send IR code to device!"))?;
 
         return Ok(());
     }
 
+    /// Read the temperature and humidity of the remote's built-in sensors at once.
+    ///
+    /// Note: Only the Pro models have sensors. See [RemoteCapabilities::sensors].
+    pub fn check_sensors(&self) -> Result<SensorReading, RbroadlinkError> {
+        if !self.capabilities().sensors {
+            return Err(RbroadlinkError::UnsupportedDevice(self.info.model_code));
+        }
+
+        let data = self
+            .send_command(&[], RemoteDataCommand::CheckSensors)
+            .map_err(|e| e.context("Could not read sensors from device!"))?;
+
+        return parse_sensor_reading(&data);
+    }
+
+    /// Read the temperature of the remote's built-in sensor, in degrees Celsius.
+    ///
+    /// Note: Only the Pro models have a sensor. See [RemoteCapabilities::sensors].
+    pub fn check_temperature(&self) -> Result<f32, RbroadlinkError> {
+        return Ok(self.check_sensors()?.temperature);
+    }
+
+    /// Read the relative humidity of the remote's built-in sensor, as a percentage.
+    ///
+    /// Note: Only the Pro models have a sensor. See [RemoteCapabilities::sensors].
+    pub fn check_humidity(&self) -> Result<f32, RbroadlinkError> {
+        return Ok(self.check_sensors()?.humidity);
+    }
+
     /// Sends a raw command to the remote.
     /// Note: Try to avoid using this method in favor of [RemoteDevice::send_code], [RemoteDevice::learn_ir], etc.
     pub fn send_command(
         &self,
         payload: &[u8],
         command: RemoteDataCommand,
-    ) -> Result<Vec<u8>, String> {
+    ) -> Result<Vec<u8>, RbroadlinkError> {
+        return self.send_command_with_device_type(payload, command, self.info.model_code);
+    }
+
+    /// Sends a raw command to the remote, overriding the device type embedded in the packet.
+    /// Note: Try to avoid using this method in favor of [RemoteDevice::send_command].
+    pub fn send_command_with_device_type(
+        &self,
+        payload: &[u8],
+        command: RemoteDataCommand,
+        device_type: u16,
+    ) -> Result<Vec<u8>, RbroadlinkError> {
         // We cast this object to a generic device in order to make use of the shared
         // helper utilities.
         let generic_device = Device::Remote {
@@ -182,11 +799,11 @@ impl RemoteDevice {
         let msg = RemoteDataMessage::new(command);
         let packed = msg
             .pack_with_payload(&payload)
-            .map_err(|e| format!("Could not pack remote data message! {}", e))?;
+            .map_err(|e| e.context("Could not pack remote data message!"))?;
 
         let response = generic_device
-            .send_command::<RemoteDataMessage>(&packed)
-            .map_err(|e| format!("Could not send code inside of the command! {}", e))?;
+            .send_command_with_device_type::<RemoteDataMessage>(&packed, device_type)
+            .map_err(|e| e.context("Could not send code inside of the command!"))?;
 
         return RemoteDataMessage::unpack_with_payload(&response);
     }
@@ -198,7 +815,7 @@ impl RemoteDevice {
         payload: &[u8],
         command: RemoteDataCommand,
         response_timeout: Duration
-    ) -> Result<Vec<u8>, String> {
+    ) -> Result<Vec<u8>, RbroadlinkError> {
         // We cast this object to a generic device in order to make use of the shared
         // helper utilities.
         let generic_device = Device::Remote {
@@ -209,13 +826,107 @@ impl RemoteDevice {
         let msg = RemoteDataMessage::new(command);
         let packed = msg
             .pack_with_payload(&payload)
-            .map_err(|e| format!("Could not pack remote data message! {}", e))?;
+            .map_err(|e| e.context("Could not pack remote data message!"))?;
 
         let response = generic_device
             .send_command_async::<RemoteDataMessage>(&packed,response_timeout)
             .await
-            .map_err(|e| format!("Could not send code inside of the command! {}", e))?;
+            .map_err(|e| e.context("Could not send code inside of the command!"))?;
 
         return RemoteDataMessage::unpack_with_payload(&response);
     }
 }
+
+/// Parses the payload of a sensor reading.
+///
+/// Each value is stored as a whole part followed by the hundredths. The whole part of the
+/// temperature is signed.
+pub(crate) fn parse_sensor_reading(data: &[u8]) -> Result<SensorReading, RbroadlinkError> {
+    if data.len() < 4 {
+        return Err(RbroadlinkError::Decode(format!(
+            "Sensor reading is too short! Expected at least 4 bytes, got {}",
+            data.len()
+        )));
+    }
+
+    let whole = f32::from(data[0x00] as i8);
+    let fraction = f32::from(data[0x01]) / 100.0;
+    let temperature = if whole < 0.0 { whole - fraction } else { whole + fraction };
+    let humidity = f32::from(data[0x02]) + f32::from(data[0x03]) / 100.0;
+
+    return Ok(SensorReading {
+        temperature,
+        humidity,
+    });
+}
+
+/// Interprets the response to a check for a learned code.
+///
+/// The device answers with an error code (or an empty payload) while it has not captured a code,
+/// which is reported as `None`. Any other failure, such as a timeout, is returned as-is.
+pub(crate) fn parse_check_code(response: Result<Vec<u8>, RbroadlinkError>) -> Result<Option<Vec<u8>>, RbroadlinkError> {
+    return match response {
+        Ok(code) if code.len() != 0 => Ok(Some(code)),
+        Ok(_) => Ok(None),
+        Err(e) => match e.root_cause() {
+            RbroadlinkError::DeviceError(code) if NOTHING_CAPTURED_ERRORS.contains(code) => Ok(None),
+            _ => Err(e.context("Device did not respond while checking for a learned code!")),
+        },
+    };
+}
+
+/// Sets the type byte of a learned RF code to the one of the specified band. IR codes and
+/// codes of an unknown type are returned as-is.
+pub(crate) fn tag_rf_band(mut code: Vec<u8>, band: RfBand) -> Vec<u8> {
+    let kind = code.first().and_then(|&byte| CodeKind::from_type_byte(byte));
+    if matches!(kind, Some(CodeKind::Rf433 | CodeKind::Rf315)) {
+        code[0x00] = band.type_byte();
+    }
+
+    return code;
+}
+
+/// Repeatedly calls `poll`, sleeping `interval` before each call, until it returns a value
+/// or the deadline has passed.
+fn poll_until<T, F>(deadline: Instant, interval: Duration, mut poll: F) -> Result<Option<T>, RbroadlinkError>
+where
+    F: FnMut() -> Result<Option<T>, RbroadlinkError>,
+{
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+
+        // Sleep before trying again, without overshooting the deadline
+        std::thread::sleep(interval.min(deadline - now));
+
+        if let Some(value) = poll()? {
+            return Ok(Some(value));
+        }
+    }
+}
+
+/// Repeatedly awaits `poll`, sleeping `interval` before each call, until it returns a value
+/// or the deadline has passed.
+///
+/// This is the non-blocking counterpart of [poll_until].
+async fn poll_until_async<T, F, Fut>(deadline: Instant, interval: Duration, mut poll: F) -> Result<Option<T>, RbroadlinkError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>, RbroadlinkError>>,
+{
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+
+        // Sleep before trying again, without overshooting the deadline
+        tokio::time::sleep(interval.min(deadline - now)).await;
+
+        if let Some(value) = poll().await? {
+            return Ok(Some(value));
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::net::Ipv4Addr;
+
+use packed_struct::PackedStruct;
+use phf::phf_map;
+
+use crate::{
+    network::{
+        DiscoveryResponse, SensorDataCommand, SensorDataMessage,
+    },
+    Device, DeviceInfo, RbroadlinkError,
+};
+
+/// A mapping of environment sensor device codes to their friendly model equivalent.
+pub const SENSOR_CODES: phf::Map<u16, &'static str> = phf_map! {
+    0x2714u16 => "A1",
+};
+
+/// The amount of light measured by an A1 sensor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LightLevel {
+    Dark,
+    Dim,
+    Normal,
+    Bright,
+}
+
+/// The air quality measured by an A1 sensor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AirQuality {
+    Excellent,
+    Good,
+    Normal,
+    Bad,
+}
+
+/// The amount of noise measured by an A1 sensor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NoiseLevel {
+    Quiet,
+    Normal,
+    Noisy,
+}
+
+/// A single reading of all of the measurements of an A1 sensor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct A1Reading {
+    /// The temperature, in degrees Celsius.
+    pub temperature: f32,
+
+    /// The relative humidity, as a percentage.
+    pub humidity: f32,
+
+    /// The amount of light.
+    pub light: LightLevel,
+
+    /// The air quality.
+    pub air_quality: AirQuality,
+
+    /// The amount of noise.
+    pub noise: NoiseLevel,
+}
+
+/// A broadlink A1 environment sensor.
+#[derive(Debug, Clone)]
+pub struct A1Sensor {
+    /// Base information about the sensor.
+    pub info: DeviceInfo,
+}
+
+impl A1Sensor {
+    /// Create a new A1Sensor.
+    ///
+    /// Note: This should not be called directly. Please use [Device::from_ip] or
+    /// [Device::list] instead.
+    pub fn new(name: &str, addr: Ipv4Addr, response: DiscoveryResponse) -> A1Sensor {
+        // Get the type of sensor
+        let friendly_model: String = SENSOR_CODES
+            .get(&response.model_code)
+            .unwrap_or(&"Unknown")
+            .to_string();
+
+        return Self {
+            info: DeviceInfo::from_response(name, addr, &response, "Sensor", &friendly_model),
+        };
+    }
+
+    /// Read all of the measurements of the sensor.
+    pub fn read(&self) -> Result<A1Reading, RbroadlinkError> {
+        let response = self
+            .send_command(SensorDataCommand::CheckSensors)
+            .map_err(|e| e.context("Could not read sensors from device!"))?;
+
+        return parse_a1_reading(&response);
+    }
+
+    /// Sends a raw command to the sensor.
+    /// Note: Try to avoid using this method in favor of [A1Sensor::read].
+    pub fn send_command(&self, command: SensorDataCommand) -> Result<Vec<u8>, RbroadlinkError> {
+        // We cast this object to a generic device in order to make use of the shared
+        // helper utilities.
+        let generic_device = Device::Sensor {
+            sensor: self.clone(),
+        };
+
+        // Construct the data message
+        let msg = SensorDataMessage::new(command);
+        let packed = msg.pack().map_err(|e| {
+            RbroadlinkError::Encode(format!("Could not pack sensor data message! {}", e))
+        })?;
+
+        return generic_device
+            .send_command::<SensorDataMessage>(&packed)
+            .map_err(|e| e.context("Could not send command!"));
+    }
+}
+
+/// Parses the response to a sensor check into an [A1Reading].
+pub(crate) fn parse_a1_reading(data: &[u8]) -> Result<A1Reading, RbroadlinkError> {
+    if data.len() < 0x0D {
+        return Err(RbroadlinkError::Decode(format!(
+            "Sensor reading is too short! Expected at least 13 bytes, got {}",
+            data.len()
+        )));
+    }
+
+    let light = match data[0x08] {
+        0 => LightLevel::Dark,
+        1 => LightLevel::Dim,
+        2 => LightLevel::Normal,
+        3 => LightLevel::Bright,
+        x => return Err(RbroadlinkError::Decode(format!("Unknown light level: {}", x))),
+    };
+    let air_quality = match data[0x0A] {
+        0 => AirQuality::Excellent,
+        1 => AirQuality::Good,
+        2 => AirQuality::Normal,
+        3 => AirQuality::Bad,
+        x => return Err(RbroadlinkError::Decode(format!("Unknown air quality: {}", x))),
+    };
+    let noise = match data[0x0C] {
+        0 => NoiseLevel::Quiet,
+        1 => NoiseLevel::Normal,
+        2 => NoiseLevel::Noisy,
+        x => return Err(RbroadlinkError::Decode(format!("Unknown noise level: {}", x))),
+    };
+
+    return Ok(A1Reading {
+        temperature: f32::from(data[0x04]) + f32::from(data[0x05]) / 10.0,
+        humidity: f32::from(data[0x06]) + f32::from(data[0x07]) / 10.0,
+        light,
+        air_quality,
+        noise,
+    });
+}
@@ -0,0 +1,82 @@
+//! Helpers for (de)serializing types with serde.
+
+use packed_struct::PackedStruct;
+use serde::{de, Deserialize, Deserializer, Serializer};
+
+use crate::network::AirCondState;
+
+/// (De)serializes a MAC address as a colon-separated hex string, e.g. `A0:43:B0:01:02:03`.
+pub(crate) mod mac_hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(mac: &[u8; 6], serializer: S) -> Result<S::Ok, S::Error> {
+        let formatted = mac
+            .iter()
+            .map(|x| format!("{:02X}", x))
+            .collect::<Vec<String>>()
+            .join(":");
+
+        return serializer.serialize_str(&formatted);
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 6], D::Error> {
+        let formatted = String::deserialize(deserializer)?;
+        let invalid = || de::Error::custom(format!("Invalid MAC address: {}", formatted));
+
+        let mut mac = [0u8; 6];
+        let mut parts = formatted.split(':');
+        for byte in mac.iter_mut() {
+            let part = parts.next().ok_or_else(invalid)?;
+            *byte = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+        }
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        return Ok(mac);
+    }
+}
+
+/// (De)serializes an optional air conditioner state as the hex string of its packed bytes.
+pub(crate) mod air_cond_state_hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        state: &Option<AirCondState>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let state = match state {
+            Some(state) => state,
+            None => return serializer.serialize_none(),
+        };
+
+        let packed = state
+            .pack()
+            .map_err(|e| serde::ser::Error::custom(format!("Could not pack HVAC state! {}", e)))?;
+        let formatted = packed.iter().map(|x| format!("{:02X}", x)).collect::<String>();
+
+        return serializer.serialize_some(&formatted);
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<AirCondState>, D::Error> {
+        let formatted = match Option::<String>::deserialize(deserializer)? {
+            Some(formatted) => formatted,
+            None => return Ok(None),
+        };
+        let invalid = || de::Error::custom(format!("Invalid HVAC state: {}", formatted));
+
+        if !formatted.is_ascii() || formatted.len() != 26 {
+            return Err(invalid());
+        }
+
+        let mut packed = [0u8; 13];
+        for (i, byte) in packed.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&formatted[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        let state = AirCondState::unpack(&packed).map_err(|_| invalid())?;
+
+        return Ok(Some(state));
+    }
+}
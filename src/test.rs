@@ -1,20 +1,62 @@
 #[cfg(test)]
 mod tests {
-    use std::net::{IpAddr, Ipv4Addr};
+    use std::future::Future;
+    use std::mem::ManuallyDrop;
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::pin::Pin;
+    use std::sync::Arc;
 
     use chrono::naive::NaiveDate;
     use chrono::offset::{FixedOffset, TimeZone};
     use chrono::prelude::DateTime;
-    use packed_struct::prelude::PackedStruct;
+    use packed_struct::prelude::{PackedStruct, PackedStructSlice};
 
     use crate::{
+        codes::{
+            BroadlinkCode, CodeKind, RfBand, decode_base64, decode_durations, encode_base64, encode_durations,
+            try_encode_durations, validate_duration, MAX_DURATION_MICROSECONDS,
+            extract_from_learn_response, flipper, from_pronto, gc, lirc, protocols, to_pronto,
+        },
         constants,
+        device::{dedup_devices, device_from_packet, retry_with_backoff, retry_with_backoff_async},
+        remote::{parse_check_code, parse_sensor_reading, tag_rf_band},
+        power_strip::decode_outlet_states,
+        alarm::{parse_alarm_sensors, AlarmSensorKind, AlarmSensorState},
+        bulb::parse_bulb_state,
+        sensor::parse_a1_reading,
+        thermostat::{parse_thermostat_status, parse_thermostat_time, DeviceTime},
+        wall_switch::parse_wall_switch_state,
         network::{
-            AuthenticationMessage, CommandMessage, DiscoveryMessage, RemoteDataCommand,
-            RemoteDataMessage, WirelessConnection,
+            util::{
+                check_truncation, checksum, collect_responses_async, compute_modbus_crc16, interface_ip, recv_buffer, recv_error,
+                select_local_ipv4, DatagramSocket, DEFAULT_RECV_BUFFER_SIZE, MAX_CONSECUTIVE_RECEIVE_ERRORS,
+            },
+            unpack_json_payload, AuthenticationMessage, BulbDataMessage, CommandMessage, DiscoveryMessage, DiscoveryResponse,
+            CurtainDataCommand, CurtainDataMessage, EnergyEncoding, FirmwareVersionMessage, HvacDataMessage, PlugDataCommand, PlugDataMessage, PowerStripDataMessage, RemoteDataCommand,
+            ThermostatDataMessage, THERMOSTAT_STATUS_QUERY, RemoteDataMessage, SettingsMessage, WallSwitchDataMessage,
+            MockTransport, WirelessConnection,
         },
+        traits::{DeviceListTrait, DeviceTrait},
+        AirQuality, ConnectOptions, Device, LearnStatus, DeviceBuilder, DeviceKind, DiagnosticReport, DiagnosticStep, SessionKey, LightLevel, NoiseLevel, RbroadlinkError, RemoteDevice,
+        ThermostatMode,
+        ALARM_CODES, BULB_CODES, CURTAIN_CODES, HVAC_CODES, PLUG_CODES, POWER_STRIP_CODES, REMOTE_CODES,
+        SENSOR_CODES, THERMOSTAT_CODES, WALL_SWITCH_CODES, DEFAULT_LEARN_POLL_INTERVAL, MIN_LEARN_POLL_INTERVAL,
     };
 
+    /// Creates an unauthenticated remote from a fake discovery response.
+    fn fake_remote(addr: Ipv4Addr, mac: [u8; 6]) -> Device {
+        let response = DiscoveryResponse {
+            model_code: 0x649B,
+            mac,
+            name: [0u8; 62],
+            is_locked: false,
+        };
+
+        return Device::Remote {
+            remote: RemoteDevice::new("Test", addr, response),
+        };
+    }
+
     #[test]
     fn authentication_packs_correctly() {
         let auth = AuthenticationMessage::new("Test 1");
@@ -31,6 +73,41 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn base64_round_trips() {
+        let code: &[u8] = &[0x26, 0x00, 0x04, 0x00, 0x10, 0x00, 0x0D, 0x05];
+        let encoded = encode_base64(code);
+
+        assert_eq!(encoded, "JgAEABAADQU=");
+        assert_eq!(decode_base64(&encoded).unwrap(), code);
+        assert!(decode_base64("not base64!").is_err());
+    }
+
+    #[test]
+    fn broadlink_code_parses() {
+        let bytes: &[u8] = &[0xB2, 0x03, 0x04, 0x00, 0x10, 0x00, 0x0D, 0x05, 0x00, 0x00];
+        let code = BroadlinkCode::parse(bytes).expect("Could not parse test code!");
+
+        assert_eq!(code.kind, CodeKind::Rf433);
+        assert_eq!(code.repeat, 3);
+        assert_eq!(code.durations, vec![525, 109_456]);
+        assert_eq!(code.to_bytes(), &bytes[0..8]);
+        assert!(BroadlinkCode::parse(&[0x42, 0x00, 0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn broadlink_code_reports_band() {
+        let band = |type_byte| {
+            BroadlinkCode::parse(&[type_byte, 0x00, 0x01, 0x00, 0x10])
+                .expect("Could not parse test code!")
+                .frequency_band()
+        };
+
+        assert_eq!(band(0x26), None);
+        assert_eq!(band(0xB2), Some(RfBand::Rf433));
+        assert_eq!(band(0xD7), Some(RfBand::Rf315));
+    }
+
     #[test]
     fn command_packs_correctly() {
         let payload: [u8; 10] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
@@ -55,11 +132,434 @@ mod tests {
         assert_eq!(expected, &actual);
     }
 
+    #[test]
+    fn dedup_keeps_distinct_macs_behind_one_ip() {
+        let addr = Ipv4Addr::new(10, 0, 0, 1);
+        let mut devices = vec![
+            fake_remote(addr, [1, 2, 3, 4, 5, 6]),
+            fake_remote(addr, [6, 5, 4, 3, 2, 1]),
+        ];
+
+        dedup_devices(&mut devices);
+
+        assert_eq!(devices.len(), 2);
+    }
+
+    #[test]
+    fn duplicate_discovery_responses_are_removed() {
+        // A discovery response of an RM4 Pro
+        let mut packet = [0u8; 128];
+        packet[0x34..0x36].copy_from_slice(&0x649Bu16.to_le_bytes());
+        packet[0x3A..0x40].copy_from_slice(&[6, 5, 4, 3, 2, 1]);
+        packet[0x40..0x44].copy_from_slice(b"Test");
+
+        let from = "10.0.0.1:80".parse().unwrap();
+        let mut devices = vec![
+            device_from_packet(from, packet.len(), &packet, false).unwrap(),
+            device_from_packet(from, packet.len(), &packet, false).unwrap(),
+        ];
+        assert_eq!(devices[0].get_info(), devices[1].get_info());
+
+        dedup_devices(&mut devices);
+
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].get_info().mac, [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn session_export_keeps_auth_pair() {
+        let mut device = fake_remote(Ipv4Addr::new(10, 0, 0, 1), [1, 2, 3, 4, 5, 6]);
+        device.save_auth_pair(0x1234, [7u8; 16]);
+
+        let session = device.export_session();
+
+        assert_eq!(session.name, "Test");
+        assert_eq!(session.mac, [6, 5, 4, 3, 2, 1]);
+        assert_eq!(session.id, 0x1234);
+        assert_eq!(*session.key, [7u8; 16]);
+        assert_eq!(session.device_type, 0x649B);
+    }
+
+    #[test]
+    fn clones_share_the_session() {
+        let device = fake_remote(Ipv4Addr::new(10, 0, 0, 1), [1, 2, 3, 4, 5, 6]);
+        let clone = device.clone();
+
+        // Re-authenticating one handle must be visible through every other handle
+        let mut reauthenticated = device.clone();
+        reauthenticated.save_auth_pair(0x1234, [7u8; 16]);
+
+        for handle in [&device, &clone] {
+            assert_eq!(handle.get_info().session.auth_id(), 0x1234);
+            assert_eq!(*handle.get_info().session.key(), [7u8; 16]);
+        }
+        assert!(!format!("{:?}", device).contains("7, 7"));
+    }
+
+    #[test]
+    fn missing_interface_lists_available_ones() {
+        let err = interface_ip("does-not-exist0").unwrap_err();
+
+        assert!(matches!(err, RbroadlinkError::InvalidInput(_)));
+        assert!(err.to_string().contains("Available interfaces"));
+    }
+
+    #[test]
+    fn handshake_is_retried_until_success() {
+        let options = ConnectOptions {
+            retries: 3,
+            initial_backoff: std::time::Duration::ZERO,
+            ..Default::default()
+        };
+
+        let mut calls = 0;
+        let result = retry_with_backoff(&options, || {
+            calls += 1;
+            let lost = RbroadlinkError::Timeout(std::time::Duration::ZERO).context("Could not authenticate device!");
+            return if calls < 3 { Err(lost) } else { Ok(calls) };
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: Result<(), RbroadlinkError> = retry_with_backoff(&options, || {
+            calls += 1;
+            return Err(RbroadlinkError::Io(std::io::ErrorKind::ConnectionRefused.into()));
+        });
+        assert!(matches!(result, Err(RbroadlinkError::Io(_))));
+        assert_eq!(calls, 3);
+
+        // Errors which would fail the same way again are not retried
+        let mut calls = 0;
+        let result: Result<(), RbroadlinkError> = retry_with_backoff(&options, || {
+            calls += 1;
+            return Err(RbroadlinkError::UnsupportedDevice(0xFFFF));
+        });
+        assert!(matches!(result, Err(RbroadlinkError::UnsupportedDevice(_))));
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn async_handshake_is_retried_until_success() {
+        let options = ConnectOptions {
+            retries: 3,
+            initial_backoff: std::time::Duration::ZERO,
+            ..Default::default()
+        };
+
+        let mut calls = 0;
+        let result = retry_with_backoff_async(&options, || {
+            calls += 1;
+            let result = if calls < 3 { Err(RbroadlinkError::Timeout(std::time::Duration::ZERO)) } else { Ok(calls) };
+            return std::future::ready(result);
+        })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: Result<(), RbroadlinkError> = retry_with_backoff_async(&options, || {
+            calls += 1;
+            return std::future::ready(Err(RbroadlinkError::AuthFailed));
+        })
+            .await;
+        assert!(matches!(result, Err(RbroadlinkError::AuthFailed)));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn builder_sets_connect_options() {
+        let builder = DeviceBuilder::new(Ipv4Addr::new(10, 0, 0, 1))
+            .timeout(std::time::Duration::from_secs(2))
+            .retries(5)
+            .port(8080)
+            .learn_poll_interval(std::time::Duration::from_millis(500));
+
+        assert_eq!(builder.options().timeout, Some(std::time::Duration::from_secs(2)));
+        assert_eq!(builder.options().retries, 5);
+        assert_eq!(builder.options().destination_port, Some(8080));
+        assert_eq!(builder.options().learn_poll_interval, Some(std::time::Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn sessions_of_unknown_devices_are_rejected() {
+        let addr = Ipv4Addr::new(10, 0, 0, 5);
+        let session = crate::SessionInfo {
+            name: "Living room".into(),
+            mac: [1, 2, 3, 4, 5, 6],
+            id: 7,
+            key: SessionKey::from([0x42; 16]),
+            device_type: 0xFFFF,
+        };
+
+        // Unknown model codes are only accepted when building a device from its parts
+        assert!(matches!(
+            Device::from_session(addr, session).unwrap_err().root_cause(),
+            RbroadlinkError::UnsupportedDevice(0xFFFF)
+        ));
+        assert!(matches!(
+            Device::from_parts(addr, [1, 2, 3, 4, 5, 6], 7, [0x42; 16], 0xFFFF).unwrap(),
+            Device::Unknown { .. }
+        ));
+    }
+
+    #[test]
+    fn learn_poll_interval_is_clamped() {
+        let remote = |device: Device| match device {
+            Device::Remote { remote } => remote,
+            _ => panic!("Device is not a remote!"),
+        };
+        let device = Device::from_parts(Ipv4Addr::new(10, 0, 0, 5), [1, 2, 3, 4, 5, 6], 7, [0x42; 16], 0x649B).unwrap();
+        assert_eq!(remote(device.clone()).learn_poll_interval(), DEFAULT_LEARN_POLL_INTERVAL);
+
+        // Intervals shorter than the minimum are raised to it
+        let fast = device.clone().with_learn_poll_interval(std::time::Duration::ZERO);
+        assert_eq!(remote(fast).learn_poll_interval(), MIN_LEARN_POLL_INTERVAL);
+
+        let slow = device.with_learn_poll_interval(std::time::Duration::from_secs(5));
+        assert_eq!(remote(slow).learn_poll_interval(), std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn destination_port_defaults_to_80() {
+        let device = fake_remote(Ipv4Addr::new(10, 0, 0, 1), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(device.get_info().destination_port, 80);
+
+        let device = device.with_destination_port(8080);
+        assert_eq!(device.get_info().destination_port, 8080);
+    }
+
+    #[test]
+    fn session_key_is_zeroed_on_drop() {
+        let mut key = ManuallyDrop::new(SessionKey::from([7u8; 16]));
+        let bytes: *const [u8; 16] = &**key;
+
+        // The memory is still owned by the ManuallyDrop, so it is valid to read after dropping
+        unsafe {
+            ManuallyDrop::drop(&mut key);
+            assert_eq!(*bytes, [0u8; 16]);
+        }
+        assert_eq!(format!("{:?}", SessionKey::from([7u8; 16])), "SessionKey(***)");
+    }
+
+    #[test]
+    fn session_info_is_zeroed_on_drop() {
+        let mut device = fake_remote(Ipv4Addr::new(10, 0, 0, 1), [1, 2, 3, 4, 5, 6]);
+        device.save_auth_pair(0x1234, [7u8; 16]);
+
+        let mut session = ManuallyDrop::new(device.export_session());
+        let bytes: *const [u8; 16] = &*session.key;
+        assert!(!format!("{:?}", *session).contains("7, 7"));
+
+        // The memory is still owned by the ManuallyDrop, so it is valid to read after dropping
+        unsafe {
+            ManuallyDrop::drop(&mut session);
+            assert_eq!(*bytes, [0u8; 16]);
+        }
+    }
+
+    #[test]
+    fn device_kinds_cover_supported_codes() {
+        let maps = [
+            (&REMOTE_CODES, DeviceKind::Remote),
+            (&HVAC_CODES, DeviceKind::Hvac),
+            (&PLUG_CODES, DeviceKind::Plug),
+            (&POWER_STRIP_CODES, DeviceKind::PowerStrip),
+            (&SENSOR_CODES, DeviceKind::Sensor),
+            (&THERMOSTAT_CODES, DeviceKind::Thermostat),
+            (&ALARM_CODES, DeviceKind::Alarm),
+            (&CURTAIN_CODES, DeviceKind::Curtain),
+            (&BULB_CODES, DeviceKind::Bulb),
+            (&WALL_SWITCH_CODES, DeviceKind::WallSwitch),
+        ];
+        for (codes, kind) in maps {
+            for code in codes.keys() {
+                assert_eq!(DeviceKind::from_model_code(*code), kind, "Wrong kind for {:#06X}", code);
+            }
+        }
+
+        // A model code must belong to a single kind of device
+        let supported: usize = maps.iter().map(|(codes, _)| codes.len()).sum();
+        let unique: std::collections::HashSet<u16> = maps.iter().flat_map(|(codes, _)| codes.keys().copied()).collect();
+        assert_eq!(unique.len(), supported);
+
+        assert_eq!(DeviceKind::from_model_code(0xFFFF), DeviceKind::Unknown);
+    }
+
+    #[test]
+    fn empty_command_response_means_locked() {
+        let result = CommandMessage::unpack_with_payload(vec![0u8; 0x38], &constants::INITIAL_KEY);
+
+        assert!(matches!(result, Err(RbroadlinkError::DeviceLocked)));
+    }
+
+    #[test]
+    fn error_context_keeps_messages() {
+        let error = RbroadlinkError::ChecksumMismatch {
+            expected: 0x1234,
+            got: 0xBEAF,
+        }
+        .context("Payload checksum does not match actual checksum!")
+        .context("Could not send command!");
+
+        assert_eq!(
+            error.to_string(),
+            "Could not send command! Payload checksum does not match actual checksum! Expected 0x1234 got 0xBEAF"
+        );
+        assert!(matches!(
+            error.root_cause(),
+            RbroadlinkError::ChecksumMismatch { expected: 0x1234, got: 0xBEAF }
+        ));
+    }
+
+    #[test]
+    fn sensor_reading_is_parsed() {
+        // Recorded from an RM4 Pro reading 23.4°C and 42.5%
+        let frame = [0x08, 0x00, 0x24, 0x00, 0x00, 0x00, 0x17, 0x28, 0x2A, 0x32];
+        let payload = RemoteDataMessage::unpack_with_payload(&frame).unwrap();
+        let reading = parse_sensor_reading(&payload).unwrap();
+
+        assert_eq!(reading.temperature, 23.4);
+        assert_eq!(reading.humidity, 42.5);
+        assert_eq!(parse_sensor_reading(&[0xFB, 0x32, 0x00, 0x00]).unwrap().temperature, -5.5);
+        assert!(parse_sensor_reading(&[0x17, 0x28]).is_err());
+    }
+
+    #[test]
+    fn plug_state_packs_correctly() {
+        let packed = PlugDataMessage::new(PlugDataCommand::SetState, 1)
+            .pack()
+            .expect("Could not pack plug message!");
+
+        assert_eq!(packed, [2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(PlugDataMessage::unpack_state(&[1, 0, 0, 0, 1, 0]).unwrap(), 1);
+        assert!(PlugDataMessage::unpack_state(&[1, 0]).is_err());
+    }
+
+    #[test]
+    fn power_strip_state_packs_correctly() {
+        let packed = PowerStripDataMessage::set_state(0x04, true)
+            .pack()
+            .expect("Could not pack power strip message!");
+
+        assert_eq!(
+            packed,
+            [0x0D, 0, 0xA5, 0xA5, 0x5A, 0x5A, 0xBA, 0xC0, 0x02, 0, 0x03, 0, 0, 0x04, 0x04, 0]
+        );
+        assert_eq!(decode_outlet_states(0x05), [true, false, true, false]);
+    }
+
+    #[test]
+    fn thermostat_framing_uses_modbus_crc() {
+        assert_eq!(compute_modbus_crc16(b"123456789"), 0x4B37);
+
+        let packed = ThermostatDataMessage::new()
+            .pack_with_payload(&THERMOSTAT_STATUS_QUERY)
+            .expect("Could not pack thermostat message!");
+        let crc = compute_modbus_crc16(&THERMOSTAT_STATUS_QUERY).to_le_bytes();
+        assert_eq!(packed[0..2], [0x08, 0x00]);
+        assert_eq!(packed[2..8], THERMOSTAT_STATUS_QUERY);
+        assert_eq!(packed[8..10], crc);
+
+        // A status response: heating, manual mode, 21.5 degrees with a target of 23
+        let data = [0x01, 0x03, 0x2C, 0x00, 0x11, 0x2B, 0x2E, 0x00];
+        let mut frame = vec![0x0A, 0x00];
+        frame.extend(data);
+        frame.extend(compute_modbus_crc16(&data).to_le_bytes());
+        frame.extend([0x00, 0x00]);
+
+        let unpacked = ThermostatDataMessage::unpack_with_payload(&frame)
+            .expect("Could not unpack thermostat response!");
+        assert_eq!(unpacked, data);
+
+        let status = parse_thermostat_status(&unpacked).expect("Could not parse status!");
+        assert!(status.power);
+        assert!(status.active);
+        assert_eq!(status.mode, ThermostatMode::Manual);
+        assert_eq!(status.current_temperature, 21.5);
+        assert_eq!(status.target_temperature, 23.0);
+
+        frame[4] ^= 0xFF;
+        assert!(ThermostatDataMessage::unpack_with_payload(&frame).is_err());
+    }
+
+    #[test]
+    fn a1_reading_is_parsed() {
+        let frame = [
+            0x01, 0x00, 0x00, 0x00, 0x16, 0x05, 0x2D, 0x03, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+            0x00,
+        ];
+        let reading = parse_a1_reading(&frame).expect("Could not parse A1 frame!");
+
+        assert_eq!(reading.temperature, 22.5);
+        assert_eq!(reading.humidity, 45.3);
+        assert_eq!(reading.light, LightLevel::Normal);
+        assert_eq!(reading.air_quality, AirQuality::Good);
+        assert_eq!(reading.noise, NoiseLevel::Quiet);
+        assert!(parse_a1_reading(&frame[0..8]).is_err());
+    }
+
+    #[test]
+    fn plug_energy_is_decoded() {
+        let little_endian = [0x00, 0x00, 0x00, 0x00, 0x0A, 0x40, 0x00, 0x00];
+        let bcd = [0x00, 0x00, 0x00, 0x00, 0x00, 0x50, 0x12, 0x00];
+
+        assert_eq!(EnergyEncoding::LittleEndian.decode(&little_endian).unwrap(), 16.394);
+        assert_eq!(EnergyEncoding::Bcd.decode(&bcd).unwrap(), 12.5);
+        assert!(EnergyEncoding::Bcd.decode(&little_endian).is_err());
+        assert_eq!(EnergyEncoding::from_model_code(0x2711), None);
+    }
+
+    #[test]
+    fn plug_energy_query_matches_encoding() {
+        let bcd = EnergyEncoding::Bcd.query();
+        assert_eq!(bcd, &[0x08, 0x00, 0xFE, 0x01, 0x05, 0x01, 0x00, 0x00, 0x00, 0x2D]);
+
+        // SP mini+ plugs expect a full plug message with the energy command
+        let little_endian = EnergyEncoding::from_model_code(0x2736).unwrap().query();
+        assert_eq!(little_endian.len(), 16);
+        assert_eq!(little_endian[0], 0x04);
+        assert!(little_endian[1..].iter().all(|x| *x == 0));
+    }
+
+    #[test]
+    fn learn_response_code_is_extracted() {
+        let code: &[u8] = &[0x26, 0x00, 0x04, 0x00, 0x12, 0x34, 0x0D, 0x05];
+        let mut response = vec![0x0C, 0x00, 0x04, 0x00, 0x00, 0x00];
+        response.extend(code);
+
+        assert_eq!(extract_from_learn_response(&response).unwrap(), code);
+        assert_eq!(extract_from_learn_response(code).unwrap(), code);
+        assert!(extract_from_learn_response(&[0x01, 0x02, 0x03]).is_err());
+    }
+
+    #[test]
+    fn durations_expand_escapes() {
+        // 0x10 ticks, followed by an escaped 0x0D05 ticks
+        let code: &[u8] = &[0x26, 0x00, 0x04, 0x00, 0x10, 0x00, 0x0D, 0x05, 0x00, 0x00];
+
+        assert_eq!(decode_durations(code).unwrap(), vec![525, 109_456]);
+    }
+
+    #[test]
+    fn durations_reject_bad_length() {
+        let code: &[u8] = &[0x26, 0x00, 0x08, 0x00, 0x10, 0x20];
+
+        assert!(decode_durations(code).is_err());
+    }
+
+    #[test]
+    fn durations_round_trip() {
+        let durations = vec![525, 109_456];
+        let code = encode_durations(0x26, 0x02, &durations);
+
+        assert_eq!(code, vec![0x26, 0x02, 0x04, 0x00, 0x10, 0x00, 0x0D, 0x05]);
+        assert_eq!(decode_durations(&code).unwrap(), durations);
+    }
+
     #[test]
     fn discovery_packs_correctly() {
         // Note: No idea why we must +1 on the minute, but this test will fail otherwise
         let discover = DiscoveryMessage::new(
-            IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)),
+            Ipv4Addr::new(1, 2, 3, 4),
             42424,
             Some(DateTime::from_utc(
                 NaiveDate::from_ymd(2000, 2, 14).and_hms(10, 30 + 1, 0),
@@ -80,6 +580,172 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn hvac_info_query_packs_correctly() {
+        // Header, followed by the generic checksum of everything after the length
+        let expected: &[u8] = &[
+            0x0C, 0x00, 0xBB, 0x00, 0x06, 0x80, 0x00, 0x00, 0x02, 0x00, 0x21, 0x01, 0x1B, 0x7E,
+        ];
+        let actual = HvacDataMessage::info_query().expect("Could not pack test info query!");
+
+        assert_eq!(expected, &actual);
+
+        // The complete command, as calculated following the packet layout of python-broadlink
+        let expected: [u8; 72] = [
+            0x5A, 0xA5, 0xAA, 0x55, 0x5A, 0xA5, 0xAA, 0x55, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC3, 0xCF, 0x00, 0x00,
+            0x2A, 0x4E, 0x6A, 0x00, 0x34, 0x92, 0x03, 0x02, 0x01, 0x34, 0xEA, 0x34,
+            0x01, 0x00, 0x00, 0x00, 0xB9, 0xC0, 0x00, 0x00, 0xA8, 0xBA, 0xB8, 0x22,
+            0xE9, 0x2F, 0x8C, 0x71, 0x8C, 0xE2, 0x07, 0xD0, 0xFE, 0x07, 0x34, 0xCF,
+        ];
+        let mac = [0x34, 0xEA, 0x34, 0x01, 0x02, 0x03];
+        let packet = CommandMessage::with_count::<HvacDataMessage>(0x1234, 0x4E2A, mac, 1)
+            .pack_with_payload(&actual, &constants::INITIAL_KEY)
+            .expect("Could not pack test info command!");
+
+        assert_eq!(expected.to_vec(), packet);
+    }
+
+    #[test]
+    fn recv_buffer_respects_size() {
+        assert_eq!(recv_buffer(None).unwrap().len(), DEFAULT_RECV_BUFFER_SIZE);
+        assert_eq!(recv_buffer(Some(1)).unwrap().len(), 1);
+        assert!(recv_buffer(Some(0)).is_err());
+    }
+
+    #[test]
+    fn receive_errors_are_not_timeouts() {
+        let timeout = std::time::Duration::from_secs(1);
+        let timed_out = recv_error(std::io::ErrorKind::WouldBlock.into(), timeout);
+        let refused = recv_error(std::io::ErrorKind::ConnectionRefused.into(), timeout);
+
+        assert!(matches!(timed_out, RbroadlinkError::Timeout(_)));
+        assert!(matches!(
+            refused.root_cause(),
+            RbroadlinkError::Io(e) if e.kind() == std::io::ErrorKind::ConnectionRefused
+        ));
+    }
+
+    #[test]
+    fn truncated_responses_are_reported() {
+        let buffer = [0u8; 16];
+
+        assert_eq!(check_truncation(15, &buffer).unwrap(), 15);
+        assert!(check_truncation(16, &buffer).is_err());
+    }
+
+    #[test]
+    fn lirc_config_is_parsed() {
+        let conf = "
+# Small test remote
+begin remote
+  name  test
+  bits           16
+  flags SPACE_ENC|CONST_LENGTH
+  header       9000  4500
+  one           560  1690
+  zero          560   560
+  ptrail        560
+  pre_data_bits   16
+  pre_data       0x00FF
+  gap          108000
+
+      begin codes
+          KEY_POWER                0x02FD
+      end codes
+end remote
+
+begin remote
+  name  raw
+  flags RAW_CODES
+  gap   100000
+
+      begin raw_codes
+          name KEY_UP
+              9000 4500 560 560
+              560
+          name KEY_DOWN
+              9000 4500 560 1690 560
+      end raw_codes
+end remote
+";
+
+        let codes = lirc::from_lirc(conf).expect("Could not parse test LIRC config!");
+        let names: Vec<&str> = codes.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["KEY_POWER", "KEY_UP", "KEY_DOWN"]);
+
+        // Header, 32 bits, trailing pulse and gap
+        let power = decode_durations(&codes[0].1).unwrap();
+        assert_eq!(power.len(), 2 + 32 * 2 + 2);
+        assert!(power[0].abs_diff(9000) <= 20);
+        assert!(power[power.len() - 1].abs_diff(108_000) <= 20);
+
+        let up = decode_durations(&codes[1].1).unwrap();
+        assert_eq!(up.len(), 6);
+        assert!(up[5].abs_diff(100_000) <= 20);
+    }
+
+    #[test]
+    fn lirc_blocks_without_an_end_are_recovered() {
+        // The codes section of the first remote is never closed
+        let conf = "
+begin remote
+  name  first
+  bits  8
+  one   560 1690
+  zero  560 560
+      begin codes
+          KEY_POWER  0x01
+begin remote
+  name  second
+  flags RAW_CODES
+      begin raw_codes
+          name KEY_UP
+              9000 4500 560 560
+";
+
+        let codes = lirc::from_lirc(conf).expect("Could not parse test LIRC config!");
+        let names: Vec<&str> = codes.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["KEY_POWER", "KEY_UP"]);
+
+        // The raw code is kept, even though its section is never closed
+        let up = decode_durations(&codes[1].1).unwrap();
+        assert_eq!(up.len(), 4);
+        assert!(up[0].abs_diff(9000) <= 20);
+    }
+
+    #[test]
+    fn lirc_round_trips() {
+        let code = encode_durations(0x26, 0x00, &[9000, 4500, 560, 560, 560, 40_000]);
+        let conf = lirc::to_lirc("KEY_TEST", &code).expect("Could not convert test code!");
+        let codes = lirc::from_lirc(&conf).expect("Could not parse test LIRC config!");
+
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].0, "KEY_TEST");
+        assert_eq!(decode_durations(&codes[0].1), decode_durations(&code));
+    }
+
+    #[test]
+    fn pronto_round_trips() {
+        // A learned NEC style code, ending with the usual long gap
+        let code: &[u8] = &[
+            0x26, 0x00, 0x0C, 0x00, 0x00, 0x01, 0x12, 0x89, 0x11, 0x11, 0x11, 0x34, 0x11, 0x00,
+            0x0D, 0x05, 0x00, 0x00,
+        ];
+
+        let pronto = to_pronto(code).expect("Could not convert test code to Pronto!");
+        assert_eq!(&pronto[0..4], &[0x0000, 0x006D, 0x0004, 0x0000]);
+
+        let round_trip = from_pronto(&pronto).expect("Could not convert test Pronto code!");
+        let expected = decode_durations(code).unwrap();
+        let actual = decode_durations(&round_trip).unwrap();
+        assert_eq!(expected.len(), actual.len());
+        for (e, a) in expected.iter().zip(actual.iter()) {
+            assert!(e.abs_diff(*a) <= 60, "Expected {} got {}", e, a);
+        }
+    }
+
     #[test]
     fn remote_data_packs_correctly() {
         let remote = RemoteDataMessage::new(RemoteDataCommand::SendCode);
@@ -115,4 +781,950 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn wireless_password_is_redacted() {
+        let connection = WirelessConnection::WPA2("Home", "hunter22");
+        let formatted = format!("{:?}", connection);
+
+        assert!(formatted.contains("Home"));
+        assert!(formatted.contains("WPA2"));
+        assert!(!formatted.contains("hunter22"));
+    }
+
+    /// A socket which returns queued datagrams and errors, and then never receives again.
+    struct FakeSocket(std::sync::Mutex<std::collections::VecDeque<std::io::Result<Vec<u8>>>>);
+
+    impl DatagramSocket for FakeSocket {
+        fn recv_datagram<'a>(
+            &'a self,
+            buffer: &'a mut [u8],
+        ) -> Pin<Box<dyn Future<Output = std::io::Result<(usize, SocketAddr)>> + Send + 'a>> {
+            let next = self.0.lock().unwrap().pop_front();
+            let from = SocketAddr::from((Ipv4Addr::new(10, 0, 0, 1), 80));
+
+            return match next {
+                Some(Ok(datagram)) => {
+                    // Like a real socket, the excess of a datagram larger than the buffer is discarded
+                    let len = datagram.len().min(buffer.len());
+                    buffer[0..len].copy_from_slice(&datagram[0..len]);
+                    Box::pin(std::future::ready(Ok((len, from))))
+                }
+                Some(Err(e)) => Box::pin(std::future::ready(Err(e))),
+                None => Box::pin(std::future::pending()),
+            };
+        }
+    }
+
+    #[tokio::test]
+    async fn async_collection_survives_receive_errors() {
+        let refused = || Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused));
+        let timeout = std::time::Duration::from_millis(50);
+
+        // A receive error before the real response does not end collection
+        let socket = FakeSocket(std::sync::Mutex::new(vec![refused(), Ok(vec![0x42; 128])].into()));
+        let results = collect_responses_async(&socket, None, |_, bytes, _| std::future::ready(Ok(bytes[0])), timeout)
+            .await
+            .expect("Could not collect responses!");
+        assert_eq!(results, vec![0x42]);
+
+        // A socket which keeps failing ends collection instead of spinning forever
+        let errors: Vec<std::io::Result<Vec<u8>>> = (0..MAX_CONSECUTIVE_RECEIVE_ERRORS + 1).map(|_| refused()).collect();
+        let socket = FakeSocket(std::sync::Mutex::new(errors.into()));
+        let results = collect_responses_async(&socket, None, |_, bytes, _| std::future::ready(Ok(bytes[0])), timeout)
+            .await
+            .expect("Could not collect responses!");
+        assert!(results.is_empty());
+        assert_eq!(socket.0.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn async_collection_skips_truncated_datagrams() {
+        let timeout = std::time::Duration::from_millis(50);
+        let socket = FakeSocket(std::sync::Mutex::new(vec![Ok(vec![0x41; 300]), Ok(vec![0x42; 128])].into()));
+        let results = collect_responses_async(&socket, Some(256), |_, bytes, _| std::future::ready(Ok(bytes[0])), timeout)
+            .await
+            .expect("Could not collect responses!");
+        assert_eq!(results, vec![0x42]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn device_info_and_codes_roundtrip_through_serde() {
+        let info = crate::DeviceInfo {
+            address: Ipv4Addr::new(192, 168, 1, 42),
+            mac: [0xA0, 0x43, 0xB0, 0x01, 0x02, 0x03],
+            model_code: 0x5F36,
+            friendly_type: "Remote".into(),
+            friendly_model: "RM Mini 3".into(),
+            name: "Living room".into(),
+            session: crate::SharedSession::new(0x1234, SessionKey::from([7u8; 16])),
+            is_locked: false,
+            firmware_version: Some(44057),
+            destination_port: 80,
+            read_timeout: crate::network::util::DEFAULT_READ_TIMEOUT,
+            transport: crate::SharedTransport::default(),
+        };
+        let json = serde_json::to_string(&info).expect("Could not serialize device info!");
+        assert!(json.contains("\"A0:43:B0:01:02:03\""));
+
+        // The session must never be serialized
+        let decoded: crate::DeviceInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.mac, info.mac);
+        assert_eq!(decoded.name, info.name);
+        assert_eq!(decoded.session.auth_id(), 0);
+        assert_eq!(*decoded.session.key(), constants::INITIAL_KEY);
+
+        let code = BroadlinkCode::parse(&[0xB2, 0x03, 0x04, 0x00, 0x10, 0x00, 0x0D, 0x05]).unwrap();
+        let json = serde_json::to_string(&code).expect("Could not serialize code!");
+        assert_eq!(serde_json::from_str::<BroadlinkCode>(&json).unwrap(), code);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn device_snapshot_roundtrips_through_serde() {
+        let mut packed = [0u8; 13];
+        packed[8] = 0x20; // Power on
+        let snapshot = crate::DeviceSnapshot {
+            address: Ipv4Addr::new(192, 168, 1, 42),
+            mac: [0xA0, 0x43, 0xB0, 0x01, 0x02, 0x03],
+            model_code: 0x4E2A,
+            friendly_model: "HVAC".into(),
+            friendly_type: "HVAC".into(),
+            name: "Bedroom".into(),
+            is_locked: false,
+            firmware_version: Some(44057),
+            hvac_state: Some(crate::network::AirCondState::unpack(&packed).unwrap()),
+            plug_power: None,
+            strip_states: None,
+            thermostat_status: None,
+            thermostat_time: Some(crate::DeviceTime { hour: 7, minute: 30, second: 0, weekday: chrono::Weekday::Mon }),
+            switch_state: None,
+        };
+        let json = serde_json::to_string(&snapshot).expect("Could not serialize snapshot!");
+
+        let decoded: crate::DeviceSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.mac, snapshot.mac);
+        assert_eq!(decoded.firmware_version, Some(44057));
+        assert_eq!(decoded.thermostat_time, snapshot.thermostat_time);
+        assert!(decoded.hvac_state.expect("HVAC state was not kept!").power);
+    }
+
+    #[test]
+    fn firmware_version_is_decoded() {
+        // Decrypted response of an RM mini 3 to the firmware version query
+        let response: &[u8] = &[
+            0x01, 0x00, 0x00, 0x00, 0x19, 0xAC, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(FirmwareVersionMessage::unpack_version(response).unwrap(), 44057);
+        assert!(FirmwareVersionMessage::unpack_version(&response[..5]).is_err());
+
+        let mut device = fake_remote(Ipv4Addr::new(192, 168, 1, 2), [0u8; 6]);
+        assert!(!device.to_string().contains("firmware"));
+        if let Device::Remote { remote } = &mut device {
+            remote.info.firmware_version = Some(44057);
+        }
+        assert!(device.to_string().ends_with(", firmware = 44057)"));
+    }
+
+    #[test]
+    fn learn_status_is_not_reported_for_unsupported_rf() {
+        let response = DiscoveryResponse {
+            model_code: 0x51DA,
+            mac: [0u8; 6],
+            name: [0u8; 62],
+            is_locked: false,
+        };
+        let remote = RemoteDevice::new("Test", Ipv4Addr::LOCALHOST, response);
+
+        let mut statuses = vec![];
+        let result = remote.learn_rf_with_status(std::time::Duration::from_secs(1), |status| statuses.push(status));
+
+        assert!(matches!(result.unwrap_err().root_cause(), RbroadlinkError::Unsupported(_)));
+        assert!(statuses.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelled_learning_returns_promptly() {
+        // A device which never answers, so that learning would otherwise run until the timeout
+        let device = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let response = DiscoveryResponse {
+            model_code: 0x51DA,
+            mac: [0u8; 6],
+            name: [0u8; 62],
+            is_locked: false,
+        };
+        let mut remote = RemoteDevice::new("Test", Ipv4Addr::LOCALHOST, response);
+        remote.info.destination_port = device.local_addr().unwrap().port();
+
+        let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            cancel_tx.send(()).unwrap();
+        });
+
+        let start = std::time::Instant::now();
+        let result = remote
+            .learn_ir_async_cancellable(std::time::Duration::from_secs(30), cancel_rx)
+            .await;
+
+        assert!(matches!(result, Err(RbroadlinkError::Cancelled)));
+        assert!(start.elapsed() < std::time::Duration::from_secs(3));
+    }
+
+    #[test]
+    fn failed_sequence_step_is_reported() {
+        // A device which never answers, so that the first step times out
+        let device = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let response = DiscoveryResponse {
+            model_code: 0x51DA,
+            mac: [0u8; 6],
+            name: [0u8; 62],
+            is_locked: false,
+        };
+        let mut remote = RemoteDevice::new("Test", Ipv4Addr::LOCALHOST, response);
+        remote.info.destination_port = device.local_addr().unwrap().port();
+        remote.info.read_timeout = std::time::Duration::from_millis(100);
+
+        let code = vec![0x26, 0x00, 0x04, 0x00, 0x10, 0x00, 0x0D, 0x05];
+        let steps = vec![
+            (code.clone(), std::time::Duration::from_millis(10)),
+            (code, std::time::Duration::from_millis(10)),
+        ];
+        let err = remote.send_sequence(&steps).unwrap_err();
+
+        assert!(err.to_string().starts_with("Could not send step 1 of 2!"));
+        assert!(matches!(err.root_cause(), RbroadlinkError::Timeout(_)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn code_library_roundtrips_through_json() {
+        let code = BroadlinkCode::parse(&[0x26, 0x00, 0x04, 0x00, 0x10, 0x00, 0x0D, 0x05]).unwrap();
+        let mut library = crate::codes::CodeLibrary::default();
+        library.insert("tv_power", &code);
+
+        let path = std::env::temp_dir().join(format!("rbroadlink-library-{}.json", std::process::id()));
+        library.save_to_path(&path).expect("Could not save library!");
+        let loaded = crate::codes::CodeLibrary::load_from_path(&path).expect("Could not load library!");
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.get("tv_power"), Some(code.to_bytes().as_slice()));
+        assert_eq!(loaded.get("missing"), None);
+    }
+
+    #[test]
+    fn code_library_skips_hidden_files_and_rejects_duplicates() {
+        let dir = std::env::temp_dir().join(format!("rbroadlink-codes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("tv_power.txt"), "2600040010000d05").unwrap();
+        std::fs::write(dir.join(".DS_Store"), [0x00, 0x01]).unwrap();
+
+        let library = crate::codes::CodeLibrary::load(&dir).expect("Could not load library!");
+        assert_eq!(library.names().collect::<Vec<_>>(), ["tv_power"]);
+        assert_eq!(library.get("tv_power"), Some([0x26, 0x00, 0x04, 0x00, 0x10, 0x00, 0x0D, 0x05].as_slice()));
+
+        // The same name with another extension is ambiguous
+        std::fs::write(dir.join("tv_power.bin"), [0x26, 0x00]).unwrap();
+        let err = crate::codes::CodeLibrary::load(&dir).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(err, RbroadlinkError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn learned_rf_codes_are_tagged_with_band() {
+        let code = vec![RfBand::Rf433.type_byte(), 0x00, 0x02, 0x00, 0x10, 0x00];
+
+        assert_eq!(tag_rf_band(code.clone(), RfBand::Rf315)[0], 0xD7);
+        assert_eq!(tag_rf_band(code, RfBand::Rf433)[0], 0xB2);
+        assert_eq!(tag_rf_band(vec![0x26, 0x00], RfBand::Rf315), vec![0x26, 0x00]);
+        assert_eq!(RfBand::Rf433.frequency_khz(), 433_920);
+    }
+
+    #[test]
+    fn command_header_is_described() {
+        let mac = [0x34, 0xEA, 0x34, 0x01, 0x02, 0x03];
+        let packed = CommandMessage::with_count::<PlugDataMessage>(0x1234, 0x649B, mac, 1)
+            .pack_with_payload(&[0x01; 16], &constants::INITIAL_KEY)
+            .unwrap();
+        let header = CommandMessage::unpack_from_slice(&packed[0..0x38]).unwrap();
+        let description = header.describe();
+
+        assert!(description.starts_with("device_type = 0x649B, packet_type = 0x006A, count = 0x1234"));
+        assert!(description.contains("mac = 34:EA:34:01:02:03, id = 0x00000001"));
+    }
+
+    #[test]
+    fn truncated_codes_are_rejected() {
+        let remote = match fake_remote(Ipv4Addr::LOCALHOST, [0u8; 6]) {
+            Device::Remote { remote } => remote,
+            _ => unreachable!(),
+        };
+
+        assert!(remote.validate_code(&[0x26, 0x00, 0x04, 0x00, 0x10, 0x00, 0x0D, 0x05]).is_ok());
+        assert!(remote.validate_code(&[0x42, 0x00, 0x00, 0x00]).is_err());
+
+        // The code says it has 4 bytes of durations, but only 2 were kept
+        let err = remote.send_code(&[0x26, 0x00, 0x04, 0x00, 0x10, 0x00]).unwrap_err();
+        match err.root_cause() {
+            RbroadlinkError::InvalidInput(msg) => {
+                assert_eq!(msg, "Code length field says 4 but payload has 2 bytes")
+            }
+            e => panic!("Unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn blasting_to_all_reports_each_device() {
+        let plug_response = DiscoveryResponse {
+            model_code: 0x2711,
+            mac: [0u8; 6],
+            name: [0u8; 62],
+            is_locked: false,
+        };
+        let devices = vec![
+            fake_remote(Ipv4Addr::LOCALHOST, [0u8; 6]),
+            Device::Plug {
+                plug: crate::SmartPlug::new("Plug", Ipv4Addr::LOCALHOST, plug_response),
+            },
+        ];
+
+        // An invalid code fails before anything is sent, so no device is needed
+        let results = Device::blast_to_all(&devices, &[0x26, 0x00, 0x04, 0x00]);
+
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0].as_ref().unwrap_err().root_cause(), RbroadlinkError::InvalidInput(_)));
+        assert!(matches!(results[1].as_ref().unwrap_err().root_cause(), RbroadlinkError::Unsupported(_)));
+    }
+
+    #[test]
+    fn settings_payload_holds_name_and_lock() {
+        let payload = SettingsMessage::pack_settings("Living room", true).unwrap();
+
+        assert_eq!(payload.len(), 0x50);
+        assert_eq!(&payload[0x04..0x0F], b"Living room");
+        assert_eq!(payload[0x0F], 0x00);
+        assert_eq!(payload[0x43], 0x01);
+
+        let err = SettingsMessage::pack_settings(&"x".repeat(63), false).unwrap_err();
+        assert_eq!(err.to_string(), "Device name is too long! Expected at most 62 bytes, got 63");
+    }
+
+    #[test]
+    fn thermostat_time_is_decoded() {
+        // A status response captured on a Thursday at 07:45:30
+        let data = [
+            0x01, 0x03, 0x2C, 0x00, 0x11, 0x2B, 0x2E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x07, 0x2D, 0x1E, 0x04,
+        ];
+        let time = parse_thermostat_time(&data).expect("Could not parse time!");
+        assert_eq!(
+            time,
+            DeviceTime {
+                hour: 7,
+                minute: 45,
+                second: 30,
+                weekday: chrono::Weekday::Thu,
+            }
+        );
+
+        assert_eq!(
+            ThermostatDataMessage::time_request(7, 45, 30, 4),
+            [0x01, 0x10, 0x00, 0x08, 0x00, 0x02, 0x04, 0x07, 0x2D, 0x1E, 0x04]
+        );
+    }
+
+    #[test]
+    fn raw_commands_use_the_specified_timeout() {
+        // A device which never answers
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let device = fake_remote(Ipv4Addr::LOCALHOST, [0u8; 6])
+            .with_destination_port(socket.local_addr().unwrap().port());
+
+        let timeout = std::time::Duration::from_millis(50);
+        let err = device
+            .send_raw_command::<RemoteDataMessage>(&[0x04, 0x00, 0x00, 0x00], timeout)
+            .unwrap_err();
+
+        assert!(matches!(err.root_cause(), RbroadlinkError::Timeout(t) if *t == timeout));
+    }
+
+    #[test]
+    fn discovery_can_be_repeated_back_to_back() {
+        // Both discoveries bind the standard discovery port, which must be reusable right away
+        let options = crate::network::DiscoveryOptions {
+            timeout: Some(std::time::Duration::from_millis(50)),
+            bind_addr: Some(Ipv4Addr::LOCALHOST),
+            broadcast_addr: Some(Ipv4Addr::LOCALHOST),
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            let devices = Device::list_with_options(Some(Ipv4Addr::LOCALHOST), &options)
+                .expect("Could not repeat discovery!");
+            assert!(devices.is_empty());
+        }
+    }
+
+    #[test]
+    fn wireless_credentials_are_validated() {
+        let too_long = "x".repeat(33);
+
+        assert!(WirelessConnection::WPA2("Home", "correct horse").to_message().is_ok());
+        assert!(WirelessConnection::None("Guest").to_message().is_ok());
+        assert!(WirelessConnection::WEP("Old", "12345").to_message().is_ok());
+
+        // Fields which do not fit in the message
+        assert!(WirelessConnection::None(&too_long).to_message().is_err());
+        assert!(WirelessConnection::None("").to_message().is_err());
+        assert!(WirelessConnection::WPA2("Home", &too_long).to_message().is_err());
+
+        // Passwords which do not match the security mode
+        assert!(WirelessConnection::WPA("Home", "").to_message().is_err());
+        assert!(WirelessConnection::WPA1("Home", "short").to_message().is_err());
+        assert!(WirelessConnection::WEP("Old", "123456").to_message().is_err());
+    }
+
+    #[test]
+    fn raw_durations_are_validated() {
+        let code = try_encode_durations(0x26, 1, &[526, 8400]).expect("Could not encode durations!");
+        assert_eq!(code, vec![0x26, 0x01, 0x04, 0x00, 0x10, 0x00, 0x01, 0x00]);
+
+        assert!(try_encode_durations(0x26, 0, &[]).is_err());
+        assert!(try_encode_durations(0x26, 0, &[526, 0]).is_err());
+        assert!(try_encode_durations(0x26, 0, &[MAX_DURATION_MICROSECONDS + 1]).is_err());
+        assert!(try_encode_durations(0x26, 0, &[MAX_DURATION_MICROSECONDS]).is_ok());
+
+        assert!(validate_duration(0).is_err());
+        assert!(validate_duration(MAX_DURATION_MICROSECONDS + 1).is_err());
+        assert!(validate_duration(1).is_ok());
+    }
+
+    #[test]
+    fn devices_can_be_built_from_parts() {
+        let mac = [0x34, 0xEA, 0x34, 0x01, 0x02, 0x03];
+        let key = [0x42u8; 16];
+        let device = Device::from_parts(Ipv4Addr::new(10, 0, 0, 5), mac, 7, key, 0x649B).unwrap();
+
+        let session = device.export_session();
+        assert_eq!((session.mac, session.id, *session.key, session.device_type), (mac, 7, key, 0x649B));
+
+        // Commands are encrypted with the injected key
+        let payload = [0x04u8; 16];
+        let packed = device.pack_command::<RemoteDataMessage>(&payload, None).unwrap();
+        assert_eq!(CommandMessage::unpack_with_payload(packed, &key).unwrap(), payload);
+    }
+
+    #[test]
+    fn global_cache_codes_roundtrip() {
+        // The start of an NEC code, as published in the Global Caché control tower
+        let command = "sendir,1:1,1,38000,1,1,342,171,21,21,21,64,21,1517";
+        let code = gc::from_global_cache(command).expect("Could not parse Global Caché code!");
+
+        let durations = decode_durations(&code).unwrap();
+        assert_eq!(durations.len(), 8);
+        assert!((f64::from(durations[0]) - 9000.0).abs() < 32.84);
+        assert_eq!(code[0x01], 0);
+
+        assert_eq!(gc::to_global_cache(&code, 1).unwrap(), command);
+        assert!(gc::from_global_cache("sendir,1:1,1,1000,1,1,342,171").is_err());
+    }
+
+    #[test]
+    fn flipper_raw_signals_are_imported() {
+        let content = "Filetype: IR signals file\nVersion: 1\n#\nname: Power\ntype: raw\nfrequency: 38000\nduty_cycle: 0.330000\ndata: 9024 4512 579 552 579 1683\n";
+        let codes = flipper::parse(content).expect("Could not parse Flipper file!");
+
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].0, "Power");
+        let code = codes[0].1.as_ref().unwrap();
+        assert_eq!(code.kind, CodeKind::Ir);
+        assert_eq!(code.durations, vec![9024, 4512, 579, 552, 579, 1683]);
+
+        // Unsupported and invalid signals are reported without failing the rest of the file
+        let mixed = "name: Vol_up\ntype: parsed\nprotocol: NEC\naddress: 07 00 00 00\ncommand: 02 00 00 00\n\
+                     name: Mute\ntype: raw\ndata: 9024 0 579\n\
+                     name: Power\ntype: raw\ndata: 9024 4512 579\n";
+        let codes = flipper::parse(mixed).expect("Could not parse Flipper file!");
+        let names: Vec<&str> = codes.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["Vol_up", "Mute", "Power"]);
+        assert!(matches!(codes[0].1.as_ref().unwrap_err().root_cause(), RbroadlinkError::Unsupported(_)));
+        assert!(matches!(codes[1].1.as_ref().unwrap_err().root_cause(), RbroadlinkError::InvalidInput(_)));
+        assert_eq!(codes[2].1.as_ref().unwrap().durations, vec![9024, 4512, 579]);
+
+        // A malformed file fails as a whole
+        assert!(flipper::parse("name: Power\nnot a key value pair\n").is_err());
+    }
+
+    #[test]
+    fn protocol_codes_have_reference_timings() {
+        let code = protocols::nec(0x00, 0x12);
+        assert_eq!(code.durations.len(), 68);
+        assert_eq!(code.durations[0..2], [9000, 4500]);
+        assert_eq!(code.durations[2..4], [562, 562]); // Address bit 0 is 0
+        assert_eq!(code.durations[18..20], [562, 1687]); // Inverted address bit 0 is 1
+        assert_eq!(code.durations[36..38], [562, 1687]); // Command bit 1 is 1
+        assert_eq!(code.durations.iter().sum::<u32>(), 108_000);
+
+        let code = protocols::rc5(0x00, 0x00, false).unwrap();
+        assert_eq!(code.durations[0..4], [889, 889, 1778, 889]);
+        assert_eq!(code.durations.len(), 26);
+        assert_eq!(code.durations.iter().sum::<u32>(), 113_778);
+        assert!(protocols::rc5(0x20, 0x00, false).is_err());
+    }
+
+    #[test]
+    fn nec_codes_are_decoded() {
+        // Round trip through the device format to get the timings of a learned code
+        let learned = BroadlinkCode::parse(&protocols::nec(0x04, 0x08).to_bytes()).unwrap();
+        assert_ne!(learned.durations, protocols::nec(0x04, 0x08).durations);
+        assert_eq!(
+            protocols::decode_nec(&learned),
+            Some(protocols::NecFrame { address: 0x04, command: 0x08 })
+        );
+
+        // A code in the format returned when learning, mimicking a capture with stretched marks,
+        // jittered timings, a long trailing gap and zero padding
+        let learned: [u8; 80] = [
+            0x26, 0x00, 0x48, 0x00, 0x00, 0x01, 0x27, 0x93, 0x13, 0x11, 0x13, 0x11, 0x12, 0x11, 0x13, 0x11,
+            0x12, 0x11, 0x12, 0x37, 0x13, 0x11, 0x12, 0x11, 0x13, 0x36, 0x12, 0x36, 0x12, 0x37, 0x12, 0x36,
+            0x12, 0x36, 0x13, 0x12, 0x12, 0x36, 0x13, 0x36, 0x12, 0x11, 0x13, 0x11, 0x12, 0x36, 0x12, 0x37,
+            0x13, 0x12, 0x13, 0x12, 0x13, 0x12, 0x12, 0x11, 0x12, 0x36, 0x13, 0x37, 0x13, 0x12, 0x13, 0x11,
+            0x12, 0x37, 0x12, 0x37, 0x12, 0x37, 0x13, 0x36, 0x12, 0x00, 0x0D, 0x05, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let learned = BroadlinkCode::parse(&learned).unwrap();
+        assert_eq!(
+            protocols::decode_nec(&learned),
+            Some(protocols::NecFrame { address: 0x20, command: 0x0C })
+        );
+
+        let extended = protocols::nec(0x1234, 0x56);
+        assert_eq!(protocols::decode_nec(&extended).unwrap().address, 0x1234);
+
+        let rc5 = protocols::rc5(0x04, 0x08, false).unwrap();
+        assert_eq!(protocols::decode_nec(&rc5), None);
+    }
+
+    #[test]
+    fn alarm_sensors_are_parsed_up_to_the_count() {
+        let record = |status: u8, order: u8, kind: u8, name: &str, serial: u32| {
+            let mut record = vec![0u8; 83];
+            record[0] = status;
+            record[1] = order;
+            record[3] = kind;
+            record[4..4 + name.len()].copy_from_slice(name.as_bytes());
+            record[26..30].copy_from_slice(&serial.to_le_bytes());
+
+            return record;
+        };
+
+        // Two sensors, followed by a stale record past the count. This frame is built by
+        // hand following the record layout, and is not a capture from a real kit.
+        let mut frame = vec![0x00, 0x00, 0x00, 0x00, 0x02, 0x00];
+        frame.extend(record(0x10, 0, 0x31, "Front door", 0x00A1B2C3));
+        frame.extend(record(0x00, 1, 0x21, "Hallway", 0x00D4E5F6));
+        frame.extend(record(0x00, 2, 0x91, "Stale", 0x00010203));
+
+        let sensors = parse_alarm_sensors(&frame).expect("Could not parse alarm sensors!");
+        assert_eq!(sensors.len(), 2);
+        assert_eq!(sensors[0].name, "Front door");
+        assert_eq!(sensors[0].kind, AlarmSensorKind::DoorSensor);
+        assert_eq!(sensors[0].status, 0x10);
+        assert_eq!(sensors[0].state, AlarmSensorState::Open);
+        assert_eq!(sensors[1].kind, AlarmSensorKind::MotionSensor);
+        assert_eq!(sensors[1].serial, 0x00D4E5F6);
+        assert_eq!(sensors[1].state, AlarmSensorState::Idle);
+
+        assert_eq!(
+            AlarmSensorState::from_status(AlarmSensorKind::DoorSensor, 0),
+            AlarmSensorState::Closed
+        );
+        assert_eq!(
+            AlarmSensorState::from_status(AlarmSensorKind::KeyFob, 0x20),
+            AlarmSensorState::Triggered
+        );
+        assert_eq!(
+            AlarmSensorState::from_status(AlarmSensorKind::Unknown(0x51), 0x20),
+            AlarmSensorState::Unknown(0x20)
+        );
+
+        assert!(parse_alarm_sensors(&frame[..100]).is_err());
+    }
+
+    #[test]
+    fn curtain_commands_pack_correctly() {
+        let packed = CurtainDataMessage::new(CurtainDataCommand::GetPosition)
+            .pack()
+            .expect("Could not pack curtain message!");
+        assert_eq!(packed, [0x09, 0, 0xBB, 0x06, 0x5D, 0, 0, 0, 0, 0xFA, 0x44, 0, 0, 0, 0, 0]);
+        assert_eq!(CurtainDataMessage::unpack_position(&[0, 0, 0, 0, 42]).unwrap(), 42);
+
+        let device = Device::from_parts(Ipv4Addr::new(10, 0, 0, 5), [0; 6], 1, [0; 16], 0x4E4D).unwrap();
+        let curtain = match device {
+            Device::Curtain { curtain } => curtain,
+            _ => panic!("Dooya model code should be a curtain motor!"),
+        };
+        let err = curtain.set_position(101).unwrap_err();
+        assert!(matches!(err, RbroadlinkError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn bulb_color_payload_is_framed() {
+        let packed = BulbDataMessage::color(255, 128, 0)
+            .pack()
+            .expect("Could not pack bulb message!");
+        let json = br#"{"blue":0,"bulb_colormode":0,"green":128,"red":255}"#;
+
+        assert_eq!(packed[..0x0E], [0x3F, 0x00, 0xA5, 0xA5, 0x5A, 0x5A, 0xB4, 0xD0, 0x02, 0x0B, 0x33, 0x00, 0x00, 0x00]);
+        assert_eq!(packed[0x0E..], json[..]);
+
+        assert_eq!(unpack_json_payload(&packed).unwrap()["red"], 255);
+
+        let state = serde_json::json!({
+            "pwr": 1, "brightness": 80, "red": 255, "green": 128, "blue": 0,
+            "colortemp": 2700, "bulb_colormode": 0,
+        });
+        let state = parse_bulb_state(&state).expect("Could not parse bulb state!");
+        assert!(state.power);
+        assert_eq!(state.brightness, 80);
+        assert!(parse_bulb_state(&serde_json::json!({ "pwr": 1 })).is_err());
+        assert!(BulbDataMessage::brightness(101).is_err());
+        assert!(BulbDataMessage::color_temperature(2000).is_err());
+    }
+
+    #[test]
+    fn wall_switch_gangs_are_validated() {
+        let packed = WallSwitchDataMessage::gang(1, true)
+            .pack()
+            .expect("Could not pack wall switch message!");
+        assert_eq!(unpack_json_payload(&packed).unwrap(), serde_json::json!({ "pwr2": 1 }));
+
+        // Wall switches seed their checksum with 0xBEAF from offset 0x02 (with the checksum
+        // zeroed), which is the same checksum that bulbs use
+        let mut zeroed = packed.clone();
+        zeroed[0x06..0x08].copy_from_slice(&[0, 0]);
+        let sum = zeroed[0x02..].iter().fold(0xBEAFu16, |sum, &byte| sum.wrapping_add(u16::from(byte)));
+        assert_eq!(packed[0x06..0x08], sum.to_le_bytes());
+
+        let state = serde_json::json!({ "pwr1": 1, "pwr2": 0, "ntlight": 1, "ntlbrightness": 30 });
+        let state = parse_wall_switch_state(&state).expect("Could not parse wall switch state!");
+        assert_eq!(state.gangs, [true, false]);
+        assert_eq!(state.night_light_brightness, Some(30));
+
+        let device = Device::from_parts(Ipv4Addr::new(10, 0, 0, 5), [0; 6], 1, [0; 16], 0x51E3).unwrap();
+        let switch = match device {
+            Device::WallSwitch { switch } => switch,
+            _ => panic!("BG1 model code should be a wall switch!"),
+        };
+        let err = switch.set_gang(2, true).unwrap_err();
+        assert!(matches!(err, RbroadlinkError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn device_lists_are_split_by_type() {
+        let addr = Ipv4Addr::new(10, 0, 0, 5);
+        let devices = vec![
+            fake_remote(addr, [1, 2, 3, 4, 5, 6]),
+            Device::from_parts(addr, [0; 6], 1, [0; 16], 0x2736).unwrap(),
+            fake_remote(addr, [6, 5, 4, 3, 2, 1]),
+        ];
+
+        assert_eq!(devices.remotes().count(), 2);
+        assert_eq!(devices.plugs().next().unwrap().info.model_code, 0x2736);
+        assert_eq!(devices.sensors().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn commands_roundtrip_through_a_mock_transport() {
+        let transport = Arc::new(MockTransport::new());
+        let device = fake_remote(Ipv4Addr::new(10, 0, 0, 5), [1, 2, 3, 4, 5, 6]).with_transport(transport.clone());
+        let response = [0x07u8; 16];
+
+        // A packed command has the same framing as a device's response
+        let packed = device.pack_command::<RemoteDataMessage>(&response, None).unwrap();
+        transport.push_response(packed.clone());
+        transport.push_response(packed);
+
+        let received = device
+            .send_command::<RemoteDataMessage>(&[0x04; 16])
+            .expect("Could not exchange command with mock transport!");
+        assert_eq!(received, response);
+
+        // Async commands go through the same transport
+        let received = device
+            .send_command_async::<RemoteDataMessage>(&[0x05; 16], std::time::Duration::from_secs(1))
+            .await
+            .expect("Could not exchange async command with mock transport!");
+        assert_eq!(received, response);
+
+        let sent = transport.sent();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].0, std::net::SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 5), 80));
+        assert_eq!(CommandMessage::unpack_with_payload(sent[0].1.clone(), &constants::INITIAL_KEY).unwrap(), [0x04; 16]);
+        assert_eq!(CommandMessage::unpack_with_payload(sent[1].1.clone(), &constants::INITIAL_KEY).unwrap(), [0x05; 16]);
+
+        let err = device
+            .send_command::<RemoteDataMessage>(&[0x04; 16])
+            .unwrap_err();
+        assert!(matches!(err, RbroadlinkError::Timeout(_)));
+    }
+
+    #[test]
+    fn device_methods_use_the_device_transport() {
+        let transport = Arc::new(MockTransport::new());
+        let device = Device::from_parts(Ipv4Addr::new(10, 0, 0, 6), [1, 2, 3, 4, 5, 6], 7, [0x42; 16], 0x2711)
+            .unwrap()
+            .with_transport(transport.clone());
+
+        // The plug reports its power in the state byte of its response
+        let mut response = [0u8; 16];
+        response[0x04] = 0x01;
+        transport.push_response(device.pack_command::<PlugDataMessage>(&response, None).unwrap());
+
+        let plug = match &device {
+            Device::Plug { plug } => plug,
+            _ => panic!("Device is not a plug!"),
+        };
+        assert!(plug.get_power().expect("Could not get power through mock transport!"));
+
+        let sent = transport.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, std::net::SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 6), 80));
+        let payload = CommandMessage::unpack_with_payload(sent[0].1.clone(), &[0x42; 16]).unwrap();
+        assert_eq!(payload[0], 0x01); // Get state
+
+        // Clones of the device keep using the same transport
+        assert!(matches!(device.clone().send_raw_packet(&[0x00]), Err(RbroadlinkError::Timeout(_))));
+        assert_eq!(transport.sent().len(), 2);
+    }
+
+    /// Sets the error code of a packed response, as sent by a device which failed a command.
+    fn with_error_code(mut packet: Vec<u8>, error_code: i16) -> Vec<u8> {
+        packet[0x22..0x24].copy_from_slice(&error_code.to_le_bytes());
+        packet[0x20..0x22].copy_from_slice(&[0, 0]);
+        let checksum = checksum(&packet);
+        packet[0x20..0x22].copy_from_slice(&checksum.to_le_bytes());
+
+        return packet;
+    }
+
+    #[test]
+    fn device_error_codes_are_reported() {
+        let key = [0x42u8; 16];
+        let device = Device::from_parts(Ipv4Addr::new(10, 0, 0, 5), [1, 2, 3, 4, 5, 6], 7, key, 0x649B).unwrap();
+        let packed = device.pack_command::<RemoteDataMessage>(&[0u8; 16], None).unwrap();
+
+        let err = CommandMessage::unpack_with_payload(with_error_code(packed, -10), &key).unwrap_err();
+        assert!(matches!(err, RbroadlinkError::DeviceError(-10)));
+
+        // Read and storage errors mean that nothing was captured yet, anything else is a failure
+        assert_eq!(parse_check_code(Err(RbroadlinkError::DeviceError(-10))).unwrap(), None);
+        assert_eq!(parse_check_code(Err(RbroadlinkError::DeviceError(-5).context("Wrapped"))).unwrap(), None);
+        assert_eq!(parse_check_code(Ok(vec![])).unwrap(), None);
+        assert_eq!(parse_check_code(Ok(vec![0x26])).unwrap(), Some(vec![0x26]));
+        assert!(parse_check_code(Err(RbroadlinkError::DeviceError(-4))).is_err());
+    }
+
+    #[test]
+    fn learning_distinguishes_nothing_captured_from_timeouts() {
+        let device = Device::from_parts(Ipv4Addr::new(10, 0, 0, 5), [1, 2, 3, 4, 5, 6], 7, [0x42; 16], 0x649B).unwrap();
+        let remote_with = |transport: &Arc<MockTransport>| match device.clone().with_transport(transport.clone()) {
+            Device::Remote { remote } => remote.with_learn_poll_interval(std::time::Duration::ZERO),
+            _ => panic!("Device is not a remote!"),
+        };
+        let ack = || device.pack_command::<RemoteDataMessage>(&[0u8; 16], None).unwrap();
+        let nothing_captured = || with_error_code(ack(), -10);
+
+        // The device answers without a code before capturing one
+        let transport = Arc::new(MockTransport::new());
+        let code = vec![0x26, 0x00, 0x02, 0x00, 0x11, 0x22];
+        let captured = RemoteDataMessage::new(RemoteDataCommand::GetCode).pack_with_payload(&code).unwrap();
+        transport.push_response(ack());
+        transport.push_response(nothing_captured());
+        transport.push_response(device.pack_command::<RemoteDataMessage>(&captured, None).unwrap());
+        let learned = remote_with(&transport).learn_ir_with_deadline(std::time::Duration::from_secs(5));
+        assert_eq!(learned.unwrap(), code);
+
+        // The device keeps answering, but never captures a code
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response(ack());
+        for _ in 0..8 {
+            transport.push_response(nothing_captured());
+        }
+        let err = remote_with(&transport)
+            .learn_ir_with_deadline(std::time::Duration::from_millis(600))
+            .unwrap_err();
+        assert!(matches!(err.root_cause(), RbroadlinkError::NothingLearned(_)));
+
+        // The device stops answering
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response(ack());
+        let err = remote_with(&transport)
+            .learn_ir_with_deadline(std::time::Duration::from_secs(5))
+            .unwrap_err();
+        assert!(matches!(err.root_cause(), RbroadlinkError::Timeout(_)));
+    }
+
+    #[tokio::test]
+    async fn async_learning_polls_until_a_code_is_captured() {
+        let device = Device::from_parts(Ipv4Addr::new(10, 0, 0, 5), [1, 2, 3, 4, 5, 6], 7, [0x42; 16], 0x649B).unwrap();
+        let remote_with = |transport: &Arc<MockTransport>| match device.clone().with_transport(transport.clone()) {
+            Device::Remote { remote } => remote.with_learn_poll_interval(std::time::Duration::ZERO),
+            _ => panic!("Device is not a remote!"),
+        };
+        let ack = || device.pack_command::<RemoteDataMessage>(&[0u8; 16], None).unwrap();
+
+        // The device answers without a code before capturing one
+        let transport = Arc::new(MockTransport::new());
+        let code = vec![0x26, 0x00, 0x02, 0x00, 0x11, 0x22];
+        let captured = RemoteDataMessage::new(RemoteDataCommand::GetCode).pack_with_payload(&code).unwrap();
+        transport.push_response(ack());
+        transport.push_response(with_error_code(ack(), -10));
+        transport.push_response(device.pack_command::<RemoteDataMessage>(&captured, None).unwrap());
+        let learned = remote_with(&transport).learn_ir_async(std::time::Duration::from_secs(5)).await;
+        assert_eq!(learned.unwrap(), code);
+        assert_eq!(transport.sent().len(), 3);
+
+        // The device keeps answering, but never captures a code
+        let transport = Arc::new(MockTransport::new());
+        transport.push_response(ack());
+        for _ in 0..8 {
+            transport.push_response(with_error_code(ack(), -10));
+        }
+        let err = remote_with(&transport)
+            .learn_ir_async(std::time::Duration::from_millis(600))
+            .await
+            .unwrap_err();
+        assert!(matches!(err.root_cause(), RbroadlinkError::NothingLearned(_)));
+    }
+
+    #[test]
+    fn learning_reports_its_progress() {
+        let transport = Arc::new(MockTransport::new());
+        let device = Device::from_parts(Ipv4Addr::new(10, 0, 0, 5), [1, 2, 3, 4, 5, 6], 7, [0x42; 16], 0x649B)
+            .unwrap()
+            .with_transport(transport.clone());
+        let remote = match &device {
+            Device::Remote { remote } => remote.clone().with_learn_poll_interval(std::time::Duration::ZERO),
+            _ => panic!("Device is not a remote!"),
+        };
+
+        // The device captures a code on the second check
+        let ack = device.pack_command::<RemoteDataMessage>(&[0u8; 16], None).unwrap();
+        let captured = RemoteDataMessage::new(RemoteDataCommand::GetCode).pack_with_payload(&[0x26, 0x00]).unwrap();
+        transport.push_response(ack.clone());
+        transport.push_response(with_error_code(ack, -10));
+        transport.push_response(device.pack_command::<RemoteDataMessage>(&captured, None).unwrap());
+
+        let mut statuses = vec![];
+        let code = remote
+            .learn_ir_with_status(std::time::Duration::from_secs(5), |status| statuses.push(status))
+            .expect("Could not learn code!");
+        assert_eq!(code, vec![0x26, 0x00]);
+        assert_eq!(
+            statuses,
+            vec![
+                LearnStatus::EnteringLearnMode,
+                LearnStatus::WaitingForSignal,
+                LearnStatus::WaitingForSignal,
+                LearnStatus::DataCaptured,
+            ]
+        );
+    }
+
+    #[test]
+    fn diagnostic_report_points_at_the_first_failed_step() {
+        let elapsed = std::time::Duration::from_millis(5);
+        let ok = DiagnosticStep::new(&Ok::<(), RbroadlinkError>(()), elapsed);
+        let failed = DiagnosticStep::new(&Err::<(), RbroadlinkError>(RbroadlinkError::AuthFailed), elapsed);
+        assert!(ok.succeeded());
+        assert!(!failed.succeeded());
+
+        let mut report = DiagnosticReport {
+            discovery: failed.clone(),
+            authentication: None,
+            status_query: None,
+        };
+        assert!(!report.succeeded());
+        assert_eq!(report.summary(), "No response at all (wrong IP or subnet?)");
+
+        report.discovery = ok.clone();
+        report.authentication = Some(failed.clone());
+        assert_eq!(report.summary(), "Device reachable, but authentication failed (locked?)");
+        assert_eq!(
+            report.to_string(),
+            "Device reachable, but authentication failed (locked?)\n  discovery: ok (5ms)\n  \
+             authentication: failed after 5ms: Device rejected the authentication handshake!\n  status query: skipped\n"
+        );
+
+        report.authentication = Some(ok.clone());
+        report.status_query = Some(failed);
+        assert_eq!(report.summary(), "Device authenticated, but did not answer a status query");
+
+        report.status_query = Some(ok);
+        assert!(report.succeeded());
+        assert_eq!(report.summary(), "Device is working correctly");
+    }
+
+    #[test]
+    fn remote_status_query_does_not_check_for_learned_codes() {
+        let transport = Arc::new(MockTransport::new());
+        let device = Device::from_parts(Ipv4Addr::new(10, 0, 0, 5), [1, 2, 3, 4, 5, 6], 7, [0x42; 16], 0x649B)
+            .unwrap()
+            .with_transport(transport.clone());
+        transport.push_response(device.pack_command::<FirmwareVersionMessage>(&[0u8; 16], None).unwrap());
+        assert!(!device.is_locked().unwrap());
+
+        // The firmware version query is sent instead of reading the learn buffer
+        let sent = transport.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(u16::from_le_bytes([sent[0].1[0x26], sent[0].1[0x27]]), 0x0068);
+    }
+
+    #[test]
+    fn curtain_is_stopped_when_polling_fails() {
+        let transport = Arc::new(MockTransport::new());
+        let device = Device::from_parts(Ipv4Addr::new(10, 0, 0, 7), [1, 2, 3, 4, 5, 6], 7, [0x42; 16], 0x4E4D)
+            .unwrap()
+            .with_transport(transport.clone());
+        let curtain = match &device {
+            Device::Curtain { curtain } => curtain,
+            _ => panic!("Device is not a curtain motor!"),
+        };
+
+        // The curtain starts closed and accepts the open command, but the first poll is lost
+        let mut position = [0u8; 16];
+        position[0x04] = 10;
+        transport.push_response(device.pack_command::<CurtainDataMessage>(&position, None).unwrap());
+        transport.push_response(device.pack_command::<CurtainDataMessage>(&[0u8; 16], None).unwrap());
+
+        let err = curtain.set_position(50).unwrap_err();
+        assert!(matches!(err.root_cause(), RbroadlinkError::Timeout(_)));
+
+        let commands: Vec<u8> = transport
+            .sent()
+            .into_iter()
+            .map(|(_, packet)| CommandMessage::unpack_with_payload(packet, &[0x42; 16]).unwrap()[0x03])
+            .collect();
+        let (get_position, open, stop) = (
+            CurtainDataCommand::GetPosition as u8,
+            CurtainDataCommand::Open as u8,
+            CurtainDataCommand::Stop as u8,
+        );
+        assert_eq!(commands, vec![get_position, open, get_position, stop]);
+    }
+
+    #[test]
+    fn routable_ipv4_address_is_preferred() {
+        let addrs: Vec<std::net::IpAddr> = ["::1", "fe80::1", "127.0.0.1", "169.254.3.4", "2001:db8::1", "192.168.1.20"]
+            .iter()
+            .map(|x| x.parse().unwrap())
+            .collect();
+        assert_eq!(select_local_ipv4(&addrs), Some(Ipv4Addr::new(192, 168, 1, 20)));
+
+        // Link-local addresses are only used as a last resort
+        assert_eq!(select_local_ipv4(&addrs[..4]), Some(Ipv4Addr::new(169, 254, 3, 4)));
+        assert_eq!(select_local_ipv4(&addrs[..3]), None);
+    }
 }
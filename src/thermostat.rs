@@ -0,0 +1,242 @@
+use std::net::Ipv4Addr;
+
+use chrono::{Datelike, Local, Timelike, Weekday};
+use phf::phf_map;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    network::{
+        DiscoveryResponse, ThermostatDataMessage, THERMOSTAT_STATUS_QUERY,
+    },
+    Device, DeviceInfo, RbroadlinkError,
+};
+
+/// A mapping of thermostat device codes to their friendly model equivalent.
+pub const THERMOSTAT_CODES: phf::Map<u16, &'static str> = phf_map! {
+    0x4EADu16 => "HY02/HY03",
+};
+
+/// The mode in which a thermostat chooses its target temperature.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ThermostatMode {
+    /// The target temperature is set manually.
+    Manual,
+
+    /// The target temperature follows the thermostat's schedule.
+    Auto,
+}
+
+/// The current status of a thermostat.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ThermostatStatus {
+    /// Whether the thermostat is powered on.
+    pub power: bool,
+
+    /// Whether the thermostat is currently heating.
+    pub active: bool,
+
+    /// The mode in which the target temperature is chosen.
+    pub mode: ThermostatMode,
+
+    /// The current room temperature, in degrees Celsius.
+    pub current_temperature: f32,
+
+    /// The target temperature, in degrees Celsius.
+    pub target_temperature: f32,
+}
+
+/// The time of a thermostat's clock, which drives its schedule.
+///
+/// Note: The thermostat only keeps the time of the week, not the date.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceTime {
+    /// The hour, from 0 to 23.
+    pub hour: u8,
+
+    /// The minute, from 0 to 59.
+    pub minute: u8,
+
+    /// The second, from 0 to 59.
+    pub second: u8,
+
+    /// The day of the week.
+    pub weekday: Weekday,
+}
+
+/// A broadlink (Hysen) floor-heating thermostat.
+#[derive(Debug, Clone)]
+pub struct Thermostat {
+    /// Base information about the thermostat.
+    pub info: DeviceInfo,
+}
+
+impl Thermostat {
+    /// Create a new Thermostat.
+    ///
+    /// Note: This should not be called directly. Please use [Device::from_ip] or
+    /// [Device::list] instead.
+    pub fn new(name: &str, addr: Ipv4Addr, response: DiscoveryResponse) -> Thermostat {
+        // Get the type of thermostat
+        let friendly_model: String = THERMOSTAT_CODES
+            .get(&response.model_code)
+            .unwrap_or(&"Unknown")
+            .to_string();
+
+        return Self {
+            info: DeviceInfo::from_response(name, addr, &response, "Thermostat", &friendly_model),
+        };
+    }
+
+    /// Get the current status of the thermostat.
+    pub fn get_status(&self) -> Result<ThermostatStatus, RbroadlinkError> {
+        let response = self
+            .send_request(&THERMOSTAT_STATUS_QUERY)
+            .map_err(|e| e.context("Could not get status of thermostat!"))?;
+
+        return parse_thermostat_status(&response);
+    }
+
+    /// Set the target temperature of the thermostat, in degrees Celsius.
+    ///
+    /// Note: The thermostat only supports steps of half a degree.
+    pub fn set_target_temperature(&self, celsius: f32) -> Result<(), RbroadlinkError> {
+        if !(0.0..=99.0).contains(&celsius) {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Target temperature must be between 0 and 99 degrees! Got {}",
+                celsius
+            )));
+        }
+
+        let request = ThermostatDataMessage::target_temperature_request((celsius * 2.0) as u8);
+        self.send_request(&request)
+            .map_err(|e| e.context("Could not set target temperature of thermostat!"))?;
+
+        return Ok(());
+    }
+
+    /// Get the current time of the thermostat's clock.
+    pub fn get_time(&self) -> Result<DeviceTime, RbroadlinkError> {
+        let response = self
+            .send_request(&THERMOSTAT_STATUS_QUERY)
+            .map_err(|e| e.context("Could not get time of thermostat!"))?;
+
+        return parse_thermostat_time(&response);
+    }
+
+    /// Set the thermostat's clock.
+    pub fn set_time(&self, time: &DeviceTime) -> Result<(), RbroadlinkError> {
+        if time.hour > 23 || time.minute > 59 || time.second > 59 {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Invalid time! Got {:02}:{:02}:{:02}",
+                time.hour, time.minute, time.second
+            )));
+        }
+
+        let weekday = time.weekday.number_from_monday() as u8;
+        let request = ThermostatDataMessage::time_request(time.hour, time.minute, time.second, weekday);
+        self.send_request(&request)
+            .map_err(|e| e.context("Could not set time of thermostat!"))?;
+
+        return Ok(());
+    }
+
+    /// Set the thermostat's clock to the local time of this machine.
+    pub fn sync_time(&self) -> Result<(), RbroadlinkError> {
+        let now = Local::now();
+
+        return self.set_time(&DeviceTime {
+            hour: now.hour() as u8,
+            minute: now.minute() as u8,
+            second: now.second() as u8,
+            weekday: now.weekday(),
+        });
+    }
+
+    /// Sends a raw Modbus request to the thermostat, returning the Modbus response.
+    /// Note: Try to avoid using this method in favor of [Thermostat::get_status], etc.
+    pub fn send_request(&self, request: &[u8]) -> Result<Vec<u8>, RbroadlinkError> {
+        // We cast this object to a generic device in order to make use of the shared
+        // helper utilities.
+        let generic_device = Device::Thermostat {
+            thermostat: self.clone(),
+        };
+
+        // Construct the data message
+        let payload = ThermostatDataMessage::new()
+            .pack_with_payload(request)
+            .map_err(|e| e.context("Could not pack thermostat data message!"))?;
+
+        let response = generic_device
+            .send_command::<ThermostatDataMessage>(&payload)
+            .map_err(|e| e.context("Could not send command!"))?;
+
+        return ThermostatDataMessage::unpack_with_payload(&response);
+    }
+}
+
+/// Parses the Modbus response to a status query into a [ThermostatStatus].
+pub(crate) fn parse_thermostat_status(data: &[u8]) -> Result<ThermostatStatus, RbroadlinkError> {
+    if data.len() < 8 {
+        return Err(RbroadlinkError::Decode(format!(
+            "Thermostat status is too short! Expected at least 8 bytes, got {}",
+            data.len()
+        )));
+    }
+
+    let mode = if data[0x07] & 0x0F == 0x01 {
+        ThermostatMode::Auto
+    } else {
+        ThermostatMode::Manual
+    };
+
+    return Ok(ThermostatStatus {
+        power: data[0x04] & 0x01 != 0,
+        active: (data[0x04] >> 4) & 0x01 != 0,
+        mode,
+        current_temperature: f32::from(data[0x05]) / 2.0,
+        target_temperature: f32::from(data[0x06]) / 2.0,
+    });
+}
+
+/// The days of the week, in the order in which they are counted by a thermostat.
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Parses the Modbus response to a status query into the time of the thermostat's clock.
+pub(crate) fn parse_thermostat_time(data: &[u8]) -> Result<DeviceTime, RbroadlinkError> {
+    if data.len() < 23 {
+        return Err(RbroadlinkError::Decode(format!(
+            "Thermostat status is too short! Expected at least 23 bytes, got {}",
+            data.len()
+        )));
+    }
+
+    // The day of the week is counted from Monday (1) to Sunday (7)
+    let weekday = match data[0x16] {
+        day @ 1..=7 => WEEKDAYS[usize::from(day - 1)],
+        day => {
+            return Err(RbroadlinkError::Decode(format!(
+                "Invalid day of the week! Got {}",
+                day
+            )))
+        }
+    };
+
+    return Ok(DeviceTime {
+        hour: data[0x13],
+        minute: data[0x14],
+        second: data[0x15],
+        weekday,
+    });
+}
@@ -0,0 +1,110 @@
+use crate::{
+    A1Sensor, AlarmKit, CurtainMotor, Device, HvacDevice, PowerStrip, RemoteDevice, SmartBulb,
+    SmartPlug, Thermostat, WallSwitch,
+};
+
+/// Helpers for picking devices of a specific type out of a list of devices, such as the
+/// result of [Device::list].
+pub trait DeviceListTrait {
+    /// Get all of the remotes in the list.
+    fn remotes(&self) -> impl Iterator<Item = &RemoteDevice>;
+
+    /// Get all of the air conditioners in the list.
+    fn hvacs(&self) -> impl Iterator<Item = &HvacDevice>;
+
+    /// Get all of the smart plugs in the list.
+    fn plugs(&self) -> impl Iterator<Item = &SmartPlug>;
+
+    /// Get all of the power strips in the list.
+    fn power_strips(&self) -> impl Iterator<Item = &PowerStrip>;
+
+    /// Get all of the environment sensors in the list.
+    fn sensors(&self) -> impl Iterator<Item = &A1Sensor>;
+
+    /// Get all of the thermostats in the list.
+    fn thermostats(&self) -> impl Iterator<Item = &Thermostat>;
+
+    /// Get all of the alarm kits in the list.
+    fn alarms(&self) -> impl Iterator<Item = &AlarmKit>;
+
+    /// Get all of the curtain motors in the list.
+    fn curtains(&self) -> impl Iterator<Item = &CurtainMotor>;
+
+    /// Get all of the smart bulbs in the list.
+    fn bulbs(&self) -> impl Iterator<Item = &SmartBulb>;
+
+    /// Get all of the wall switches in the list.
+    fn wall_switches(&self) -> impl Iterator<Item = &WallSwitch>;
+}
+
+impl DeviceListTrait for [Device] {
+    fn remotes(&self) -> impl Iterator<Item = &RemoteDevice> {
+        return self.iter().filter_map(|device| match device {
+            Device::Remote { remote } => Some(remote),
+            _ => None,
+        });
+    }
+
+    fn hvacs(&self) -> impl Iterator<Item = &HvacDevice> {
+        return self.iter().filter_map(|device| match device {
+            Device::Hvac { hvac } => Some(hvac),
+            _ => None,
+        });
+    }
+
+    fn plugs(&self) -> impl Iterator<Item = &SmartPlug> {
+        return self.iter().filter_map(|device| match device {
+            Device::Plug { plug } => Some(plug),
+            _ => None,
+        });
+    }
+
+    fn power_strips(&self) -> impl Iterator<Item = &PowerStrip> {
+        return self.iter().filter_map(|device| match device {
+            Device::PowerStrip { strip } => Some(strip),
+            _ => None,
+        });
+    }
+
+    fn sensors(&self) -> impl Iterator<Item = &A1Sensor> {
+        return self.iter().filter_map(|device| match device {
+            Device::Sensor { sensor } => Some(sensor),
+            _ => None,
+        });
+    }
+
+    fn thermostats(&self) -> impl Iterator<Item = &Thermostat> {
+        return self.iter().filter_map(|device| match device {
+            Device::Thermostat { thermostat } => Some(thermostat),
+            _ => None,
+        });
+    }
+
+    fn alarms(&self) -> impl Iterator<Item = &AlarmKit> {
+        return self.iter().filter_map(|device| match device {
+            Device::Alarm { alarm } => Some(alarm),
+            _ => None,
+        });
+    }
+
+    fn curtains(&self) -> impl Iterator<Item = &CurtainMotor> {
+        return self.iter().filter_map(|device| match device {
+            Device::Curtain { curtain } => Some(curtain),
+            _ => None,
+        });
+    }
+
+    fn bulbs(&self) -> impl Iterator<Item = &SmartBulb> {
+        return self.iter().filter_map(|device| match device {
+            Device::Bulb { bulb } => Some(bulb),
+            _ => None,
+        });
+    }
+
+    fn wall_switches(&self) -> impl Iterator<Item = &WallSwitch> {
+        return self.iter().filter_map(|device| match device {
+            Device::WallSwitch { switch } => Some(switch),
+            _ => None,
+        });
+    }
+}
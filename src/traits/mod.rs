@@ -1,7 +1,11 @@
 //! Traits shared amongst the various structures.
 
 mod command_trait;
+mod device_list_trait;
 mod device_trait;
+mod transport_trait;
 
 pub use command_trait::*;
+pub use device_list_trait::*;
 pub use device_trait::*;
+pub use transport_trait::*;
@@ -0,0 +1,26 @@
+use std::{future::Future, net::SocketAddrV4, pin::Pin, time::Duration};
+
+use crate::RbroadlinkError;
+
+/// A way of exchanging packets with a broadlink device.
+///
+/// See [crate::network::UdpTransport] for the transport used by default.
+pub trait TransportTrait {
+    /// Sends a packet to a device and returns the first response, waiting at most the
+    /// specified amount of time.
+    fn send_and_receive(
+        &self,
+        packet: &[u8],
+        addr: SocketAddrV4,
+        read_timeout: Duration,
+    ) -> Result<Vec<u8>, RbroadlinkError>;
+
+    /// Sends a packet to a device and returns the first response without blocking, waiting
+    /// at most the specified amount of time.
+    fn send_and_receive_async<'a>(
+        &'a self,
+        packet: &'a [u8],
+        addr: SocketAddrV4,
+        read_timeout: Duration,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, RbroadlinkError>> + Send + 'a>>;
+}
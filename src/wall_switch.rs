@@ -0,0 +1,119 @@
+use std::net::Ipv4Addr;
+
+use phf::phf_map;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    network::{
+        unpack_json_payload,
+        DiscoveryResponse, WallSwitchDataMessage,
+    },
+    Device, DeviceInfo, RbroadlinkError,
+};
+
+/// A mapping of wall switch device codes to their friendly model equivalent.
+pub const WALL_SWITCH_CODES: phf::Map<u16, &'static str> = phf_map! {
+    0x51E3u16 => "BG1",
+};
+
+/// The amount of independently switchable gangs on a wall switch.
+pub const WALL_SWITCH_GANGS: u8 = 2;
+
+/// The state of a wall switch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WallSwitchState {
+    /// Whether each of the gangs is on.
+    pub gangs: [bool; 2],
+
+    /// Whether the night light is on, if the switch reports it.
+    pub night_light: Option<bool>,
+
+    /// The brightness of the night light, as a percentage, if the switch reports it.
+    pub night_light_brightness: Option<u8>,
+}
+
+/// A broadlink BG1 two-gang wall switch.
+#[derive(Debug, Clone)]
+pub struct WallSwitch {
+    /// Base information about the switch.
+    pub info: DeviceInfo,
+}
+
+impl WallSwitch {
+    /// Create a new WallSwitch.
+    ///
+    /// Note: This should not be called directly. Please use [Device::from_ip] or
+    /// [Device::list] instead.
+    pub fn new(name: &str, addr: Ipv4Addr, response: DiscoveryResponse) -> WallSwitch {
+        // Get the type of switch
+        let friendly_model: String = WALL_SWITCH_CODES
+            .get(&response.model_code)
+            .unwrap_or(&"Unknown")
+            .to_string();
+
+        return Self {
+            info: DeviceInfo::from_response(name, addr, &response, "WallSwitch", &friendly_model),
+        };
+    }
+
+    /// Turn a single gang on or off. Gangs are indexed from 0.
+    pub fn set_gang(&self, index: u8, on: bool) -> Result<(), RbroadlinkError> {
+        if index >= WALL_SWITCH_GANGS {
+            return Err(RbroadlinkError::InvalidInput(format!(
+                "Gang index must be less than {}! Got {}",
+                WALL_SWITCH_GANGS, index
+            )));
+        }
+
+        self.send_command(WallSwitchDataMessage::gang(index, on))
+            .map_err(|e| e.context("Could not set gang state of wall switch!"))?;
+
+        return Ok(());
+    }
+
+    /// Get the current state of the switch.
+    pub fn get_state(&self) -> Result<WallSwitchState, RbroadlinkError> {
+        let response = self
+            .send_command(WallSwitchDataMessage::query())
+            .map_err(|e| e.context("Could not get state of wall switch!"))?;
+
+        return parse_wall_switch_state(&unpack_json_payload(&response)?);
+    }
+
+    /// Sends a raw command to the switch.
+    /// Note: Try to avoid using this method in favor of [WallSwitch::set_gang], [WallSwitch::get_state], etc.
+    pub fn send_command(&self, msg: WallSwitchDataMessage) -> Result<Vec<u8>, RbroadlinkError> {
+        // We cast this object to a generic device in order to make use of the shared
+        // helper utilities.
+        let generic_device = Device::WallSwitch {
+            switch: self.clone(),
+        };
+
+        let packed = msg.pack()?;
+
+        return generic_device
+            .send_command::<WallSwitchDataMessage>(&packed)
+            .map_err(|e| e.context("Could not send command!"));
+    }
+}
+
+/// Parses the JSON state reported by a wall switch into a [WallSwitchState].
+pub(crate) fn parse_wall_switch_state(state: &Value) -> Result<WallSwitchState, RbroadlinkError> {
+    let gang = |key: &str| {
+        return state.get(key).and_then(Value::as_u64).map(|x| x != 0).ok_or_else(|| {
+            RbroadlinkError::Decode(format!("Wall switch state is missing a valid '{}' value!", key))
+        });
+    };
+
+    return Ok(WallSwitchState {
+        gangs: [gang("pwr1")?, gang("pwr2")?],
+        night_light: state.get("ntlight").and_then(Value::as_u64).map(|x| x != 0),
+        night_light_brightness: state
+            .get("ntlbrightness")
+            .and_then(Value::as_u64)
+            .map(|x| x.min(100) as u8),
+    });
+}